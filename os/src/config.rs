@@ -19,20 +19,68 @@ pub const TASK_QUEUE_FCFS2_SLICE_MS: usize = 20;
 /// 第三级队列时间片
 pub const TASK_QUEUE_RR_SLICE_MS: usize = 50;
 
+/// 第一级队列的时间配额，任务累计运行超过后降级
+pub const TASK_QUEUE_FCFS1_ALLOTMENT_MS: usize = 20;
+
+/// 第二级队列的时间配额
+pub const TASK_QUEUE_FCFS2_ALLOTMENT_MS: usize = 80;
+
+/// 多级反馈队列将所有任务提升到最高级的周期
+pub const MLFQ_BOOST_INTERVAL_MS: usize = 1000;
+
+/// 时间片轮转与步长调度的时间片
+pub const SCHED_SLICE_MS: usize = 10;
+
+/// CFS调度周期，就绪任务按权重分享
+pub const SCHED_LATENCY_MS: usize = 20;
+
+/// CFS最短时间片
+pub const SCHED_MIN_GRANULARITY_MS: usize = 4;
+
+/// 最多支持的核数，核号不小于此值的核在入口处停机
+pub const MAX_HARTS: usize = 4;
+
+/// 64K启动栈空间，每个核一个（之后作为该核空闲任务的栈）
+pub const BOOT_STACK_SIZE: usize = 4096 * 16;
+
 /// QEMU时钟频率
 pub const CLOCK_FREQ: usize = 10000000;
 
 /// 8K用户栈空间
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 
+/// exec时参数与环境变量字符串的总长度上限（放置在用户栈上）
+pub const ARG_MAX: usize = 4096;
+
 /// 8K内核栈空间
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 
 /// 跳板地址
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 
-/// 用户空间上下文地址
+/// 主线程用户空间上下文地址，其余线程的上下文依次向下排列
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
-/// VirtIO总线地址区间
-pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x1000)];
+/// 单个进程的最大线程数
+pub const MAX_THREAD_NUM: usize = 64;
+
+/// 主线程用户栈顶，其余线程的用户栈依次向下排列（之间有一页保护页）
+pub const USER_STACK_TOP: usize = TRAP_CONTEXT - MAX_THREAD_NUM * PAGE_SIZE;
+
+/// mmap区域起始地址
+pub const MMAP_BASE: usize = 0x20_0000_0000;
+
+/// mmap区域结束地址（SV39低半部分地址空间的上界）
+pub const MMAP_TOP: usize = 0x40_0000_0000;
+
+/// 交换空间最大页数（1M）
+pub const SWAP_PAGE_LIMIT: usize = 256;
+
+/// tmpfs最大页数（4M）
+pub const TMPFS_PAGE_LIMIT: usize = 1024;
+
+/// PLIC寄存器基址
+pub const PLIC_BASE: usize = 0x0c00_0000;
+
+/// 设备寄存器地址区间：VirtIO总线、PLIC
+pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x1000), (PLIC_BASE, 0x40_0000)];
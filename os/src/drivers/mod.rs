@@ -1,32 +1,82 @@
 //! 驱动程序子模块
 
+use crate::config::MAX_HARTS;
 use crate::memory::frame::address::*;
 use crate::memory::frame::frame_allocator::*;
-use crate::memory::frame::memory_set::KERNEL_MEMORY_SET;
+use crate::memory::frame::memory_set::kernel_token;
+use crate::memory::frame::page_table::PageTable;
 use alloc::vec::Vec;
+pub mod plic;
 pub mod virtio_block;
 use crate::fs::rfs::block_dev::BlockDevice;
+use crate::smp::hart_id;
+use crate::sync::SpinLock;
+use crate::task::try_get_current_task;
 use crate::tools::uninit_cell::UninitCell;
-use alloc::rc::Rc;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use virtio_block::VirtIOBlock;
 
-static mut QUEUE_FRAMES: Vec<FrameTracker> = Vec::new();
+static QUEUE_FRAMES: SpinLock<Vec<FrameTracker>> = SpinLock::new(Vec::new());
 
-pub static mut BLOCK_DEVICE: UninitCell<Rc<dyn BlockDevice>> = UninitCell::uninit();
+pub static mut BLOCK_DEVICE: UninitCell<Arc<dyn BlockDevice>> = UninitCell::uninit();
 
+/// 块设备实例，用于分发完成中断
+static mut VIRTIO_BLOCK: UninitCell<Arc<VirtIOBlock>> = UninitCell::uninit();
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_SLEEP: AtomicUsize = AtomicUsize::new(0);
+
+/// 各核上块设备请求不能阻塞当前线程的嵌套层数
+/// 期间不会发生调度，只有本核会修改
+static NO_SLEEP_DEPTH: [AtomicUsize; MAX_HARTS] = [NO_SLEEP; MAX_HARTS];
+
+/// 执行f期间块设备请求轮询等待完成而不阻塞当前线程
+/// 用于持有不能跨越调度的借用时访问磁盘，如页面置换
+pub fn without_sleep<T>(f: impl FnOnce() -> T) -> T {
+    let depth = &NO_SLEEP_DEPTH[hart_id()];
+    depth.fetch_add(1, Ordering::Relaxed);
+    let ret = f();
+    depth.fetch_sub(1, Ordering::Relaxed);
+    ret
+}
+
+/// 块设备请求能否阻塞当前线程等待完成中断
+fn can_sleep() -> bool {
+    try_get_current_task().is_some() && NO_SLEEP_DEPTH[hart_id()].load(Ordering::Relaxed) == 0
+}
+
+/// 处理当前核能认领到的所有外部中断
+pub fn handle_irqs() {
+    while let Some(irq) = plic::claim() {
+        match irq {
+            plic::VIRTIO_BLOCK_IRQ => unsafe { VIRTIO_BLOCK.handle_irq() },
+            _ => println!("[kernel] Unknown external interrupt {}", irq),
+        }
+        plic::complete(irq);
+    }
+}
+
+/// 分配连续的物理页供设备DMA使用，物理页不足或不连续时返回空地址，由驱动报告DmaError
 #[no_mangle]
 pub extern "C" fn virtio_dma_alloc(pages: usize) -> PhysAddr {
-    let mut ppn_base = PhysPageNum(0);
+    let mut frames: Vec<FrameTracker> = Vec::with_capacity(pages);
     for i in 0..pages {
-        let frame = frame_alloc().unwrap();
-        if i == 0 {
-            ppn_base = frame.ppn();
-        }
-        assert_eq!(frame.ppn().0, ppn_base.0 + i);
-        unsafe {
-            QUEUE_FRAMES.push(frame);
+        let frame = match frame_alloc() {
+            Some(frame) => frame,
+            // 已分配的物理页随frames归还
+            None => return PhysAddr(0),
+        };
+        if i > 0 && frame.ppn().0 != frames[0].ppn().0 + i {
+            return PhysAddr(0);
         }
+        frames.push(frame);
     }
+    let ppn_base = match frames.first() {
+        Some(frame) => frame.ppn(),
+        None => return PhysAddr(0),
+    };
+    QUEUE_FRAMES.lock().extend(frames);
     ppn_base.addr()
 }
 
@@ -34,9 +84,9 @@ pub extern "C" fn virtio_dma_alloc(pages: usize) -> PhysAddr {
 pub extern "C" fn virtio_dma_dealloc(pa: PhysAddr, pages: usize) -> i32 {
     let start_ppn = pa.ppn();
     let end_ppn = PhysPageNum(pa.ppn().0 + pages);
-    unsafe {
-        QUEUE_FRAMES.retain(|frame| !(start_ppn..end_ppn).contains(&frame.ppn()));
-    }
+    QUEUE_FRAMES
+        .lock()
+        .retain(|frame| !(start_ppn..end_ppn).contains(&frame.ppn()));
     0
 }
 
@@ -48,13 +98,25 @@ pub extern "C" fn virtio_phys_to_virt(paddr: PhysAddr) -> VirtAddr {
 #[no_mangle]
 pub extern "C" fn virtio_virt_to_phys(vaddr: VirtAddr) -> PhysAddr {
     let vpn = vaddr.vpn();
-    let ppn = unsafe { KERNEL_MEMORY_SET.translate(vpn).unwrap() };
+    // 不对内核地址空间加锁：分配内核栈时可能换出页面而发起块设备请求
+    let ppn = PageTable::from_token(kernel_token())
+        .translate(vpn)
+        .unwrap();
     PhysAddr(ppn.addr().0 + vaddr.page_offset())
 }
 
+/// 初始化设备，并将外部中断路由到当前核
 pub fn init() {
     unsafe {
-        BLOCK_DEVICE = UninitCell::init(Rc::new(VirtIOBlock::new()));
+        let virtio_block = Arc::new(VirtIOBlock::new());
+        BLOCK_DEVICE = UninitCell::init(virtio_block.clone());
+        VIRTIO_BLOCK = UninitCell::init(virtio_block);
     }
+    plic::init_hart();
     println!("mod drivers initialized!");
 }
+
+/// 将外部中断路由到其余核
+pub fn init_hart() {
+    plic::init_hart();
+}
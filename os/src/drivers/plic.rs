@@ -0,0 +1,58 @@
+//! PLIC平台级中断控制器驱动
+//!
+//! 外部设备的中断经PLIC路由到各核的S态上下文，在中断处理程序中认领并分发给设备驱动
+use crate::config::PLIC_BASE;
+use crate::smp::hart_id;
+
+/// virtio块设备的中断号（qemu virt平台）
+pub const VIRTIO_BLOCK_IRQ: usize = 1;
+
+/// 中断源优先级寄存器
+fn priority(irq: usize) -> *mut u32 {
+    (PLIC_BASE + 4 * irq) as *mut u32
+}
+
+/// 上下文的中断使能寄存器
+fn enable(context: usize) -> *mut u32 {
+    (PLIC_BASE + 0x2000 + 0x80 * context) as *mut u32
+}
+
+/// 上下文的优先级阈值寄存器
+fn threshold(context: usize) -> *mut u32 {
+    (PLIC_BASE + 0x20_0000 + 0x1000 * context) as *mut u32
+}
+
+/// 上下文的认领/完成寄存器
+fn claim_complete(context: usize) -> *mut u32 {
+    (PLIC_BASE + 0x20_0004 + 0x1000 * context) as *mut u32
+}
+
+/// 当前核S态对应的上下文，每个核依次有M态与S态两个上下文
+fn local_context() -> usize {
+    hart_id() * 2 + 1
+}
+
+/// 认领一个挂起的外部中断，没有时返回None
+pub fn claim() -> Option<usize> {
+    let irq = unsafe { claim_complete(local_context()).read_volatile() } as usize;
+    (irq != 0).then(|| irq)
+}
+
+/// 通知PLIC该中断已处理完毕
+pub fn complete(irq: usize) {
+    unsafe {
+        claim_complete(local_context()).write_volatile(irq as u32);
+    }
+}
+
+/// 初始化当前核：使能设备中断并开启S态外部中断
+pub fn init_hart() {
+    let context = local_context();
+    unsafe {
+        priority(VIRTIO_BLOCK_IRQ).write_volatile(1);
+        enable(context).write_volatile(1 << VIRTIO_BLOCK_IRQ);
+        threshold(context).write_volatile(0);
+        // set SEIE bit
+        core::arch::asm!("csrs sie, {}", in(reg) 1 << 9);
+    }
+}
@@ -1,33 +1,94 @@
 //! VirtIO块设备驱动
+//!
+//! 请求提交后阻塞当前线程，由完成中断唤醒；启动阶段等不能阻塞时轮询等待
+use super::can_sleep;
 use crate::config::MMIO;
 use crate::fs::rfs::block_dev::BlockDevice;
-use core::cell::RefCell;
-use core::marker::Sync;
-use virtio_drivers::{VirtIOBlk, VirtIOHeader};
+use crate::sync::SpinLock;
+use crate::task::{block_current_and_run_next, get_current_task, wakeup_task, ThreadControlBlock};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use core::marker::{Send, Sync};
+use virtio_drivers::{BlkResp, RespStatus, VirtIOBlk, VirtIOHeader};
 
-pub struct VirtIOBlock(RefCell<VirtIOBlk<'static>>);
+pub struct VirtIOBlock {
+    blk: SpinLock<VirtIOBlk<'static>>,
+    /// 等待请求完成的线程，以请求的token为键
+    waiting: SpinLock<BTreeMap<u16, Arc<ThreadControlBlock>>>,
+    /// 已完成但尚未被请求者取走的请求
+    completed: SpinLock<BTreeSet<u16>>,
+}
 
 impl VirtIOBlock {
     pub fn new() -> Self {
-        Self(RefCell::new(
-            VirtIOBlk::new(unsafe { &mut *(MMIO[0].0 as *mut VirtIOHeader) }).unwrap(),
-        ))
+        Self {
+            blk: SpinLock::new(
+                VirtIOBlk::new(unsafe { &mut *(MMIO[0].0 as *mut VirtIOHeader) })
+                    .expect("[kernel] Failed to initialize virtio block device!"),
+            ),
+            waiting: SpinLock::new(BTreeMap::new()),
+            completed: SpinLock::new(BTreeSet::new()),
+        }
+    }
+
+    /// 等待请求完成：可以阻塞时由完成中断唤醒，否则轮询设备（阻塞前不持有任何锁）
+    fn wait_for(&self, token: u16) {
+        if !can_sleep() {
+            while !self.completed.lock().remove(&token) {
+                self.pop_completed();
+            }
+            return;
+        }
+        let task = get_current_task();
+        // 被信号等提前唤醒时继续等待，缓冲区在请求完成前不能释放
+        while !self.completed.lock().remove(&token) {
+            self.waiting.lock().insert(token, task.clone());
+            block_current_and_run_next();
+        }
+    }
+
+    /// 取走所有已完成的请求并唤醒等待者
+    fn pop_completed(&self) {
+        let mut blk = self.blk.lock();
+        while let Ok(token) = blk.pop_used() {
+            self.completed.lock().insert(token);
+            if let Some(task) = self.waiting.lock().remove(&token) {
+                wakeup_task(task);
+            }
+        }
+    }
+
+    /// 处理完成中断
+    pub fn handle_irq(&self) {
+        self.blk.lock().ack_interrupt();
+        self.pop_completed();
     }
 }
 
+unsafe impl Send for VirtIOBlock {}
 unsafe impl Sync for VirtIOBlock {}
 
 impl BlockDevice for VirtIOBlock {
     fn read_block(&self, block_id: usize, buf: &mut [u8]) {
-        self.0
-            .borrow_mut()
-            .read_block(block_id, buf)
-            .expect("read error");
+        let mut resp = BlkResp::default();
+        let token = unsafe {
+            self.blk
+                .lock()
+                .read_block_nb(block_id, buf, &mut resp)
+                .expect("read error")
+        };
+        self.wait_for(token);
+        assert_eq!(resp.status(), RespStatus::Ok, "read error");
     }
     fn write_block(&self, block_id: usize, buf: &[u8]) {
-        self.0
-            .borrow_mut()
-            .write_block(block_id, buf)
-            .expect("write error");
+        let mut resp = BlkResp::default();
+        let token = unsafe {
+            self.blk
+                .lock()
+                .write_block_nb(block_id, buf, &mut resp)
+                .expect("write error")
+        };
+        self.wait_for(token);
+        assert_eq!(resp.status(), RespStatus::Ok, "write error");
     }
 }
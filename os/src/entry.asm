@@ -1,13 +1,38 @@
     .section .text.entry
     .globl _start
 # 程序入口处 分配栈空间并调用rust_main
+# a0为核号，保存在tp中；每个核使用独立的启动栈
 _start:
+    mv tp, a0
+    li t0, {MAX_HARTS}
+    bgeu a0, t0, park
     la sp, boot_stack_top
+    li t0, {BOOT_STACK_SIZE}
+    mul t0, t0, a0
+    sub sp, sp, t0
     call rust_main
 
+    .globl _start_secondary
+# 其余核的入口（由SBI HSM扩展启动）
+_start_secondary:
+    mv tp, a0
+    li t0, {MAX_HARTS}
+    bgeu a0, t0, park
+    la sp, boot_stack_top
+    li t0, {BOOT_STACK_SIZE}
+    mul t0, t0, a0
+    sub sp, sp, t0
+    call rust_main_secondary
+
+# 核号超出MAX_HARTS的核没有启动栈，停在此处
+park:
+    wfi
+    j park
+
     .section .bss.stack
     .globl boot_stack
 boot_stack:
-    .space 4096 * 16
+    # 每个核BOOT_STACK_SIZE
+    .space {BOOT_STACK_SIZE} * {MAX_HARTS}
     .globl boot_stack_top
 boot_stack_top:
@@ -0,0 +1,167 @@
+//! 设备文件系统
+//!
+//! 挂载在/dev，其中的节点为字符设备，打开后得到对应设备的文件
+
+use super::rfs::layout::Dirent;
+use super::stdio::Tty;
+use super::vfs::{Inode, SuperBlock};
+use super::{File, CHR, DIR};
+use crate::memory::frame::user_buffer::UserBuffer;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// devfs中的节点
+#[derive(Clone, Copy)]
+enum DevNode {
+    /// /dev
+    Root,
+    /// 丢弃写入的数据，读取时立即返回文件尾
+    Null,
+    /// 读取时返回任意多的0
+    Zero,
+    /// 当前终端
+    Tty,
+    /// 系统控制台
+    Console,
+}
+
+/// /dev下的设备及其名字
+const DEVICES: [(&str, DevNode); 4] = [
+    ("null", DevNode::Null),
+    ("zero", DevNode::Zero),
+    ("tty", DevNode::Tty),
+    ("console", DevNode::Console),
+];
+
+impl DevNode {
+    /// 目录的内容：.、..与各设备
+    fn dirents() -> Vec<u8> {
+        let mut content = Vec::new();
+        for (name, node) in [(".", DevNode::Root), ("..", DevNode::Root)]
+            .iter()
+            .chain(DEVICES.iter())
+        {
+            content.extend_from_slice(Dirent::new(name, node.inode_id() as u32).as_bytes());
+        }
+        content
+    }
+}
+
+impl Inode for DevNode {
+    fn mode(&self) -> usize {
+        match self {
+            DevNode::Root => DIR,
+            _ => CHR,
+        }
+    }
+    fn inode_id(&self) -> usize {
+        *self as usize
+    }
+    fn size(&self) -> usize {
+        match self {
+            DevNode::Root => Self::dirents().len(),
+            _ => 0,
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let content = match self {
+            DevNode::Root => Self::dirents(),
+            _ => return 0,
+        };
+        let start = content.len().min(offset);
+        let len = buf.len().min(content.len() - start);
+        buf[..len].copy_from_slice(&content[start..start + len]);
+        len
+    }
+    fn writable(&self) -> bool {
+        !matches!(self, DevNode::Root)
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        match self {
+            DevNode::Root => DEVICES
+                .iter()
+                .find(|(device, _)| *device == name)
+                .map(|(_, node)| Arc::new(*node) as Arc<dyn Inode>),
+            _ => None,
+        }
+    }
+    fn open(&self) -> Option<Arc<dyn File>> {
+        match self {
+            DevNode::Root => None,
+            DevNode::Null => Some(Arc::new(Null)),
+            DevNode::Zero => Some(Arc::new(Zero)),
+            // 只有一个终端，即控制台
+            DevNode::Tty | DevNode::Console => Some(Arc::new(Tty)),
+        }
+    }
+}
+
+/// /dev/null
+pub struct Null;
+
+impl File for Null {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        buf.len()
+    }
+}
+
+/// /dev/zero
+pub struct Zero;
+
+impl File for Zero {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        let len = buf.len();
+        for slice in buf.0.into_iter() {
+            slice.fill(0);
+        }
+        len
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        buf.len()
+    }
+}
+
+/// devfs实例
+pub struct DevFs;
+
+impl SuperBlock for DevFs {
+    fn fs_type(&self) -> &'static str {
+        "devfs"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::new(DevNode::Root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fs::rfs::layout::DIRENT_SZ;
+    test!(test_devfs, {
+        let root = DevFs.root_inode();
+        test_assert!(root.is_dir() && root.size() == (DEVICES.len() + 2) * DIRENT_SZ);
+        test_assert!(root.lookup("unknown").is_none() && root.open().is_none());
+        test_assert!(root.create("test").is_err());
+        let null = root.lookup("null").unwrap();
+        test_assert!(null.mode() == CHR && null.writable());
+        let file = null.open().unwrap();
+        test_assert!(file.get_mode() == CHR && !file.is_tty());
+        test_assert!(root.lookup("console").unwrap().open().unwrap().is_tty());
+        Ok("passed")
+    });
+}
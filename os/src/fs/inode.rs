@@ -1,29 +1,34 @@
 //! OSInode 文件描述符对应结构
-use super::rfs::layout::InodeType;
-use super::rfs::{find_inode, InodeHandler};
-use super::{File, DIR, LNK, REG};
+use super::vfs::{find_inode, Inode};
+use super::File;
 use crate::memory::frame::user_buffer::UserBuffer;
-use alloc::rc::Rc;
-use core::cell::RefCell;
+use crate::sync::SpinLock;
+use crate::sys_call::errno::*;
+use alloc::sync::Arc;
 
 pub struct OSInode {
     readable: bool,
     writable: bool,
-    inner: RefCell<OSInodeInner>,
+    inner: SpinLock<OSInodeInner>,
 }
 pub struct OSInodeInner {
     offset: usize,
-    inode: Rc<InodeHandler>,
+    inode: Arc<dyn Inode>,
 }
 
 impl OSInode {
-    pub fn new(readable: bool, writable: bool, inode: Rc<InodeHandler>) -> Self {
+    pub fn new(readable: bool, writable: bool, inode: Arc<dyn Inode>) -> Self {
         Self {
             readable,
             writable,
-            inner: RefCell::new(OSInodeInner { offset: 0, inode }),
+            inner: SpinLock::new(OSInodeInner { offset: 0, inode }),
         }
     }
+
+    /// 对应的inode，访问磁盘期间线程可能阻塞，不能一直借用inner
+    fn inode(&self) -> Arc<dyn Inode> {
+        self.inner.lock().inode.clone()
+    }
 }
 
 pub struct OpenFlags(pub u32);
@@ -52,29 +57,35 @@ impl OpenFlags {
     }
 }
 /// 根据路径以指定Openflags打开文件
-pub fn open_file(path: &str, flags: OpenFlags) -> Option<Rc<OSInode>> {
+pub fn open_file(path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, Errno> {
     // TODO: app mode
     let (readable, writable) = flags.read_write();
-    if flags.contains(CREATE) {
-        if let Some(inode) = find_inode(path) {
-            // clear size
-            inode.clear();
-            Some(Rc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            let (parent_path, target) = path.rsplit_once('/')?;
-            let parent_inode = find_inode(parent_path)?;
-            parent_inode
-                .create(target, InodeType::File)
-                .map(|inode| Rc::new(OSInode::new(readable, writable, inode)))
-        }
-    } else {
-        find_inode(path).map(|inode| {
-            if flags.contains(TRUNC) {
+    let inode = match find_inode(path) {
+        Some(inode) => {
+            if writable && !inode.writable() {
+                return Err(EACCES);
+            }
+            if flags.contains(CREATE) || flags.contains(TRUNC) {
+                // clear size
                 inode.clear();
             }
-            Rc::new(OSInode::new(readable, writable, inode))
-        })
+            inode
+        }
+        None if flags.contains(CREATE) => {
+            let (parent_path, target) = path.rsplit_once('/').ok_or(ENOENT)?;
+            find_inode(parent_path).ok_or(ENOENT)?.create(target)?
+        }
+        None => return Err(ENOENT),
+    };
+    Ok(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+/// 根据路径打开文件，字符设备得到设备对应的文件
+pub fn open(path: &str, flags: OpenFlags) -> Result<Arc<dyn File>, Errno> {
+    if let Some(device) = find_inode(path).and_then(|inode| inode.open()) {
+        return Ok(device);
     }
+    Ok(open_file(path, flags)?)
 }
 
 impl File for OSInode {
@@ -87,62 +98,59 @@ impl File for OSInode {
     }
     /// 从文件offset处读出内容至Userbuffer内
     fn read(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.inner.borrow_mut();
+        let inode = self.inode();
         let mut total_read_size = 0usize;
         for slice in buf.0.into_iter() {
-            let read_size = inner.inode.read_at(inner.offset, slice);
+            let read_size = inode.read_at(self.get_offset(), slice);
             if read_size == 0 {
                 break;
             }
-            inner.offset += read_size;
+            self.inner.lock().offset += read_size;
             total_read_size += read_size;
         }
         total_read_size
     }
     /// 将buf内容写入文件offset处
     fn write(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.inner.borrow_mut();
+        let inode = self.inode();
         let mut total_write_size = 0usize;
         for slice in buf.0.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
+            let write_size = inode.write_at(self.get_offset(), *slice);
+            self.inner.lock().offset += write_size;
             total_write_size += write_size;
+            // 文件系统空间不足
+            if write_size < slice.len() {
+                break;
+            }
         }
         total_write_size
     }
     /// 获取当前OSInode的偏移
     fn get_offset(&self) -> usize {
-        self.inner.borrow().offset
+        self.inner.lock().offset
     }
     /// 设置当前OSInode的偏移
     fn set_offset(&self, offset: usize) {
-        self.inner.borrow_mut().offset = offset;
+        self.inner.lock().offset = offset;
     }
     /// 获取当前OSInode的文件大小
     fn get_file_size(&self) -> usize {
-        self.inner.borrow().inode.get_file_size() as usize
+        self.inode().size()
     }
     /// 获取当前OSInode的inode_id
     fn get_inode_id(&self) -> usize {
-        self.inner.borrow().inode.get_inode_id() as usize
+        self.inode().inode_id()
     }
     /// 获取当前文件类型
     fn get_mode(&self) -> usize {
-        let inode = &self.inner.borrow().inode;
-        if inode.is_file() {
-            REG
-        } else if inode.is_dir() {
-            DIR
-        } else {
-            LNK
-        }
+        self.inode().mode()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::fs::REG;
     test!(test_file_attr, {
         let test_file = open_file("/test_file_attr", CREATE).unwrap();
         test_assert!(test_file.readable, "Test file attr failed");
@@ -1,16 +1,26 @@
 //! 文件系统模块
+pub mod devfs;
 pub mod inode;
 pub mod pipe;
+pub mod procfs;
 pub mod rfs;
 pub mod stdio;
-use alloc::rc::Rc;
+pub mod tmpfs;
+pub mod vfs;
+use crate::config::TMPFS_PAGE_LIMIT;
+use crate::sys_call::errno::{Errno, ENODEV};
+use alloc::sync::Arc;
+use devfs::DevFs;
+use procfs::ProcFs;
+use tmpfs::TmpFs;
+use vfs::SuperBlock;
 
 use crate::memory::frame::user_buffer::UserBuffer;
 
-const CHR: usize = 0;
-const REG: usize = 1;
-const DIR: usize = 2;
-const LNK: usize = 3;
+pub const CHR: usize = 0;
+pub const REG: usize = 1;
+pub const DIR: usize = 2;
+pub const LNK: usize = 3;
 
 const EOT: char = '\x04';
 const LF: char = '\x0a';
@@ -23,8 +33,8 @@ pub struct Stat {
     pub size: u32,
 }
 
-impl From<Rc<dyn File>> for Stat {
-    fn from(file: Rc<dyn File>) -> Self {
+impl From<Arc<dyn File>> for Stat {
+    fn from(file: Arc<dyn File>) -> Self {
         Self {
             ino: file.get_inode_id() as u32,
             mode: file.get_mode() as u32,
@@ -34,7 +44,7 @@ impl From<Rc<dyn File>> for Stat {
     }
 }
 
-pub trait File {
+pub trait File: Send + Sync {
     fn readable(&self) -> bool;
     fn writable(&self) -> bool;
     fn read(&self, buf: UserBuffer) -> usize;
@@ -52,9 +62,38 @@ pub trait File {
     fn get_mode(&self) -> usize {
         CHR
     }
+    /// 是否为终端设备
+    fn is_tty(&self) -> bool {
+        false
+    }
+}
+
+/// 根据类型名与挂载选项创建可挂载的文件系统实例
+/// 类型不存在时返回ENODEV，选项无效时返回EINVAL
+pub fn create_fs(fs_type: &str, options: &str) -> Result<Arc<dyn SuperBlock>, Errno> {
+    match fs_type {
+        "proc" => Ok(Arc::new(ProcFs)),
+        "devfs" => Ok(Arc::new(DevFs)),
+        "tmpfs" => Ok(Arc::new(TmpFs::with_options(options)?)),
+        _ => Err(ENODEV),
+    }
+}
+
+/// 启动时挂载文件系统，挂载点不存在时在根文件系统中创建
+fn mount_on_boot(path: &str, sb: Arc<dyn SuperBlock>) {
+    let root = vfs::find_inode("/").unwrap();
+    let name = path.trim_start_matches('/');
+    if root.lookup(name).is_none() {
+        root.mkdir(name).unwrap();
+    }
+    vfs::mount(path, sb).unwrap();
 }
 
 pub fn init() {
     rfs::init();
+    mount_on_boot("/proc", Arc::new(ProcFs));
+    mount_on_boot("/dev", Arc::new(DevFs));
+    mount_on_boot("/tmp", Arc::new(TmpFs::new(TMPFS_PAGE_LIMIT)));
+    stdio::init();
     println!("mod fs initialized!");
 }
@@ -1,26 +1,26 @@
 //! 管道
 use super::File;
+use crate::sync::SpinLock;
 use crate::{fs::EOT, memory::frame::user_buffer::UserBuffer};
-use alloc::rc::{Rc, Weak};
-use core::cell::RefCell;
+use alloc::sync::{Arc, Weak};
 
-use crate::task::suspend_current_and_run_next;
+use crate::task::{current_killed, suspend_current_and_run_next};
 
 pub struct Pipe {
     readable: bool,
     writable: bool,
-    buffer: Rc<RefCell<PipeRingBuffer>>,
+    buffer: Arc<SpinLock<PipeRingBuffer>>,
 }
 
 impl Pipe {
-    pub fn read_end_with_buffer(buffer: Rc<RefCell<PipeRingBuffer>>) -> Self {
+    pub fn read_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: true,
             writable: false,
             buffer,
         }
     }
-    pub fn write_end_with_buffer(buffer: Rc<RefCell<PipeRingBuffer>>) -> Self {
+    pub fn write_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: false,
             writable: true,
@@ -60,8 +60,8 @@ impl PipeRingBuffer {
         }
     }
     /// 返回write_end的weak指针
-    pub fn set_write_end(&mut self, write_end: &Rc<Pipe>) {
-        self.write_end = Rc::downgrade(write_end);
+    pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
+        self.write_end = Arc::downgrade(write_end);
     }
     pub fn write_byte(&mut self, byte: u8) {
         self.status = RingBufferStatus::Normal;
@@ -105,11 +105,11 @@ impl PipeRingBuffer {
 }
 
 /// Return (read_end, write_end)
-pub fn make_pipe() -> (Rc<Pipe>, Rc<Pipe>) {
-    let buffer = Rc::new(RefCell::new(PipeRingBuffer::new()));
-    let read_end = Rc::new(Pipe::read_end_with_buffer(buffer.clone()));
-    let write_end = Rc::new(Pipe::write_end_with_buffer(buffer.clone()));
-    buffer.borrow_mut().set_write_end(&write_end);
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(SpinLock::new(PipeRingBuffer::new()));
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
+    buffer.lock().set_write_end(&write_end);
     (read_end, write_end)
 }
 
@@ -126,7 +126,7 @@ impl File for Pipe {
         let mut buf_iter = buf.into_iter();
         let mut read_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.borrow_mut();
+            let mut ring_buffer = self.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
                 if ring_buffer.all_write_ends_closed() {
@@ -136,6 +136,10 @@ impl File for Pipe {
                     }
                     return read_size;
                 }
+                // 所属进程已退出时不再等待写端
+                if current_killed() {
+                    return read_size;
+                }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
                 continue;
@@ -157,9 +161,12 @@ impl File for Pipe {
         let mut buf_iter = buf.into_iter();
         let mut write_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.borrow_mut();
+            let mut ring_buffer = self.buffer.lock();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
+                if current_killed() {
+                    return write_size;
+                }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
                 continue;
@@ -0,0 +1,275 @@
+//! 进程信息文件系统
+//!
+//! 挂载在/proc，其中的目录与文件不存储在磁盘上，每次读取时根据进程控制块生成
+
+use super::rfs::layout::Dirent;
+use super::vfs::{self, Inode, SuperBlock};
+use super::{File, DIR, REG};
+use crate::interrupt::timer::ticks_to_ms;
+use crate::memory::frame::page_table::{R, U, W, X};
+use crate::task::{all_processes, find_process, ProcessControlBlock, TaskStatus};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// procfs中的节点
+#[derive(Clone, Copy)]
+enum ProcNode {
+    /// /proc
+    Root,
+    /// /proc/mounts
+    Mounts,
+    /// /proc/<pid>
+    Process(usize),
+    /// /proc/<pid>/status
+    Status(usize),
+    /// /proc/<pid>/cmdline
+    Cmdline(usize),
+    /// /proc/<pid>/cwd
+    Cwd(usize),
+    /// /proc/<pid>/maps
+    Maps(usize),
+    /// /proc/<pid>/fd
+    FdDir(usize),
+    /// /proc/<pid>/fd/<fd>
+    Fd(usize, usize),
+}
+
+/// 进程目录下的文件
+const PROCESS_ENTRIES: [&str; 5] = ["status", "cmdline", "cwd", "maps", "fd"];
+
+impl ProcNode {
+    /// 生成节点的内容（目录为目录项数组），进程已被回收时为空
+    fn content(&self) -> Vec<u8> {
+        let names = match *self {
+            ProcNode::Root => {
+                let mut pids = all_processes()
+                    .iter()
+                    .map(|proc| proc.pid.0)
+                    .collect::<Vec<_>>();
+                pids.sort_unstable();
+                let mut names = vec![String::from("mounts")];
+                names.extend(pids.iter().map(|pid| pid.to_string()));
+                names
+            }
+            ProcNode::Process(_) => PROCESS_ENTRIES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            ProcNode::FdDir(pid) => match find_process(pid) {
+                Some(proc) => proc
+                    .inner
+                    .lock()
+                    .fd_table
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, file)| file.is_some())
+                    .map(|(fd, _)| fd.to_string())
+                    .collect(),
+                None => vec![],
+            },
+            ProcNode::Mounts => {
+                return vfs::mounts()
+                    .iter()
+                    .map(|mount| format!("{} {}\n", mount.sb.fs_type(), mount.path))
+                    .collect::<String>()
+                    .into_bytes()
+            }
+            node => {
+                return match node.pid().and_then(find_process) {
+                    Some(proc) => node.file_content(&proc).into_bytes(),
+                    None => vec![],
+                }
+            }
+        };
+        let mut content = vec![];
+        for name in [".", ".."]
+            .iter()
+            .copied()
+            .chain(names.iter().map(String::as_str))
+        {
+            content.extend_from_slice(Dirent::new(name, 0).as_bytes());
+        }
+        content
+    }
+
+    /// 节点所属的进程
+    fn pid(&self) -> Option<usize> {
+        match *self {
+            ProcNode::Root | ProcNode::Mounts => None,
+            ProcNode::Process(pid)
+            | ProcNode::Status(pid)
+            | ProcNode::Cmdline(pid)
+            | ProcNode::Cwd(pid)
+            | ProcNode::Maps(pid)
+            | ProcNode::FdDir(pid)
+            | ProcNode::Fd(pid, _) => Some(pid),
+        }
+    }
+
+    /// 生成进程目录下文件的内容
+    /// 描述文件时可能读取磁盘而阻塞，不能持有进程控制块的锁
+    fn file_content(&self, proc: &Arc<ProcessControlBlock>) -> String {
+        if let ProcNode::Fd(_, fd) = *self {
+            let file = proc.inner.lock().fd_table.get(fd).cloned().flatten();
+            return file.map_or(String::new(), |file| describe(&file) + "\n");
+        }
+        let state = state(proc);
+        let inner = proc.inner.lock();
+        match *self {
+            ProcNode::Status(pid) => {
+                let name = inner
+                    .cmdline
+                    .first()
+                    .map_or("", |arg| arg.rsplit('/').next().unwrap());
+                let threads = inner
+                    .threads
+                    .iter()
+                    .flatten()
+                    .filter(|thread| thread.inner.lock().task_status != TaskStatus::Exited)
+                    .count();
+                let ppid = inner.parent.upgrade().map_or(0, |parent| parent.pid.0);
+                format!(
+                    "Name:\t{}\nState:\t{}\nPid:\t{}\nPPid:\t{}\nPgid:\t{}\nSid:\t{}\nThreads:\t{}\nVmSize:\t{} kB\nUtime:\t{} ms\nStime:\t{} ms\n",
+                    name,
+                    state,
+                    pid,
+                    ppid,
+                    inner.pgid,
+                    inner.sid,
+                    threads,
+                    inner.memory_set.get_size() / 1024,
+                    ticks_to_ms(inner.times.user),
+                    ticks_to_ms(inner.times.system),
+                )
+            }
+            // 参数以'\0'分隔
+            ProcNode::Cmdline(_) => inner.cmdline.iter().map(|arg| arg.clone() + "\0").collect(),
+            ProcNode::Cwd(_) => inner.cwd.clone() + "\n",
+            ProcNode::Maps(_) => inner
+                .memory_set
+                .segments()
+                .iter()
+                .filter(|segment| segment.flags & U != 0)
+                .map(|segment| {
+                    let flag = |bit, ch| if segment.flags & bit != 0 { ch } else { '-' };
+                    format!(
+                        "{:08x}-{:08x} {}{}{}p\n",
+                        segment.vpn_range.start.addr().0,
+                        segment.vpn_range.end.addr().0,
+                        flag(R, 'r'),
+                        flag(W, 'w'),
+                        flag(X, 'x'),
+                    )
+                })
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// 进程状态：R运行，S睡眠，T暂停，Z僵尸
+fn state(proc: &Arc<ProcessControlBlock>) -> char {
+    let inner = proc.inner.lock();
+    if inner.is_zombie {
+        'Z'
+    } else if inner.stopped {
+        'T'
+    } else if inner
+        .threads
+        .iter()
+        .flatten()
+        .any(|thread| thread.inner.lock().task_status == TaskStatus::Ready)
+    {
+        'R'
+    } else {
+        'S'
+    }
+}
+
+/// 打开的文件的简要描述
+fn describe(file: &Arc<dyn File>) -> String {
+    if file.is_tty() {
+        String::from("tty")
+    } else if file.get_mode() == REG || file.get_mode() == DIR {
+        format!("inode:[{}]", file.get_inode_id())
+    } else {
+        String::from("pipe")
+    }
+}
+
+impl Inode for ProcNode {
+    fn mode(&self) -> usize {
+        match self {
+            ProcNode::Root | ProcNode::Process(_) | ProcNode::FdDir(_) => DIR,
+            _ => REG,
+        }
+    }
+    fn size(&self) -> usize {
+        self.content().len()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let content = self.content();
+        let start = content.len().min(offset);
+        let len = buf.len().min(content.len() - start);
+        buf[..len].copy_from_slice(&content[start..start + len]);
+        len
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        let node = match (*self, name) {
+            (ProcNode::Root, "mounts") => ProcNode::Mounts,
+            (ProcNode::Root, pid) => {
+                let pid = pid.parse().ok()?;
+                find_process(pid)?;
+                ProcNode::Process(pid)
+            }
+            (ProcNode::Process(pid), "status") => ProcNode::Status(pid),
+            (ProcNode::Process(pid), "cmdline") => ProcNode::Cmdline(pid),
+            (ProcNode::Process(pid), "cwd") => ProcNode::Cwd(pid),
+            (ProcNode::Process(pid), "maps") => ProcNode::Maps(pid),
+            (ProcNode::Process(pid), "fd") => ProcNode::FdDir(pid),
+            (ProcNode::FdDir(pid), fd) => {
+                let fd: usize = fd.parse().ok()?;
+                find_process(pid)?.inner.lock().fd_table.get(fd)?.as_ref()?;
+                ProcNode::Fd(pid, fd)
+            }
+            _ => return None,
+        };
+        Some(Arc::new(node))
+    }
+}
+
+/// procfs实例
+pub struct ProcFs;
+
+impl SuperBlock for ProcFs {
+    fn fs_type(&self) -> &'static str {
+        "proc"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::new(ProcNode::Root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fs::rfs::layout::DIRENT_SZ;
+    use crate::fs::vfs::find_inode;
+    test!(test_procfs, {
+        let is_dir = |path| find_inode(path).map_or(false, |inode| inode.is_dir());
+        test_assert!(is_dir("/proc") && is_dir("/proc/0") && is_dir("/proc/0/fd"));
+        test_assert!(!is_dir("/proc/0/status") && !is_dir("/proc/mounts"));
+        test_assert!(find_inode("/proc/0/unknown").is_none());
+        let root = find_inode("/proc").unwrap();
+        test_assert!(root.mode() == DIR && root.size() >= 4 * DIRENT_SZ);
+        test_assert!(root.create("test").is_err() && !root.writable());
+        let status = find_inode("/proc/0/status").unwrap();
+        test_assert!(status.mode() == REG && status.size() > 0);
+        let mut buf = [0u8; 5];
+        test_assert!(status.read_at(0, &mut buf) == 5 && &buf == b"Name:");
+        Ok("passed")
+    });
+}
@@ -1,7 +1,7 @@
 //! Bitmap操作子模块。
 
 use super::{block_cache::get_block_cache, block_dev::BlockDevice, BLOCK_SZ};
-use alloc::rc::Rc;
+use alloc::sync::Arc;
 
 /// 方便分组读写的BitmapBlock定义
 type BitmapBlock = [u64; 64];
@@ -25,11 +25,11 @@ impl Bitmap {
     }
 
     /// 分配一个空闲块
-    pub fn alloc(&self, block_device: &Rc<dyn BlockDevice>) -> Option<usize> {
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
         // 遍历每一个BitmapBlock
         for block_id in 0..self.blocks {
-            let pos = get_block_cache(block_id + self.start_block_id, Rc::clone(block_device))
-                .borrow_mut()
+            let pos = get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                .lock()
                 .modify(0, |bitmap_block: &mut BitmapBlock| {
                     if let Some((group_pos, bit_pos)) = bitmap_block
                         .iter()
@@ -56,13 +56,13 @@ impl Bitmap {
     }
 
     /// 回收一个空闲块
-    pub fn dealloc(&self, block_device: &Rc<dyn BlockDevice>, block_id: usize) {
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, block_id: usize) {
         // 计算该块对应的BitmapBlock号，组号，位号
         let block_pos = block_id / BLOCK_BITS;
         let group_pos = (block_id % BLOCK_BITS) / 64;
         let bit_pos = (block_id % BLOCK_BITS) % 64;
-        get_block_cache(block_pos + self.start_block_id, Rc::clone(block_device))
-            .borrow_mut()
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
             .modify(0, |bitmap_block: &mut BitmapBlock| {
                 assert!(
                     bitmap_block[group_pos] & (1u64 << bit_pos) > 0,
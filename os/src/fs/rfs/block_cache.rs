@@ -1,22 +1,24 @@
 //! 块缓存管理子模块
+//!
+//! 块缓存只在持有文件系统锁时访问，读写磁盘时线程可能阻塞，此时不会有其他线程竞争缓存的锁
 use super::{block_dev::BlockDevice, BLOCK_SZ};
+use crate::sync::SpinLock;
 use crate::tools::uninit_cell::UninitCell;
-use alloc::rc::Rc;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::RefCell;
 
 /// 内存中的块缓存
 pub struct BlockCache {
     cache: Vec<u8>,
     modified: bool,
-    device: Rc<dyn BlockDevice>,
+    device: Arc<dyn BlockDevice>,
     block_id: usize,
 }
 
 impl BlockCache {
     /// 从磁盘块读到缓存中
-    pub fn new(block_id: usize, device: Rc<dyn BlockDevice>) -> Self {
+    pub fn new(block_id: usize, device: Arc<dyn BlockDevice>) -> Self {
         let mut cache = vec![0u8; BLOCK_SZ];
         device.read_block(block_id, &mut cache);
         Self {
@@ -85,63 +87,71 @@ const BLOCK_CACHE_SIZE: usize = 16;
 
 /// 块缓存管理器
 pub struct BlockCacheManager {
-    queue: Vec<(usize, Rc<RefCell<BlockCache>>)>,
+    queue: Vec<(usize, Arc<SpinLock<BlockCache>>)>,
 }
 
 impl BlockCacheManager {
     pub fn new() -> Self {
         Self { queue: Vec::new() }
     }
-    pub fn get_block_cache(
-        &mut self,
-        block_id: usize,
-        block_device: Rc<dyn BlockDevice>,
-    ) -> Rc<RefCell<BlockCache>> {
-        if let Some((_, cache)) = self.queue.iter().find(|(id, _)| *id == block_id) {
-            cache.clone()
-        } else {
-            if self.queue.len() == BLOCK_CACHE_SIZE {
-                if let Some((idx, _)) = self
-                    .queue
-                    .iter()
-                    .enumerate()
-                    .find(|(_, (_, cache))| Rc::strong_count(cache) == 1)
-                {
-                    self.queue.swap_remove(idx);
-                } else {
-                    panic!("Run out of BlockCache!");
-                }
-            }
-            let block_cache = Rc::new(RefCell::new(BlockCache::new(
-                block_id,
-                block_device.clone(),
-            )));
-            self.queue.push((block_id, Rc::clone(&block_cache)));
-            block_cache
+
+    /// 查找已缓存的块
+    fn find(&self, block_id: usize) -> Option<Arc<SpinLock<BlockCache>>> {
+        self.queue
+            .iter()
+            .find(|(id, _)| *id == block_id)
+            .map(|(_, cache)| cache.clone())
+    }
+
+    /// 缓存已满时移出一个未被使用的块，由调用者在解锁后释放（写回磁盘）
+    fn evict(&mut self) -> Option<Arc<SpinLock<BlockCache>>> {
+        if self.queue.len() < BLOCK_CACHE_SIZE {
+            return None;
+        }
+        match self
+            .queue
+            .iter()
+            .position(|(_, cache)| Arc::strong_count(cache) == 1)
+        {
+            Some(idx) => Some(self.queue.swap_remove(idx).1),
+            None => panic!("Run out of BlockCache!"),
         }
     }
 }
 
 /// 全局块缓存管理器
-pub static mut BLOCK_CACHE_MANAGER: UninitCell<BlockCacheManager> = UninitCell::uninit();
+pub static mut BLOCK_CACHE_MANAGER: UninitCell<SpinLock<BlockCacheManager>> = UninitCell::uninit();
 
-/// 获取块缓存
-pub fn get_block_cache(block_id: usize, device: Rc<dyn BlockDevice>) -> Rc<RefCell<BlockCache>> {
-    unsafe { BLOCK_CACHE_MANAGER.get_block_cache(block_id, device) }
+/// 获取块缓存，读写磁盘时不持有管理器的锁
+pub fn get_block_cache(block_id: usize, device: Arc<dyn BlockDevice>) -> Arc<SpinLock<BlockCache>> {
+    let mut manager = unsafe { BLOCK_CACHE_MANAGER.lock() };
+    if let Some(cache) = manager.find(block_id) {
+        return cache;
+    }
+    let evicted = manager.evict();
+    drop(manager);
+    drop(evicted);
+    let block_cache = Arc::new(SpinLock::new(BlockCache::new(block_id, device)));
+    let mut manager = unsafe { BLOCK_CACHE_MANAGER.lock() };
+    manager.queue.push((block_id, block_cache.clone()));
+    block_cache
 }
 
 /// 同步所有块缓存
 pub fn block_cache_sync_all() {
-    unsafe {
-        for (_, cache) in BLOCK_CACHE_MANAGER.queue.iter() {
-            cache.borrow_mut().sync();
-        }
+    let caches: Vec<_> = unsafe { BLOCK_CACHE_MANAGER.lock() }
+        .queue
+        .iter()
+        .map(|(_, cache)| cache.clone())
+        .collect();
+    for cache in caches {
+        cache.lock().sync();
     }
 }
 
 pub fn init() {
     unsafe {
-        BLOCK_CACHE_MANAGER = UninitCell::init(BlockCacheManager::new());
+        BLOCK_CACHE_MANAGER = UninitCell::init(SpinLock::new(BlockCacheManager::new()));
     }
 }
 #[cfg(test)]
@@ -150,18 +160,15 @@ mod test {
     use crate::drivers::BLOCK_DEVICE;
     use alloc::string::String;
     test!(test_block_cache, {
-        let cur_block;
-        unsafe {
-            cur_block = BLOCK_CACHE_MANAGER.get_block_cache(10, BLOCK_DEVICE.clone());
-        }
-        cur_block.borrow_mut().modify(0, |test: &mut [u8; 8]| {
+        let cur_block = get_block_cache(10, unsafe { BLOCK_DEVICE.clone() });
+        cur_block.lock().modify(0, |test: &mut [u8; 8]| {
             test[0] = b'1';
             test[1] = b'2';
             test[2] = b'3';
             test[3] = b'4';
         });
         for i in 0..4 {
-            let s = cur_block.borrow().read(i, |test: &[u8; 4]| {
+            let s = cur_block.lock().read(i, |test: &[u8; 4]| {
                 String::from_utf8(test.to_vec()).unwrap()
             });
             test_assert!(s[..4 - i] == "1234"[i..], "Read or Write Failed");
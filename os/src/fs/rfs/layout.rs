@@ -1,7 +1,7 @@
 //! 磁盘布局子模块
 
 use super::{block_cache::get_block_cache, block_dev::BlockDevice, DataBlock, BLOCK_SZ};
-use alloc::rc::Rc;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::mem::size_of;
 
@@ -27,7 +27,7 @@ const INODE_INDIRECT1_BOUND: usize = INODE_DIRECT_BOUND + INODE_INDIRECT1_COUNT;
 const INODE_INDIRECT2_BOUND: usize = INODE_INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
 
 /// 目录项名长度限制
-const NAME_LENGTH_LIMIT: usize = 27;
+pub const NAME_LENGTH_LIMIT: usize = 27;
 
 /// 第一个块，记录文件系统相关信息
 #[repr(C)]
@@ -137,28 +137,28 @@ impl Inode {
     }
 
     /// 根据内部块id获取在设备上的块id
-    pub fn get_block_id(&self, inner_id: u32, block_device: &Rc<dyn BlockDevice>) -> u32 {
+    pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
         let inner_id = inner_id as usize;
         if inner_id < INODE_DIRECT_BOUND {
             // 直接块
             self.direct[inner_id]
         } else if inner_id < INODE_INDIRECT1_BOUND {
             // 一级间接块
-            get_block_cache(self.indirect1 as usize, Rc::clone(block_device))
-                .borrow()
+            get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+                .lock()
                 .read(0, |indirect_block: &IndirectBlock| {
                     indirect_block[inner_id - INODE_DIRECT_BOUND]
                 })
         } else {
             // 二级间接块
             let last = inner_id - INODE_INDIRECT1_BOUND;
-            let sub_indirect1 = get_block_cache(self.indirect2 as usize, Rc::clone(block_device))
-                .borrow()
+            let sub_indirect1 = get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                .lock()
                 .read(0, |indirect2: &IndirectBlock| {
                     indirect2[last / INODE_INDIRECT1_COUNT]
                 });
-            get_block_cache(sub_indirect1 as usize, Rc::clone(block_device))
-                .borrow()
+            get_block_cache(sub_indirect1 as usize, Arc::clone(block_device))
+                .lock()
                 .read(0, |indirect1: &IndirectBlock| {
                     indirect1[last % INODE_INDIRECT1_COUNT]
                 })
@@ -170,7 +170,7 @@ impl Inode {
         &mut self,
         new_size: u32,
         new_blocks: Vec<u32>,
-        block_device: &Rc<dyn BlockDevice>,
+        block_device: &Arc<dyn BlockDevice>,
     ) {
         let mut current_blocks = self.data_blocks() as usize;
         self.size = new_size;
@@ -190,8 +190,8 @@ impl Inode {
             self.indirect1 = new_blocks.next().unwrap();
         }
         // 扩充一级间接块
-        get_block_cache(self.indirect1 as usize, Rc::clone(block_device))
-            .borrow_mut()
+        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+            .lock()
             .modify(0, |indirect1: &mut IndirectBlock| {
                 while current_blocks < total_blocks.min(INODE_INDIRECT1_BOUND) {
                     indirect1[current_blocks as usize - INODE_DIRECT_BOUND] =
@@ -208,8 +208,8 @@ impl Inode {
             self.indirect2 = new_blocks.next().unwrap();
         }
         // 扩充二级间接块
-        get_block_cache(self.indirect2 as usize, Rc::clone(block_device))
-            .borrow_mut()
+        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+            .lock()
             .modify(0, |indirect2: &mut IndirectBlock| {
                 while current_blocks < total_blocks {
                     let curr_sub_indirect1 =
@@ -223,9 +223,9 @@ impl Inode {
                     // 扩充sub一级间接块中的直接块
                     get_block_cache(
                         indirect2[curr_sub_indirect1] as usize,
-                        Rc::clone(block_device),
+                        Arc::clone(block_device),
                     )
-                    .borrow_mut()
+                    .lock()
                     .modify(0, |indirect1: &mut IndirectBlock| {
                         indirect1[curr_sub_direct] = new_blocks.next().unwrap();
                     });
@@ -235,7 +235,11 @@ impl Inode {
     }
 
     /// 减少Inode管理的空间大小
-    pub fn decrease_size(&mut self, new_size: u32, block_device: &Rc<dyn BlockDevice>) -> Vec<u32> {
+    pub fn decrease_size(
+        &mut self,
+        new_size: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
         let mut v: Vec<u32> = Vec::new();
         let current_blocks = self.data_blocks() as usize;
         self.size = new_size;
@@ -251,8 +255,8 @@ impl Inode {
             return v;
         }
         // 回收一级间接块
-        get_block_cache(self.indirect1 as usize, Rc::clone(block_device))
-            .borrow()
+        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+            .lock()
             .read(0, |indirect1: &IndirectBlock| {
                 while recycled_blocks < current_blocks.min(INODE_INDIRECT1_BOUND) {
                     v.push(indirect1[recycled_blocks - INODE_DIRECT_BOUND]);
@@ -266,8 +270,8 @@ impl Inode {
             return v;
         }
         // 回收二级间接块
-        get_block_cache(self.indirect2 as usize, Rc::clone(block_device))
-            .borrow()
+        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+            .lock()
             .read(0, |indirect2: &IndirectBlock| {
                 while recycled_blocks < current_blocks {
                     let curr_sub_indirect1 =
@@ -281,9 +285,9 @@ impl Inode {
                     // 回收sub一级间接块中的直接块
                     get_block_cache(
                         indirect2[curr_sub_indirect1] as usize,
-                        Rc::clone(block_device),
+                        Arc::clone(block_device),
                     )
-                    .borrow()
+                    .lock()
                     .read(0, |indirect1: &IndirectBlock| {
                         v.push(indirect1[curr_sub_direct]);
                     });
@@ -299,7 +303,7 @@ impl Inode {
         &self,
         offset: usize,
         buf: &mut [u8],
-        block_device: &Rc<dyn BlockDevice>,
+        block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut curr_start = offset;
         let end = (offset + buf.len()).min(self.size as usize);
@@ -316,9 +320,9 @@ impl Inode {
             let dst = &mut buf[read_size..read_size + curr_block_read_size];
             get_block_cache(
                 self.get_block_id(curr_block as u32, block_device) as usize,
-                Rc::clone(block_device),
+                Arc::clone(block_device),
             )
-            .borrow()
+            .lock()
             .read(0, |data_block: &DataBlock| {
                 let src = &data_block
                     [curr_start % BLOCK_SZ..curr_start % BLOCK_SZ + curr_block_read_size];
@@ -340,7 +344,7 @@ impl Inode {
         &mut self,
         offset: usize,
         buf: &[u8],
-        block_device: &Rc<dyn BlockDevice>,
+        block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut curr_start = offset;
         let end = (offset + buf.len()).min(self.size as usize);
@@ -354,9 +358,9 @@ impl Inode {
             let curr_block_write_size = curr_block_end - curr_start;
             get_block_cache(
                 self.get_block_id(curr_block as u32, block_device) as usize,
-                Rc::clone(block_device),
+                Arc::clone(block_device),
             )
-            .borrow_mut()
+            .lock()
             .modify(0, |data_block: &mut DataBlock| {
                 let src = &buf[write_size..write_size + curr_block_write_size];
                 let dst = &mut data_block
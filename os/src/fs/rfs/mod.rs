@@ -11,22 +11,36 @@ pub const BLOCK_SZ: usize = 512;
 /// 数据块
 type DataBlock = [u8; BLOCK_SZ];
 
+use super::vfs::{Inode as VfsInode, SuperBlock};
+use super::{DIR, LNK, REG};
 use crate::drivers::BLOCK_DEVICE;
+use crate::memory::frame::swap::is_swap_file;
+use crate::sync::{SleepLock, SleepLockGuard, SpinLock};
+use crate::sys_call::errno::*;
 use crate::tools::uninit_cell::UninitCell;
-use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
+use layout::InodeType;
 pub use rfs::RustedFileSystem;
 pub use vfs::InodeHandler;
 /// 根目录节点
-pub static mut ROOT_INODE: UninitCell<Rc<InodeHandler>> = UninitCell::uninit();
+pub static mut ROOT_INODE: UninitCell<Arc<InodeHandler>> = UninitCell::uninit();
+/// 文件系统锁：读写磁盘时线程会阻塞，持锁期间其他线程不能进入文件系统与块缓存
+static mut RFS_LOCK: UninitCell<SleepLock> = UninitCell::uninit();
+
+/// 获取文件系统锁
+pub fn lock_rfs() -> SleepLockGuard<'static> {
+    unsafe { RFS_LOCK.lock() }
+}
+
 /// 由路径找到文件的inodehandler
-pub fn find_inode(path: &str) -> Option<Rc<InodeHandler>> {
+pub fn find_inode(path: &str) -> Option<Arc<InodeHandler>> {
     let root_inode = unsafe { ROOT_INODE.clone() };
     path.split('/').fold(Some(root_inode), |res, name| {
         if let Some(node) = res {
             if !name.is_empty() {
-                node.find(name)
+                node.find(name).map(Arc::new)
             } else {
                 Some(node)
             }
@@ -56,18 +70,107 @@ pub fn get_full_path(cwd: &String, path: &String) -> String {
     });
     String::from("/") + &v.join("/")
 }
-/// 初始化文件系统,创建root目录
+/// 挂载到VFS中的RFS实例
+pub struct RfsSuperBlock(Arc<SpinLock<RustedFileSystem>>);
+
+impl SuperBlock for RfsSuperBlock {
+    fn fs_type(&self) -> &'static str {
+        "rfs"
+    }
+    fn root_inode(&self) -> Arc<dyn VfsInode> {
+        Arc::new(RustedFileSystem::root_inode(&self.0))
+    }
+}
+
+impl VfsInode for InodeHandler {
+    fn mode(&self) -> usize {
+        let _guard = lock_rfs();
+        if InodeHandler::is_file(self) {
+            REG
+        } else if InodeHandler::is_dir(self) {
+            DIR
+        } else {
+            LNK
+        }
+    }
+    fn inode_id(&self) -> usize {
+        let _guard = lock_rfs();
+        self.get_inode_id() as usize
+    }
+    fn size(&self) -> usize {
+        let _guard = lock_rfs();
+        self.get_file_size() as usize
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _guard = lock_rfs();
+        InodeHandler::read_at(self, offset, buf)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        if is_swap_file(self) {
+            return 0;
+        }
+        let _guard = lock_rfs();
+        InodeHandler::write_at(self, offset, buf)
+    }
+    /// 交换区的磁盘块由页面置换直接读写，不允许通过文件系统修改
+    fn writable(&self) -> bool {
+        !is_swap_file(self)
+    }
+    fn clear(&self) {
+        if is_swap_file(self) {
+            return;
+        }
+        let _guard = lock_rfs();
+        InodeHandler::clear(self)
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        let _guard = lock_rfs();
+        self.find(name)
+            .map(|inode| Arc::new(inode) as Arc<dyn VfsInode>)
+    }
+    fn create(&self, name: &str) -> Result<Arc<dyn VfsInode>, Errno> {
+        let _guard = lock_rfs();
+        let inode = InodeHandler::create(self, name, InodeType::File).ok_or(EEXIST)?;
+        Ok(Arc::new(inode))
+    }
+    fn mkdir(&self, name: &str) -> Result<Arc<dyn VfsInode>, Errno> {
+        let _guard = lock_rfs();
+        let inode = InodeHandler::create(self, name, InodeType::Directory).ok_or(EEXIST)?;
+        inode.set_default_dirent(self.get_inode_id());
+        Ok(Arc::new(inode))
+    }
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let _guard = lock_rfs();
+        let inode = self.find(name).ok_or(ENOENT)?;
+        if is_swap_file(&inode) {
+            return Err(EBUSY);
+        }
+        inode.clear();
+        self.delete(name);
+        Ok(())
+    }
+}
+
+/// 初始化文件系统,创建root目录并作为根文件系统挂载
 pub fn init() {
-    block_cache::init();
     unsafe {
-        if let Some(rfs) = RustedFileSystem::open(BLOCK_DEVICE.clone()) {
-            ROOT_INODE = UninitCell::init(Rc::new(RustedFileSystem::root_inode(&rfs)));
-        } else {
+        RFS_LOCK = UninitCell::init(SleepLock::new());
+    }
+    block_cache::init();
+    let block_device = unsafe { BLOCK_DEVICE.clone() };
+    let rfs = match RustedFileSystem::open(block_device.clone()) {
+        Some(rfs) => rfs,
+        None => {
             println!("[kernel] RFS corrupted, formatting");
-            let rfs = RustedFileSystem::format(BLOCK_DEVICE.clone(), 4096, 1);
-            ROOT_INODE = UninitCell::init(Rc::new(RustedFileSystem::root_inode(&rfs)));
-            ROOT_INODE.set_default_dirent(ROOT_INODE.get_inode_id());
+            let rfs = RustedFileSystem::format(block_device, 4096, 1);
+            let root_inode = RustedFileSystem::root_inode(&rfs);
+            root_inode.set_default_dirent(root_inode.get_inode_id());
             block_cache::block_cache_sync_all();
+            rfs
         }
+    };
+    unsafe {
+        ROOT_INODE = UninitCell::init(Arc::new(RustedFileSystem::root_inode(&rfs)));
     }
+    super::vfs::init(Arc::new(RfsSuperBlock(rfs)));
 }
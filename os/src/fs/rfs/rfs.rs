@@ -8,8 +8,8 @@ use super::{
     vfs::InodeHandler,
 };
 use super::{DataBlock, BLOCK_SZ};
-use alloc::rc::Rc;
-use core::cell::RefCell;
+use crate::sync::SpinLock;
+use alloc::sync::Arc;
 use core::mem::size_of;
 
 /// 块内Inode数量
@@ -17,7 +17,7 @@ const INODES_PER_BLOCK: u32 = (BLOCK_SZ / size_of::<Inode>()) as u32;
 
 /// rfs文件系统
 pub struct RustedFileSystem {
-    pub block_device: Rc<dyn BlockDevice>,
+    pub block_device: Arc<dyn BlockDevice>,
     pub inode_bitmap: Bitmap,
     pub data_bitmap: Bitmap,
     inode_start_block: u32,
@@ -27,10 +27,10 @@ pub struct RustedFileSystem {
 impl RustedFileSystem {
     /// 根据参数在设备上创建新的文件系统
     pub fn format(
-        block_device: Rc<dyn BlockDevice>,
+        block_device: Arc<dyn BlockDevice>,
         total_blocks: u32,
         inode_bitmap_blocks: u32,
-    ) -> Rc<RefCell<Self>> {
+    ) -> Arc<SpinLock<Self>> {
         // 计算磁盘布局
         let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
         let inode_blocks =
@@ -45,7 +45,7 @@ impl RustedFileSystem {
             data_bitmap_blocks as usize,
         );
         let mut rfs = Self {
-            block_device: Rc::clone(&block_device),
+            block_device: Arc::clone(&block_device),
             inode_bitmap,
             data_bitmap,
             inode_start_block: 1 + inode_bitmap_blocks,
@@ -53,8 +53,8 @@ impl RustedFileSystem {
         };
         // 清空数据
         for i in 0..total_blocks {
-            get_block_cache(i as usize, Rc::clone(&block_device))
-                .borrow_mut()
+            get_block_cache(i as usize, Arc::clone(&block_device))
+                .lock()
                 .modify(0, |data_block: &mut DataBlock| {
                     for byte in data_block.iter_mut() {
                         *byte = 0;
@@ -62,9 +62,9 @@ impl RustedFileSystem {
                 });
         }
         // 初始化超级块
-        get_block_cache(0, Rc::clone(&block_device))
-            .borrow_mut()
-            .modify(0, |super_block: &mut SuperBlock| {
+        get_block_cache(0, Arc::clone(&block_device)).lock().modify(
+            0,
+            |super_block: &mut SuperBlock| {
                 super_block.init(
                     total_blocks,
                     inode_bitmap_blocks,
@@ -72,25 +72,26 @@ impl RustedFileSystem {
                     data_bitmap_blocks,
                     data_blocks,
                 );
-            });
+            },
+        );
         // 初始化根Inode
         let root_inode = rfs.alloc_inode();
         let (root_inode_block_id, root_inode_offset) = rfs.get_disk_inode_pos(root_inode);
-        get_block_cache(root_inode_block_id as usize, Rc::clone(&block_device))
-            .borrow_mut()
+        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+            .lock()
             .modify(root_inode_offset, |disk_inode: &mut Inode| {
                 disk_inode.init(InodeType::Directory);
             });
         // 立刻写回
         block_cache_sync_all();
-        Rc::new(RefCell::new(rfs))
+        Arc::new(SpinLock::new(rfs))
     }
 
     /// 打开设备上的文件系统
-    pub fn open(block_device: Rc<dyn BlockDevice>) -> Option<Rc<RefCell<Self>>> {
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<SpinLock<Self>>> {
         // 根据超级块信息初始化文件系统
-        get_block_cache(0, Rc::clone(&block_device))
-            .borrow()
+        get_block_cache(0, Arc::clone(&block_device))
+            .lock()
             .read(0, |super_block: &SuperBlock| {
                 if !super_block.is_valid() {
                     return None;
@@ -106,16 +107,16 @@ impl RustedFileSystem {
                     inode_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
                 };
-                Some(Rc::new(RefCell::new(rfs)))
+                Some(Arc::new(SpinLock::new(rfs)))
             })
     }
     /// 获取根目录的引用
-    pub fn root_inode(rfs: &Rc<RefCell<Self>>) -> InodeHandler {
-        let block_device = Rc::clone(&rfs.borrow().block_device);
+    pub fn root_inode(rfs: &Arc<SpinLock<Self>>) -> InodeHandler {
+        let block_device = Arc::clone(&rfs.lock().block_device);
         // acquire rfs lock temporarily
-        let (block_id, block_offset) = rfs.borrow().get_disk_inode_pos(0);
+        let (block_id, block_offset) = rfs.lock().get_disk_inode_pos(0);
         // release rfs lock
-        InodeHandler::new(block_id, block_offset, Rc::clone(rfs), block_device)
+        InodeHandler::new(block_id, block_offset, Arc::clone(rfs), block_device)
     }
 
     /// 根据Inode编号获取在磁盘上的块号和偏移
@@ -151,8 +152,8 @@ impl RustedFileSystem {
 
     /// 回收数据块
     pub fn dealloc_data(&mut self, block_id: u32) {
-        get_block_cache(block_id as usize, Rc::clone(&self.block_device))
-            .borrow_mut()
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
             .modify(0, |data_block: &mut DataBlock| {
                 data_block.iter_mut().for_each(|p| {
                     *p = 0;
@@ -172,19 +173,19 @@ mod test {
         unsafe {
             if let Some(rfs) = RustedFileSystem::open(BLOCK_DEVICE.clone()) {
                 test_assert!(
-                    rfs.as_ref().borrow().get_disk_inode_id(2, 500) == 3,
+                    rfs.as_ref().lock().get_disk_inode_id(2, 500) == 3,
                     "get_disk_inode_id_failed"
                 );
                 test_assert!(
-                    rfs.as_ref().borrow().get_disk_inode_id(3, 0) == 4,
+                    rfs.as_ref().lock().get_disk_inode_id(3, 0) == 4,
                     "get_disk_inode_id_failed"
                 );
                 test_assert!(
-                    rfs.as_ref().borrow().get_disk_inode_id(3, 200) == 5,
+                    rfs.as_ref().lock().get_disk_inode_id(3, 200) == 5,
                     "get_disk_inode_id_failed"
                 );
                 test_assert!(
-                    rfs.as_ref().borrow().get_disk_inode_id(4, 100) == 8,
+                    rfs.as_ref().lock().get_disk_inode_id(4, 100) == 8,
                     "get_disk_inode_id_failed"
                 );
             }
@@ -195,13 +196,13 @@ mod test {
     test!(test_get_inode_pos, {
         unsafe {
             if let Some(rfs) = RustedFileSystem::open(BLOCK_DEVICE.clone()) {
-                let (disk_id, offset) = rfs.as_ref().borrow().get_disk_inode_pos(3);
+                let (disk_id, offset) = rfs.as_ref().lock().get_disk_inode_pos(3);
                 test_assert!(disk_id == 2 && offset == 384, "get_disk_inode_id_failed");
-                let (disk_id, offset) = rfs.as_ref().borrow().get_disk_inode_pos(4);
+                let (disk_id, offset) = rfs.as_ref().lock().get_disk_inode_pos(4);
                 test_assert!(disk_id == 3 && offset == 0, "get_disk_inode_id_failed");
-                let (disk_id, offset) = rfs.as_ref().borrow().get_disk_inode_pos(5);
+                let (disk_id, offset) = rfs.as_ref().lock().get_disk_inode_pos(5);
                 test_assert!(disk_id == 3 && offset == 128, "get_disk_inode_id_failed");
-                let (disk_id, offset) = rfs.as_ref().borrow().get_disk_inode_pos(8);
+                let (disk_id, offset) = rfs.as_ref().lock().get_disk_inode_pos(8);
                 test_assert!(disk_id == 4 && offset == 0, "get_disk_inode_id_failed");
             }
         }
@@ -211,7 +212,7 @@ mod test {
     test!(test_alloc, {
         unsafe {
             if let Some(rfs) = RustedFileSystem::open(BLOCK_DEVICE.clone()) {
-                let mut using_rfs = rfs.as_ref().borrow_mut();
+                let mut using_rfs = rfs.as_ref().lock();
                 let cur_inode = using_rfs.alloc_inode();
                 using_rfs.dealloc_inode(cur_inode);
                 test_assert!(using_rfs.alloc_inode() == cur_inode, "test failed");
@@ -5,15 +5,16 @@ use super::{
     layout::{Dirent, Inode, InodeType, DIRENT_SZ},
     rfs::RustedFileSystem,
 };
-use alloc::rc::Rc;
-use core::cell::RefCell;
+use crate::sync::SpinLock;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 /// Inode句柄
 pub struct InodeHandler {
     block_id: u32,
     block_offset: usize,
-    fs: Rc<RefCell<RustedFileSystem>>,
-    block_device: Rc<dyn BlockDevice>,
+    fs: Arc<SpinLock<RustedFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
 }
 
 impl InodeHandler {
@@ -21,8 +22,8 @@ impl InodeHandler {
     pub fn new(
         block_id: u32,
         block_offset: usize,
-        fs: Rc<RefCell<RustedFileSystem>>,
-        block_device: Rc<dyn BlockDevice>,
+        fs: Arc<SpinLock<RustedFileSystem>>,
+        block_device: Arc<dyn BlockDevice>,
     ) -> Self {
         Self {
             block_id,
@@ -34,21 +35,21 @@ impl InodeHandler {
 
     /// 读取对应的Inode
     fn read_disk_inode<V>(&self, f: impl FnOnce(&Inode) -> V) -> V {
-        get_block_cache(self.block_id as usize, Rc::clone(&self.block_device))
-            .borrow()
+        get_block_cache(self.block_id as usize, Arc::clone(&self.block_device))
+            .lock()
             .read(self.block_offset, f)
     }
 
     /// 修改对应的Inode
     fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut Inode) -> V) -> V {
-        get_block_cache(self.block_id as usize, Rc::clone(&self.block_device))
-            .borrow_mut()
+        get_block_cache(self.block_id as usize, Arc::clone(&self.block_device))
+            .lock()
             .modify(self.block_offset, f)
     }
     /// 获取自身inode_id
     pub fn get_inode_id(&self) -> u32 {
         self.fs
-            .borrow()
+            .lock()
             .get_disk_inode_id(self.block_id, self.block_offset)
     }
     /// 根据当前目录下的文件名找到inode_id
@@ -66,17 +67,17 @@ impl InodeHandler {
         None
     }
     /// 根据当前目录下的文件名找到inodehandler
-    pub fn find(&self, name: &str) -> Option<Rc<InodeHandler>> {
-        let fs = self.fs.borrow();
+    pub fn find(&self, name: &str) -> Option<InodeHandler> {
+        let fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
             let inode_id = self.find_inode_id(name, disk_inode)?;
             let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-            Some(Rc::new(Self::new(
+            Some(Self::new(
                 block_id,
                 block_offset,
                 self.fs.clone(),
                 self.block_device.clone(),
-            )))
+            ))
         })
     }
     /// 扩充当前文件的大小
@@ -101,8 +102,8 @@ impl InodeHandler {
             .for_each(|block_id| fs.dealloc_data(block_id));
     }
     /// 在当前目录下创建文件
-    pub fn create(&self, name: &str, filetype: InodeType) -> Option<Rc<InodeHandler>> {
-        let mut fs = self.fs.borrow_mut();
+    pub fn create(&self, name: &str, filetype: InodeType) -> Option<InodeHandler> {
+        let mut fs = self.fs.lock();
         let op = |dir_inode: &Inode| {
             // assert it is a directory
             assert!(dir_inode.is_dir());
@@ -117,8 +118,8 @@ impl InodeHandler {
         let new_inode_id = fs.alloc_inode();
         // initialize inode
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
-        get_block_cache(new_inode_block_id as usize, Rc::clone(&self.block_device))
-            .borrow_mut()
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
             .modify(new_inode_block_offset, |new_inode: &mut Inode| {
                 new_inode.init(filetype);
             });
@@ -141,12 +142,12 @@ impl InodeHandler {
         block_cache_sync_all();
         // return inode
         if filetype != InodeType::Directory {
-            return Some(Rc::new(Self::new(
+            return Some(Self::new(
                 block_id,
                 block_offset,
                 self.fs.clone(),
                 self.block_device.clone(),
-            )));
+            ));
         } else {
             let new_inode_handler = Self::new(
                 block_id,
@@ -159,12 +160,12 @@ impl InodeHandler {
                 disk_inode.read_at(0, dirent_self.as_bytes_mut(), &self.block_device)
             });
             // new_inode_handler.create_default_for_dir(dirent_self.inode_number(), new_inode_id);
-            Some(Rc::new(new_inode_handler))
+            Some(new_inode_handler)
         }
     }
     /// 给当前文件设置默认目录项(.和..)
     pub fn set_default_dirent(&self, parent_inode_id: u32) {
-        let mut fs = self.fs.borrow_mut();
+        let mut fs = self.fs.lock();
         self.modify_disk_inode(|cur_dir_inode| {
             // increase size
             self.increase_size(2 * DIRENT_SZ as u32, cur_dir_inode, &mut fs);
@@ -180,7 +181,7 @@ impl InodeHandler {
     }
     /// 根据当前目录下的文件名删除指定文件
     pub fn delete(&self, name: &str) {
-        let mut fs = self.fs.borrow_mut();
+        let mut fs = self.fs.lock();
         self.modify_disk_inode(|dir_inode| {
             assert!(dir_inode.is_dir());
             self.find_inode_id(name, dir_inode).expect("No target");
@@ -216,12 +217,12 @@ impl InodeHandler {
     }
     /// 从指定偏移处读文件内容
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
-        let _fs = self.fs.borrow();
+        let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
     }
     /// 向指定偏移处写入文件内容
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
-        let mut fs = self.fs.borrow_mut();
+        let mut fs = self.fs.lock();
         let size = self.modify_disk_inode(|disk_inode| {
             self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
             disk_inode.write_at(offset, buf, &self.block_device)
@@ -233,10 +234,18 @@ impl InodeHandler {
     pub fn get_file_size(&self) -> u32 {
         self.read_disk_inode(|disk_inode| disk_inode.size)
     }
+    /// 按文件内的顺序获取存放文件数据的磁盘块号
+    pub fn data_block_ids(&self) -> Vec<u32> {
+        self.read_disk_inode(|disk_inode| {
+            (0..disk_inode.data_blocks())
+                .map(|inner_id| disk_inode.get_block_id(inner_id, &self.block_device))
+                .collect()
+        })
+    }
 
     /// 清空所有数据并回收块
     pub fn clear(&self) {
-        let mut fs = self.fs.borrow_mut();
+        let mut fs = self.fs.lock();
         self.modify_disk_inode(|disk_inode| {
             let data_blocks_dealloc = disk_inode.decrease_size(0, &self.block_device);
             for data_block in data_blocks_dealloc.into_iter() {
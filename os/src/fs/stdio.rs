@@ -1,11 +1,74 @@
 //! 标准输入输出
+//!
+//! 控制台输入先读入缓冲区，^C、^\、^Z转换为发往前台进程组的信号
 use super::File;
 use crate::fs::{CR, LF};
 use crate::memory::frame::user_buffer::UserBuffer;
 use crate::sbi::console_getchar;
+use crate::sync::SpinLock;
+use crate::task::get_current_process;
+use crate::task::send_group_signal;
+use crate::task::signal::{SIGINT, SIGQUIT, SIGTSTP, SIGTTIN};
+use crate::tools::uninit_cell::UninitCell;
+use alloc::collections::VecDeque;
 
+/// ^C
+const ETX: u8 = 0x03;
+/// ^Z
+const SUB: u8 = 0x1a;
+/// ^\
+const FS: u8 = 0x1c;
+
+/// 全局控制台实例
+static mut CONSOLE: UninitCell<SpinLock<Console>> = UninitCell::uninit();
+
+/// 控制台状态
+struct Console {
+    /// 前台进程组
+    foreground_pgid: Option<usize>,
+    /// 已从串口读入但尚未被进程读取的字符
+    input: VecDeque<u8>,
+}
+
+/// 控制台的前台进程组
+pub fn foreground_pgid() -> Option<usize> {
+    unsafe { CONSOLE.lock().foreground_pgid }
+}
+
+/// 设置控制台的前台进程组
+pub fn set_foreground_pgid(pgid: usize) {
+    unsafe {
+        CONSOLE.lock().foreground_pgid = Some(pgid);
+    }
+}
+
+/// 读入串口上的全部字符，控制字符转换为信号（时钟中断时调用）
+pub fn poll_console() {
+    loop {
+        let ch = console_getchar() as u8;
+        let (signum, echo) = match ch {
+            // no input
+            255 => break,
+            ETX => (SIGINT, "^C"),
+            FS => (SIGQUIT, "^\\"),
+            SUB => (SIGTSTP, "^Z"),
+            _ => {
+                let ch = if ch == CR as u8 { LF as u8 } else { ch };
+                unsafe { CONSOLE.lock().input.push_back(ch) };
+                continue;
+            }
+        };
+        if let Some(pgid) = foreground_pgid() {
+            print!("{}", echo);
+            send_group_signal(pgid, signum);
+        }
+    }
+}
+
+/// 标准输入，只读的终端
 pub struct Stdin;
 
+/// 标准输出，只写的终端
 pub struct Stdout;
 
 impl File for Stdin {
@@ -15,28 +78,45 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        // busy loop
-        loop {
-            let mut ch = console_getchar() as u8;
-            if ch == 255 {
-                break usize::MAX;
-            } else {
-                if ch == CR as u8 {
-                    ch = LF as u8;
-                }
-                unsafe {
-                    user_buf.0[0].as_mut_ptr().write_volatile(ch);
+    /// 读取已输入的字符，至多读到换行为止，没有输入时返回usize::MAX
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        if user_buf.len() == 0 {
+            return 0;
+        }
+        // 后台进程组读取控制台时被暂停
+        let pgid = get_current_process().inner.lock().pgid;
+        if foreground_pgid().map_or(false, |foreground| foreground != pgid) {
+            send_group_signal(pgid, SIGTTIN);
+            return usize::MAX;
+        }
+        poll_console();
+        let mut read_size = 0;
+        for byte in user_buf.into_iter() {
+            let ch = unsafe { CONSOLE.lock().input.pop_front() };
+            match ch {
+                Some(ch) => {
+                    *byte = ch;
+                    print!("{}", ch as char);
+                    read_size += 1;
+                    if ch == LF as u8 {
+                        break;
+                    }
                 }
-                print!("{}", ch as char);
-                break 1;
+                None => break,
             }
         }
+        if read_size == 0 {
+            usize::MAX
+        } else {
+            read_size
+        }
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+    fn is_tty(&self) -> bool {
+        true
+    }
 }
 
 impl File for Stdout {
@@ -55,4 +135,38 @@ impl File for Stdout {
         }
         user_buf.len()
     }
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
+
+/// 可读写的终端，即/dev/tty与/dev/console
+pub struct Tty;
+
+impl File for Tty {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        Stdin.read(user_buf)
+    }
+    fn write(&self, user_buf: UserBuffer) -> usize {
+        Stdout.write(user_buf)
+    }
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
+
+/// 初始化控制台
+pub fn init() {
+    unsafe {
+        CONSOLE = UninitCell::init(SpinLock::new(Console {
+            foreground_pgid: None,
+            input: VecDeque::new(),
+        }));
+    }
 }
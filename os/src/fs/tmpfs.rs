@@ -0,0 +1,283 @@
+//! 内存文件系统
+//!
+//! 文件内容保存在内核堆中，不经过块缓存，总大小受页数预算限制
+
+use super::rfs::layout::{Dirent, NAME_LENGTH_LIMIT};
+use super::vfs::{Inode, SuperBlock};
+use super::{DIR, REG};
+use crate::config::{PAGE_SIZE, TMPFS_PAGE_LIMIT};
+use crate::sync::SpinLock;
+use crate::sys_call::errno::*;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 文件系统实例共享的状态
+struct TmpFsState {
+    /// 页数预算
+    page_limit: usize,
+    /// 已使用的页数，检查预算与增加用量在同一次加锁中完成
+    used_pages: SpinLock<usize>,
+    /// 下一个索引节点编号
+    next_inode_id: AtomicUsize,
+}
+
+impl TmpFsState {
+    fn alloc_inode_id(&self) -> usize {
+        self.next_inode_id.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// 存放size字节所需的页数
+fn pages(size: usize) -> usize {
+    size / PAGE_SIZE + (size % PAGE_SIZE != 0) as usize
+}
+
+/// 节点内容
+enum TmpContent {
+    File(SpinLock<Vec<u8>>),
+    /// 目录项按创建顺序排列
+    Dir(SpinLock<Vec<(String, Arc<TmpInode>)>>),
+}
+
+/// tmpfs中的索引节点
+pub struct TmpInode {
+    inode_id: usize,
+    parent_id: usize,
+    content: TmpContent,
+    fs: Arc<TmpFsState>,
+}
+
+impl TmpInode {
+    fn new(parent_id: usize, content: TmpContent, fs: &Arc<TmpFsState>) -> Self {
+        Self {
+            inode_id: fs.alloc_inode_id(),
+            parent_id,
+            content,
+            fs: fs.clone(),
+        }
+    }
+
+    /// 目录的目录项，普通文件返回ENOTDIR
+    fn entries(&self) -> Result<&SpinLock<Vec<(String, Arc<TmpInode>)>>, Errno> {
+        match &self.content {
+            TmpContent::Dir(entries) => Ok(entries),
+            TmpContent::File(_) => Err(ENOTDIR),
+        }
+    }
+
+    /// 在当前目录下添加新节点
+    fn add(&self, name: &str, content: TmpContent) -> Result<Arc<dyn Inode>, Errno> {
+        if name.len() > NAME_LENGTH_LIMIT {
+            return Err(ENAMETOOLONG);
+        }
+        let mut entries = self.entries()?.lock();
+        if entries.iter().any(|(entry, _)| entry == name) {
+            return Err(EEXIST);
+        }
+        let inode = Arc::new(Self::new(self.inode_id, content, &self.fs));
+        entries.push((String::from(name), inode.clone()));
+        Ok(inode)
+    }
+
+    /// 目录的内容：.、..与各目录项
+    fn dirents(&self, entries: &SpinLock<Vec<(String, Arc<TmpInode>)>>) -> Vec<u8> {
+        let mut content = Vec::new();
+        content.extend_from_slice(Dirent::new(".", self.inode_id as u32).as_bytes());
+        content.extend_from_slice(Dirent::new("..", self.parent_id as u32).as_bytes());
+        for (name, inode) in entries.lock().iter() {
+            content.extend_from_slice(Dirent::new(name, inode.inode_id as u32).as_bytes());
+        }
+        content
+    }
+}
+
+impl Inode for TmpInode {
+    fn mode(&self) -> usize {
+        match self.content {
+            TmpContent::File(_) => REG,
+            TmpContent::Dir(_) => DIR,
+        }
+    }
+    fn inode_id(&self) -> usize {
+        self.inode_id
+    }
+    fn size(&self) -> usize {
+        match &self.content {
+            TmpContent::File(data) => data.lock().len(),
+            TmpContent::Dir(entries) => self.dirents(entries).len(),
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let mut read = |data: &[u8]| {
+            let start = data.len().min(offset);
+            let len = buf.len().min(data.len() - start);
+            buf[..len].copy_from_slice(&data[start..start + len]);
+            len
+        };
+        match &self.content {
+            TmpContent::File(data) => read(&data.lock()),
+            TmpContent::Dir(entries) => read(&self.dirents(entries)),
+        }
+    }
+    /// 超出页数预算的部分不会被写入
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut data = match &self.content {
+            TmpContent::File(data) => data.lock(),
+            TmpContent::Dir(_) => return 0,
+        };
+        let old_pages = pages(data.len());
+        let mut used_pages = self.fs.used_pages.lock();
+        let free_pages = self.fs.page_limit - *used_pages;
+        let end = (offset + buf.len()).min((old_pages + free_pages) * PAGE_SIZE);
+        if end <= offset {
+            return 0;
+        }
+        if end > data.len() {
+            data.resize(end, 0);
+            *used_pages += pages(end) - old_pages;
+        }
+        drop(used_pages);
+        data[offset..end].copy_from_slice(&buf[..end - offset]);
+        end - offset
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn clear(&self) {
+        if let TmpContent::File(data) = &self.content {
+            let mut data = data.lock();
+            *self.fs.used_pages.lock() -= pages(data.len());
+            data.clear();
+            data.shrink_to_fit();
+        }
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        match name {
+            "." | ".." => None,
+            _ => self
+                .entries()
+                .ok()?
+                .lock()
+                .iter()
+                .find(|(entry, _)| entry == name)
+                .map(|(_, inode)| inode.clone() as Arc<dyn Inode>),
+        }
+    }
+    fn create(&self, name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        self.add(name, TmpContent::File(SpinLock::new(Vec::new())))
+    }
+    fn mkdir(&self, name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        self.add(name, TmpContent::Dir(SpinLock::new(Vec::new())))
+    }
+    /// 文件仍被打开时，其占用的页在最后一次关闭后归还
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let mut entries = self.entries()?.lock();
+        let index = entries
+            .iter()
+            .position(|(entry, _)| entry == name)
+            .ok_or(ENOENT)?;
+        entries.remove(index);
+        Ok(())
+    }
+}
+
+impl Drop for TmpInode {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// tmpfs实例
+pub struct TmpFs {
+    root: Arc<TmpInode>,
+}
+
+impl TmpFs {
+    /// 创建最多使用page_limit页的tmpfs
+    pub fn new(page_limit: usize) -> Self {
+        let fs = Arc::new(TmpFsState {
+            page_limit,
+            used_pages: SpinLock::new(0),
+            next_inode_id: AtomicUsize::new(1),
+        });
+        let root_id = fs.next_inode_id.load(Ordering::Relaxed);
+        let root = TmpInode::new(root_id, TmpContent::Dir(SpinLock::new(Vec::new())), &fs);
+        Self {
+            root: Arc::new(root),
+        }
+    }
+
+    /// 根据挂载选项创建tmpfs，size=<字节数>[k|m|g]指定容量，未指定时使用默认预算
+    pub fn with_options(options: &str) -> Result<Self, Errno> {
+        let mut page_limit = TMPFS_PAGE_LIMIT;
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let size = option.strip_prefix("size=").ok_or(EINVAL)?;
+            let (digits, unit) = match size.as_bytes().last() {
+                Some(b'k' | b'K') => (&size[..size.len() - 1], 1 << 10),
+                Some(b'm' | b'M') => (&size[..size.len() - 1], 1 << 20),
+                Some(b'g' | b'G') => (&size[..size.len() - 1], 1 << 30),
+                _ => (size, 1),
+            };
+            let bytes = digits
+                .parse::<usize>()
+                .ok()
+                .and_then(|size| size.checked_mul(unit))
+                .ok_or(EINVAL)?;
+            page_limit = pages(bytes);
+        }
+        Ok(Self::new(page_limit))
+    }
+}
+
+impl SuperBlock for TmpFs {
+    fn fs_type(&self) -> &'static str {
+        "tmpfs"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+    test!(test_tmpfs, {
+        let fs = TmpFs::new(2);
+        let root = fs.root_inode();
+        let dir = root.mkdir("dir").unwrap();
+        test_assert!(root.mkdir("dir").err() == Some(EEXIST));
+        test_assert!(root.lookup("dir").unwrap().is_dir());
+        let file = dir.create("file").unwrap();
+        test_assert!(file.write_at(10, b"hello") == 5 && file.size() == 15);
+        // 普通文件上的目录操作
+        test_assert!(file.lookup("x").is_none() && file.create("x").err() == Some(ENOTDIR));
+        test_assert!(file.mkdir("x").err() == Some(ENOTDIR) && file.unlink("x") == Err(ENOTDIR));
+        let mut buf = [0u8; 5];
+        test_assert!(file.read_at(10, &mut buf) == 5 && &buf == b"hello");
+        // 超出预算的部分被截断
+        let data = vec![1u8; 3 * PAGE_SIZE];
+        test_assert!(file.write_at(0, &data) == 2 * PAGE_SIZE);
+        test_assert!(*fs.root.fs.used_pages.lock() == 2);
+        test_assert!(dir.create("other").unwrap().write_at(0, b"x") == 0);
+        // 文件被删除且不再被引用后页被归还
+        dir.unlink("file").unwrap();
+        test_assert!(*fs.root.fs.used_pages.lock() == 2);
+        drop(file);
+        test_assert!(*fs.root.fs.used_pages.lock() == 0);
+        test_assert!(dir.lookup("file").is_none() && dir.lookup("other").is_some());
+        Ok("passed")
+    });
+
+    test!(test_tmpfs_options, {
+        test_assert!(TmpFs::with_options("").unwrap().root.fs.page_limit == TMPFS_PAGE_LIMIT);
+        let fs = TmpFs::with_options("size=5k").unwrap();
+        test_assert!(fs.root.fs.page_limit == 2);
+        test_assert!(TmpFs::with_options("size=1M").unwrap().root.fs.page_limit == 256);
+        test_assert!(TmpFs::with_options("size=").is_err());
+        test_assert!(TmpFs::with_options("mode=755").is_err());
+        Ok("passed")
+    });
+}
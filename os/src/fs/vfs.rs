@@ -0,0 +1,182 @@
+//! 虚拟文件系统子模块
+//!
+//! 各文件系统实现Inode与SuperBlock，路径查找时逐级查询挂载表
+
+use super::{File, DIR, REG};
+use crate::sync::SpinLock;
+use crate::sys_call::errno::*;
+use crate::tools::uninit_cell::UninitCell;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 与具体文件系统无关的索引节点
+pub trait Inode: Send + Sync {
+    /// 文件类型（REG、DIR、CHR等）
+    fn mode(&self) -> usize;
+    /// 索引节点编号
+    fn inode_id(&self) -> usize {
+        0
+    }
+    /// 文件大小，目录为目录项数组的大小
+    fn size(&self) -> usize;
+    /// 从指定偏移处读取内容，目录读出的是目录项数组
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+    /// 向指定偏移处写入内容
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    /// 是否允许写入
+    fn writable(&self) -> bool {
+        false
+    }
+    /// 清空文件内容
+    fn clear(&self) {}
+    /// 在当前目录下按名字查找
+    fn lookup(&self, _name: &str) -> Option<Arc<dyn Inode>> {
+        None
+    }
+    /// 在当前目录下创建普通文件
+    fn create(&self, _name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        Err(EACCES)
+    }
+    /// 在当前目录下创建目录
+    fn mkdir(&self, _name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        Err(EACCES)
+    }
+    /// 删除当前目录下的文件或空目录
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(EACCES)
+    }
+    /// 字符设备打开后对应的文件
+    fn open(&self) -> Option<Arc<dyn File>> {
+        None
+    }
+    fn is_dir(&self) -> bool {
+        self.mode() == DIR
+    }
+    fn is_file(&self) -> bool {
+        self.mode() == REG
+    }
+}
+
+/// 文件系统实例
+pub trait SuperBlock: Send + Sync {
+    /// 文件系统类型名
+    fn fs_type(&self) -> &'static str;
+    /// 根目录
+    fn root_inode(&self) -> Arc<dyn Inode>;
+}
+
+/// 挂载表项
+#[derive(Clone)]
+pub struct Mount {
+    /// 挂载点的绝对路径
+    pub path: String,
+    pub sb: Arc<dyn SuperBlock>,
+    root: Arc<dyn Inode>,
+}
+
+/// 全局挂载表，同一挂载点上后挂载的覆盖先挂载的
+static mut MOUNT_TABLE: UninitCell<SpinLock<Vec<Mount>>> = UninitCell::uninit();
+
+/// 挂载在指定路径上的文件系统的根目录
+fn mounted_root(path: &str) -> Option<Arc<dyn Inode>> {
+    unsafe {
+        MOUNT_TABLE
+            .lock()
+            .iter()
+            .rev()
+            .find(|mount| mount.path == path)
+            .map(|mount| mount.root.clone())
+    }
+}
+
+/// 由绝对路径找到索引节点，经过挂载点时进入挂载的文件系统
+pub fn find_inode(path: &str) -> Option<Arc<dyn Inode>> {
+    let mut inode = mounted_root("/")?;
+    let mut cur_path = String::new();
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        inode = inode.lookup(name)?;
+        cur_path = cur_path + "/" + name;
+        if let Some(root) = mounted_root(&cur_path) {
+            inode = root;
+        }
+    }
+    Some(inode)
+}
+
+/// 路径是否为挂载点
+pub fn is_mount_point(path: &str) -> bool {
+    mounted_root(path).is_some()
+}
+
+/// 将文件系统挂载到指定目录
+pub fn mount(path: &str, sb: Arc<dyn SuperBlock>) -> Result<(), Errno> {
+    if !find_inode(path).ok_or(ENOENT)?.is_dir() {
+        return Err(ENOTDIR);
+    }
+    let root = sb.root_inode();
+    unsafe {
+        MOUNT_TABLE.lock().push(Mount {
+            path: String::from(path),
+            sb,
+            root,
+        });
+    }
+    Ok(())
+}
+
+/// 卸载指定目录上最后挂载的文件系统
+pub fn umount(path: &str) -> Result<(), Errno> {
+    let mut table = unsafe { MOUNT_TABLE.lock() };
+    let index = table
+        .iter()
+        .rposition(|mount| mount.path == path)
+        .ok_or(EINVAL)?;
+    // 根文件系统与其上还挂载着其他文件系统的不能卸载
+    let prefix = String::from(path) + "/";
+    if path == "/" || table.iter().any(|mount| mount.path.starts_with(&prefix)) {
+        return Err(EBUSY);
+    }
+    table.remove(index);
+    Ok(())
+}
+
+/// 当前挂载表的快照
+pub fn mounts() -> Vec<Mount> {
+    unsafe { MOUNT_TABLE.lock().clone() }
+}
+
+/// 初始化挂载表并挂载根文件系统
+pub fn init(root_sb: Arc<dyn SuperBlock>) {
+    let root = root_sb.root_inode();
+    unsafe {
+        MOUNT_TABLE = UninitCell::init(SpinLock::new(vec![Mount {
+            path: String::from("/"),
+            sb: root_sb,
+            root,
+        }]));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fs::procfs::ProcFs;
+    test!(test_mount, {
+        let root = find_inode("/").unwrap();
+        root.mkdir("test_mount").unwrap();
+        test_assert!(find_inode("/test_mount/mounts").is_none());
+        mount("/test_mount", Arc::new(ProcFs)).unwrap();
+        test_assert!(is_mount_point("/test_mount"));
+        test_assert!(find_inode("/test_mount/mounts").is_some());
+        test_assert!(umount("/") == Err(EBUSY));
+        test_assert!(umount("/test_mount").is_ok() && umount("/test_mount") == Err(EINVAL));
+        test_assert!(find_inode("/test_mount/mounts").is_none());
+        root.unlink("test_mount").unwrap();
+        test_assert!(mount("/test_mount", Arc::new(ProcFs)) == Err(ENOENT));
+        Ok("passed")
+    });
+}
@@ -2,7 +2,7 @@
 
 /// 中断切换上下文
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct Context {
     pub x: [usize; 32], // 32个通用寄存器
     pub sstatus: usize, // CSR寄存器
@@ -10,6 +10,8 @@ pub struct Context {
     pub kernel_satp: usize,
     pub kernel_sp: usize,
     pub interrupt_handler: usize,
+    /// 返回用户态时所在的核，进入中断时恢复到tp
+    pub hart_id: usize,
 }
 
 impl Context {
@@ -39,8 +41,18 @@ impl Context {
             kernel_satp,
             kernel_sp,
             interrupt_handler,
+            hart_id: 0,
         };
         cx.set_sp(sp);
         cx
     }
 }
+
+/// 内核态中断时保存在内核栈上的上下文
+#[repr(C)]
+#[derive(Debug)]
+pub struct KernelContext {
+    pub x: [usize; 32],
+    pub sstatus: usize,
+    pub sepc: usize,
+}
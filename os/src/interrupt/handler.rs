@@ -1,16 +1,43 @@
 //! 中断处理子模块
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use super::context::KernelContext;
+use super::timer;
+use crate::config::{MAX_HARTS, TRAMPOLINE};
+use crate::drivers::handle_irqs;
+use crate::fs::stdio::poll_console;
+use crate::memory::frame::address::{PhysAddr, VirtAddr};
+use crate::memory::frame::page_table::{R, W, X};
+use crate::smp::{clear_ipi, enter_user, hart_id, leave_user, lock_kernel, unlock_kernel};
+use crate::sys_call::errno::ENOMEM;
 use crate::sys_call::sys_call;
-use crate::task::{exit_current_and_run_next, get_current_process, schedule_callback};
+use crate::task::signal::{SIGBUS, SIGILL, SIGKILL, SIGSEGV};
+use crate::task::{
+    account_current_time, cond_resched, exit_if_killed, get_current_process, get_current_task,
+    handle_signals, kill_current_and_run_next, reap_prev_task, request_resched, schedule_callback,
+    try_get_current_task,
+};
 use core::arch::global_asm;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 global_asm!(include_str!("./interrupt.asm"));
 
+const INSTRUCTION_MISALIGNED: usize = 0;
+const INSTRUCTION_FAULT: usize = 1;
 const ILLEGAL_INSTRUCTION: usize = 2;
 const BREAKPOINT: usize = 3;
+const LOAD_MISALIGNED: usize = 4;
+const LOAD_FAULT: usize = 5;
+const STORE_MISALIGNED: usize = 6;
+const STORE_FAULT: usize = 7;
 const ENVIRONMENT_CALL: usize = 8;
 const INSTRUCTION_PAGE_FAULT: usize = 12;
+const LOAD_PAGE_FAULT: usize = 13;
+const STORE_PAGE_FAULT: usize = 15;
+const SUPERVISOR_SOFTWARE_INTERRUPT: usize = (1 << 63) + 1;
 const SUPERVISOR_TIMER_INTERRUPT: usize = (1 << 63) + 5;
+const SUPERVISOR_EXTERNAL_INTERRUPT: usize = (1 << 63) + 9;
+
+/// sie中的SEIE位
+const SEIE: usize = 1 << 9;
 
 /// 初始化中断向量
 pub fn init() {
@@ -22,8 +49,11 @@ pub fn init() {
 
 /// 设置内核态中断地址
 fn set_kernel_interrupt() {
+    extern "C" {
+        fn __kernel_trap();
+    }
     unsafe {
-        core::arch::asm!("csrw stvec, {}", in(reg) interrupt_kernel as usize);
+        core::arch::asm!("csrw stvec, {}", in(reg) __kernel_trap as usize);
     };
 }
 
@@ -34,26 +64,185 @@ fn set_user_trap_entry() {
     };
 }
 
-/// 内核态中断处理程序
+/// 开启内核态中断
+pub fn enable_interrupt() {
+    unsafe {
+        // set SIE bit
+        core::arch::asm!("csrsi sstatus, 2");
+    }
+}
+
+/// 关闭内核态中断，返回之前是否开启
+pub fn disable_interrupt() -> bool {
+    let sstatus: usize;
+    unsafe {
+        core::arch::asm!("csrrci {}, sstatus, 2", out(reg) sstatus);
+    }
+    sstatus & 2 != 0
+}
+
+/// 恢复disable_interrupt之前的中断状态
+pub fn restore_interrupt(enabled: bool) {
+    if enabled {
+        enable_interrupt();
+    }
+}
+
+/// 内核态收到的外部中断推迟处理：被打断的代码可能正在使用设备驱动或调度器的数据
+/// 屏蔽本核的外部中断，中断在PLIC中保持挂起
+fn defer_external_interrupt() {
+    unsafe {
+        core::arch::asm!("csrc sie, {}", in(reg) SEIE);
+    }
+}
+
+/// 处理被推迟的外部中断并重新开启，调用时不能持有任何借用
+pub fn handle_deferred_interrupt() {
+    let sie: usize;
+    unsafe {
+        core::arch::asm!("csrr {}, sie", out(reg) sie);
+    }
+    if sie & SEIE == 0 {
+        handle_irqs();
+        unsafe {
+            core::arch::asm!("csrs sie, {}", in(reg) SEIE);
+        }
+    }
+}
+
+/// 异常或中断的名字
+fn trap_name(scause: usize) -> &'static str {
+    match scause {
+        INSTRUCTION_MISALIGNED => "InstructionMisaligned",
+        INSTRUCTION_FAULT => "InstructionFault",
+        ILLEGAL_INSTRUCTION => "IllegalInstruction",
+        BREAKPOINT => "Breakpoint",
+        LOAD_MISALIGNED => "LoadMisaligned",
+        LOAD_FAULT => "LoadFault",
+        STORE_MISALIGNED => "StoreMisaligned",
+        STORE_FAULT => "StoreFault",
+        ENVIRONMENT_CALL => "UserEnvCall",
+        9 => "SupervisorEnvCall",
+        INSTRUCTION_PAGE_FAULT => "InstructionPageFault",
+        LOAD_PAGE_FAULT => "LoadPageFault",
+        STORE_PAGE_FAULT => "StorePageFault",
+        SUPERVISOR_SOFTWARE_INTERRUPT => "SupervisorSoft",
+        SUPERVISOR_TIMER_INTERRUPT => "SupervisorTimer",
+        SUPERVISOR_EXTERNAL_INTERRUPT => "SupervisorExternal",
+        _ if scause >> 63 != 0 => "UnknownInterrupt",
+        _ => "UnknownException",
+    }
+}
+
+/// 从指令的第一个半字得到指令长度：低两位为0b11的是4字节指令，否则为2字节的压缩指令
+fn inst_len(halfword: u16) -> usize {
+    if halfword & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// 读取当前进程地址空间中的半字（用于取指令），页面被换出时先换入，不可访问时返回None
+fn read_user_halfword(vaddr: VirtAddr) -> Option<u16> {
+    let process = get_current_process();
+    let mut inner = process.inner.lock();
+    let vpn = vaddr.vpn();
+    if inner.memory_set.translate(vpn).is_none() {
+        inner.memory_set.handle_page_fault(vpn, X).ok()?;
+    }
+    let ppn = inner.memory_set.translate(vpn)?;
+    Some(*PhysAddr(ppn.addr().0 + vaddr.page_offset()).get_mut::<u16>())
+}
+
+/// 通用寄存器的ABI名
+const REG_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NOT_IN_FAULT: AtomicBool = AtomicBool::new(false);
+
+/// 各核是否正在报告内核错误，报告过程中再次出错时不再打印现场
+static IN_KERNEL_FAULT: [AtomicBool; MAX_HARTS] = [NOT_IN_FAULT; MAX_HARTS];
+
+/// 内核代码本身出错：打印现场后停机
+fn kernel_fault(context: &KernelContext, scause: usize, stval: usize) -> ! {
+    if IN_KERNEL_FAULT[hart_id()].swap(true, Ordering::Relaxed) {
+        panic!(
+            "[kernel] Nested {} at 0x{:x} while reporting a kernel fault",
+            trap_name(scause),
+            context.sepc
+        );
+    }
+    println!(
+        "[kernel] {} in kernel on hart {}",
+        trap_name(scause),
+        hart_id()
+    );
+    println!(
+        "sepc: {:#018x} stval: {:#018x} sstatus: {:#018x}",
+        context.sepc, stval, context.sstatus
+    );
+    if let Some(process) = try_get_current_task().and_then(|task| task.process.upgrade()) {
+        println!("current process: {}", process.pid.0);
+    }
+    for row in (0..32).step_by(4) {
+        println!(
+            "{:>4}: {:#018x}  {:>4}: {:#018x}  {:>4}: {:#018x}  {:>4}: {:#018x}",
+            REG_NAMES[row],
+            context.x[row],
+            REG_NAMES[row + 1],
+            context.x[row + 1],
+            REG_NAMES[row + 2],
+            context.x[row + 2],
+            REG_NAMES[row + 3],
+            context.x[row + 3]
+        );
+    }
+    panic!("Unresolved kernel trap: {:?}", scause);
+}
+
+/// 内核态中断处理程序，返回后继续执行被打断的内核代码
+/// 中断可以在系统调用期间处理；内核代码本身的异常视为错误
 #[no_mangle]
-pub fn interrupt_kernel() -> ! {
+pub extern "C" fn kernel_trap_handler(context: &mut KernelContext) {
     let mut scause: usize;
     let mut stval: usize;
-    let mut sepc: usize;
     unsafe {
-        core::arch::asm!("csrr {}, scause","csrr {}, stval","csrr {}, sepc", out(reg) scause, out(reg) stval, out(reg) sepc);
+        core::arch::asm!("csrr {}, scause","csrr {}, stval", out(reg) scause, out(reg) stval);
+    }
+    match scause {
+        SUPERVISOR_TIMER_INTERRUPT => {
+            // 被打断的代码可能正在使用任务或调度器的数据，到可以安全切换的位置再调度
+            timer::stop_timer();
+            request_resched();
+        }
+        SUPERVISOR_SOFTWARE_INTERRUPT => clear_ipi(),
+        // 在返回用户态或空闲等待前处理
+        SUPERVISOR_EXTERNAL_INTERRUPT => defer_external_interrupt(),
+        BREAKPOINT => {
+            println!("[kernel] Breakpoint at 0x{:x}", context.sepc);
+            // 压缩指令c.ebreak长2字节
+            context.sepc += inst_len(unsafe { *(context.sepc as *const u16) });
+        }
+        _ => kernel_fault(context, scause, stval),
     }
-    panic!(
-        "[kernel] Multi-interrupt:\nscause: {:?} stval: {:x} sepc: {:x}",
-        scause, stval, sepc
-    );
 }
 
 /// 用户态中断处理程序
 #[no_mangle]
 pub fn interrupt_handler() -> ! {
+    leave_user();
+    lock_kernel();
     set_kernel_interrupt();
-    let context = get_current_process().inner.borrow_mut().trap_cx();
+    // 所属进程已在其他核上退出或执行了exec
+    exit_if_killed();
+    // 自上次返回用户态以来的时间为用户态时间
+    account_current_time(true);
+    let context = get_current_task().inner.lock().trap_cx();
     let mut scause: usize;
     let mut stval: usize;
     unsafe {
@@ -62,37 +251,108 @@ pub fn interrupt_handler() -> ! {
     match scause {
         BREAKPOINT => {
             println!("Breakpoint at 0x{:x}", context.sepc);
-            context.sepc += 2;
+            // 与内核态相同，按指令长度区分ebreak与c.ebreak
+            match read_user_halfword(VirtAddr(context.sepc)) {
+                Some(halfword) => context.sepc += inst_len(halfword),
+                None => {
+                    println!(
+                        "[kernel] Cannot fetch instruction at 0x{:x}, kernel killed it.",
+                        context.sepc
+                    );
+                    kill_current_and_run_next(SIGSEGV);
+                }
+            }
+        }
+        // 核间中断只用于让本核离开用户态（刷新TLB）
+        SUPERVISOR_SOFTWARE_INTERRUPT => clear_ipi(),
+        SUPERVISOR_TIMER_INTERRUPT => {
+            poll_console();
+            schedule_callback();
         }
-        SUPERVISOR_TIMER_INTERRUPT => schedule_callback(),
+        SUPERVISOR_EXTERNAL_INTERRUPT => handle_irqs(),
         ENVIRONMENT_CALL => {
             context.sepc += 4;
-            let ret_code = sys_call(context.x[17], [context.x[10], context.x[11], context.x[12]]);
-            let context = get_current_process().inner.borrow_mut().trap_cx();
+            // 系统调用期间允许中断，时钟中断只标记需要调度，在返回用户态前让出处理器
+            enable_interrupt();
+            let args = [
+                context.x[10],
+                context.x[11],
+                context.x[12],
+                context.x[13],
+                context.x[14],
+            ];
+            let ret_code = sys_call(context.x[17], args);
+            disable_interrupt();
+            let context = get_current_task().inner.lock().trap_cx();
             context.x[10] = ret_code as usize;
         }
         ILLEGAL_INSTRUCTION => {
-            unsafe {
-                let vaddr = crate::memory::frame::address::VirtAddr(context.sepc);
-                let token = get_current_process().inner.borrow().token();
-                let ppn = crate::memory::frame::page_table::PageTable::from_token(token)
-                    .translate(vaddr.vpn())
-                    .unwrap();
-                let paddr = ppn.addr().0 + vaddr.page_offset();
-                println!(
-                    "[kernel] IllegalInstruction at 0x{:x}: {}, kernel killed it.",
-                    context.sepc,
-                    *(paddr as *const u32)
-                );
+            // 32位指令可能跨页，分两个半字读取
+            let sepc = context.sepc;
+            let inst = match read_user_halfword(VirtAddr(sepc)) {
+                Some(low) if inst_len(low) == 4 => read_user_halfword(VirtAddr(sepc + 2))
+                    .map(|high| (high as u32) << 16 | low as u32),
+                low => low.map(u32::from),
+            };
+            match inst {
+                Some(inst) => println!(
+                    "[kernel] IllegalInstruction at 0x{:x}: 0x{:x}, kernel killed it.",
+                    sepc, inst
+                ),
+                None => println!(
+                    "[kernel] IllegalInstruction at 0x{:x}, kernel killed it.",
+                    sepc
+                ),
+            }
+            kill_current_and_run_next(SIGILL);
+        }
+        INSTRUCTION_PAGE_FAULT | LOAD_PAGE_FAULT | STORE_PAGE_FAULT => {
+            let (access, name) = match scause {
+                INSTRUCTION_PAGE_FAULT => (X, "InstructionPageFault"),
+                LOAD_PAGE_FAULT => (R, "LoadPageFault"),
+                _ => (W, "StorePageFault"),
+            };
+            let result = get_current_process()
+                .inner
+                .lock()
+                .memory_set
+                .handle_page_fault(VirtAddr(stval).vpn(), access);
+            match result {
+                Ok(()) => {}
+                Err(ENOMEM) => {
+                    println!(
+                        "[kernel] Out of memory at 0x{:x}, address 0x{:x}, kernel killed it.",
+                        context.sepc, stval
+                    );
+                    kill_current_and_run_next(SIGKILL);
+                }
+                Err(_) => {
+                    println!(
+                        "[kernel] {} at 0x{:x}, address 0x{:x}, kernel killed it.",
+                        name, context.sepc, stval
+                    );
+                    kill_current_and_run_next(SIGSEGV);
+                }
             }
-            exit_current_and_run_next(-1);
         }
-        INSTRUCTION_PAGE_FAULT => {
+        // 未对齐的访存按SIGBUS、访问错误按SIGSEGV结束进程
+        INSTRUCTION_MISALIGNED
+        | LOAD_MISALIGNED
+        | STORE_MISALIGNED
+        | INSTRUCTION_FAULT
+        | LOAD_FAULT
+        | STORE_FAULT => {
             println!(
-                "[kernel] InstructionPageFault at 0x{:x}, kernel killed it.",
+                "[kernel] {} at 0x{:x}, address 0x{:x}, kernel killed it.",
+                trap_name(scause),
                 context.sepc,
+                stval
             );
-            exit_current_and_run_next(-2);
+            let signum = match scause {
+                INSTRUCTION_MISALIGNED | LOAD_MISALIGNED | STORE_MISALIGNED => SIGBUS,
+                _ => SIGSEGV,
+            };
+            kill_current_and_run_next(signum);
         }
         _ => {
             panic!(
@@ -106,8 +366,20 @@ pub fn interrupt_handler() -> ! {
 
 /// 中断恢复程序
 pub fn interrupt_return() -> ! {
+    disable_interrupt();
+    // 新线程第一次运行时从这里开始，需要释放被换下的线程
+    reap_prev_task();
+    handle_deferred_interrupt();
+    cond_resched();
+    handle_signals();
+    account_current_time(false);
     set_user_trap_entry();
-    let user_satp = get_current_process().inner.borrow().token();
+    let user_satp = get_current_process().inner.lock().token();
+    let trap_cx_user_va = get_current_task().inner.lock().trap_cx_user_va();
+    // 线程可能在其他核上再次进入中断
+    get_current_task().inner.lock().trap_cx().hart_id = hart_id();
+    enter_user(user_satp);
+    unlock_kernel();
     extern "C" {
         fn __interrupt();
         fn __restore();
@@ -119,9 +391,26 @@ pub fn interrupt_return() -> ! {
             "fence.i",
             "jr {restore_va}",
             restore_va = in(reg) restore_va,
-            in("a0") TRAP_CONTEXT,      // 固定的用户空间context位置
+            in("a0") trap_cx_user_va,   // 当前线程的用户空间context位置
             in("a1") user_satp,
             options(noreturn)
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    test!(test_kernel_trap, {
+        let enabled = disable_interrupt();
+        enable_interrupt();
+        test_assert!(disable_interrupt());
+        test_assert!(!disable_interrupt());
+        restore_interrupt(enabled);
+        // 内核态断点可以恢复执行
+        unsafe {
+            core::arch::asm!("ebreak");
+        }
+        Ok("passed")
+    });
+}
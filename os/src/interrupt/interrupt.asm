@@ -54,6 +54,8 @@ __interrupt:
     LOAD    t1, 34
     # 加载trap_handler
     LOAD    t2, 36
+    # 加载核号
+    LOAD    tp, 37
     # 加载kernel的sp
     LOAD    sp, 35
     # mv      a0, sp                              # context: &mut Context
@@ -91,4 +93,41 @@ __restore:
     .endr
     # 切换到用户栈(Context.x[2])
     LOAD sp, 2
-    sret
\ No newline at end of file
+    sret
+
+# 内核态中断入口（不在跳板页中）
+# 在当前内核栈上保存 KernelContext 并调用 interrupt::handler::kernel_trap_handler()，可以嵌套
+    .section .text
+    .globl __kernel_trap
+    .align 2
+__kernel_trap:
+    addi    sp, sp, -CONTEXT_SIZE * REG_SIZE
+    SAVE    x1, 1
+    # 保存中断前的 sp
+    addi    x1, sp, CONTEXT_SIZE * REG_SIZE
+    SAVE    x1, 2
+    .set    n, 3
+    .rept   29
+        SAVE_N  %n
+        .set    n, n + 1
+    .endr
+    csrr    t1, sstatus
+    SAVE    t1, 32
+    csrr    t2, sepc
+    SAVE    t2, 33
+    # context: &mut KernelContext
+    mv      a0, sp
+    call    kernel_trap_handler
+    # 恢复 CSR，嵌套中断返回后 sstatus 与 sepc 已被覆盖
+    LOAD    t1, 32
+    csrw    sstatus, t1
+    LOAD    t2, 33
+    csrw    sepc, t2
+    LOAD    x1, 1
+    .set    n, 3
+    .rept   29
+        LOAD_N  %n
+        .set    n, n + 1
+    .endr
+    addi    sp, sp, CONTEXT_SIZE * REG_SIZE
+    sret
@@ -12,3 +12,9 @@ pub fn init() {
     timer::init();
     println!("mod interrupt initialized!");
 }
+
+/// 初始化其余核的中断向量与时钟中断
+pub fn init_hart() {
+    handler::init();
+    timer::init_hart();
+}
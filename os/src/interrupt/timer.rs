@@ -2,7 +2,64 @@
 
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::SpinLock;
 use crate::task::schd::get_default_time_slice;
+use crate::task::ThreadControlBlock;
+use crate::tools::uninit_cell::UninitCell;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 与用户库中定义一致的时间结构
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    /// 从毫秒数构造
+    pub fn from_ms(ms: usize) -> Self {
+        Self {
+            sec: ms / 1000,
+            nsec: ms % 1000 * 1_000_000,
+        }
+    }
+
+    /// 转换为毫秒数（不足1ms的部分向上取整），溢出时取usize::MAX
+    pub fn to_ms(&self) -> usize {
+        let ms = self.nsec / 1_000_000 + (self.nsec % 1_000_000 != 0) as usize;
+        self.sec.saturating_mul(1000).saturating_add(ms)
+    }
+}
+
+/// 与用户库中定义一致的时间结构（微秒精度）
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    /// 从时钟周期数构造
+    pub fn from_ticks(ticks: usize) -> Self {
+        Self {
+            sec: ticks / CLOCK_FREQ,
+            usec: ticks % CLOCK_FREQ / (CLOCK_FREQ / 1_000_000),
+        }
+    }
+}
+
+/// 睡眠中的线程
+struct SleepingTask {
+    expire_ms: usize,
+    task: Arc<ThreadControlBlock>,
+}
+
+/// 按唤醒时间排序的睡眠队列
+static mut SLEEPING_TASKS: UninitCell<SpinLock<VecDeque<SleepingTask>>> = UninitCell::uninit();
 
 /// 读取time寄存器
 pub fn get_time() -> usize {
@@ -13,9 +70,14 @@ pub fn get_time() -> usize {
     time
 }
 
+/// 将时钟周期数转换为毫秒数
+pub fn ticks_to_ms(ticks: usize) -> usize {
+    ticks / (CLOCK_FREQ / 1000)
+}
+
 /// 获取系统时钟(ms)
 pub fn get_time_ms() -> usize {
-    get_time() / (CLOCK_FREQ / 1000)
+    ticks_to_ms(get_time())
 }
 
 /// 开启时钟中断
@@ -31,8 +93,95 @@ pub fn set_next_timeout(interval: usize) {
     set_timer(get_time() + interval * (CLOCK_FREQ / 1000));
 }
 
+/// 推迟时钟中断，直到下次调用set_next_timeout
+pub fn stop_timer() {
+    set_timer(usize::MAX);
+}
+
+/// 将线程加入睡眠队列，在expire_ms时刻后唤醒
+pub fn add_sleeping_task(expire_ms: usize, task: Arc<ThreadControlBlock>) {
+    let mut sleeping_tasks = unsafe { SLEEPING_TASKS.lock() };
+    let pos = sleeping_tasks.partition_point(|sleeping| sleeping.expire_ms <= expire_ms);
+    sleeping_tasks.insert(pos, SleepingTask { expire_ms, task });
+}
+
+/// 将线程移出睡眠队列（被信号提前唤醒时使用）
+pub fn remove_sleeping_task(task: &Arc<ThreadControlBlock>) {
+    unsafe {
+        SLEEPING_TASKS
+            .lock()
+            .retain(|sleeping| !Arc::ptr_eq(&sleeping.task, task));
+    }
+}
+
+/// 取出所有已到期的睡眠线程
+pub fn take_expired_tasks() -> Vec<Arc<ThreadControlBlock>> {
+    let current_ms = get_time_ms();
+    let mut expired = Vec::new();
+    let mut sleeping_tasks = unsafe { SLEEPING_TASKS.lock() };
+    while let Some(sleeping) = sleeping_tasks.front() {
+        if sleeping.expire_ms > current_ms {
+            break;
+        }
+        expired.push(sleeping_tasks.pop_front().unwrap().task);
+    }
+    expired
+}
+
 /// 时钟初始化
 pub fn init() {
+    unsafe {
+        SLEEPING_TASKS = UninitCell::init(SpinLock::new(VecDeque::new()));
+    }
+    init_hart();
+}
+
+/// 开启当前核的时钟中断（每个核有独立的计时器）
+pub fn init_hart() {
     enable_timer_interrupt();
     set_next_timeout(get_default_time_slice());
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fs::rfs::find_inode;
+    use crate::task::ProcessControlBlock;
+    use alloc::vec;
+
+    test!(test_sleeping_tasks, {
+        let time = TimeSpec::from_ms(1500);
+        test_assert!(time.sec == 1 && time.nsec == 500_000_000);
+        test_assert!(TimeSpec { sec: 0, nsec: 1 }.to_ms() == 1);
+        test_assert!(
+            TimeSpec {
+                sec: usize::MAX,
+                nsec: 999_999_999
+            }
+            .to_ms()
+                == usize::MAX
+        );
+        let time = TimeVal::from_ticks(CLOCK_FREQ * 3 / 2);
+        test_assert!(time.sec == 1 && time.usec == 500_000);
+        test_assert!(ticks_to_ms(CLOCK_FREQ) == 1000);
+
+        let app_inode = find_inode("/bin/daemon").expect("[kernel] daemon not found!");
+        let mut app_data = vec![0u8; app_inode.get_file_size() as usize];
+        app_inode.read_at(0, &mut app_data);
+        let pcb1 = ProcessControlBlock::new(&app_data);
+        let pcb2 = ProcessControlBlock::new(&app_data);
+        let pcb3 = ProcessControlBlock::new(&app_data);
+        let tcb1 = pcb1.inner.lock().threads[0].clone().unwrap();
+        let tcb2 = pcb2.inner.lock().threads[0].clone().unwrap();
+        let tcb3 = pcb3.inner.lock().threads[0].clone().unwrap();
+        add_sleeping_task(1, tcb1.clone());
+        add_sleeping_task(0, tcb2.clone());
+        add_sleeping_task(usize::MAX, tcb3.clone());
+        let expired = take_expired_tasks();
+        test_assert!(expired.len() == 2);
+        test_assert!(Arc::ptr_eq(&expired[0], &tcb2) && Arc::ptr_eq(&expired[1], &tcb1));
+        remove_sleeping_task(&tcb3);
+        test_assert!(unsafe { SLEEPING_TASKS.lock().is_empty() });
+        Ok("passed")
+    });
+}
@@ -2,6 +2,7 @@
 #![no_main]
 #![feature(panic_info_message)]
 #![feature(alloc_error_handler)]
+#![feature(asm_const)]
 #![feature(custom_test_frameworks)]
 #![feature(step_trait)]
 #![test_runner(test::test_runner)]
@@ -20,24 +21,46 @@ mod interrupt;
 mod memory;
 mod panic;
 mod sbi;
+mod smp;
+mod sync;
 mod sys_call;
 mod task;
 mod tools;
 use core::arch::global_asm;
 
-global_asm!(include_str!("entry.asm"));
+global_asm!(
+    include_str!("entry.asm"),
+    BOOT_STACK_SIZE = const config::BOOT_STACK_SIZE,
+    MAX_HARTS = const config::MAX_HARTS,
+);
 
 /// This is where we start.
 #[no_mangle]
 pub extern "C" fn rust_main() -> ! {
+    smp::lock_kernel();
     memory::init();
     interrupt::init();
     drivers::init();
     fs::init();
+    memory::init_swap();
     task::init();
     #[cfg(test)]
     test_main();
     println!("[kernel] Hello rusted_os!");
+    smp::init();
+    task::run();
+    panic!("Dummy as fuck");
+}
+
+/// 其余核从这里开始
+#[no_mangle]
+pub extern "C" fn rust_main_secondary() -> ! {
+    smp::lock_kernel();
+    memory::init_hart();
+    interrupt::init_hart();
+    drivers::init_hart();
+    smp::init_hart();
+    println!("[kernel] hart {} started", smp::hart_id());
     task::run();
     panic!("Dummy as fuck");
 }
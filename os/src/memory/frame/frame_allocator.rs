@@ -1,6 +1,8 @@
 //! 页式内存分配器子模块
 use super::address::{PhysAddr, PhysPageNum};
+use super::swap::swap_out_one;
 use crate::config::{MEMORY_END_ADDR, PAGE_SIZE};
+use crate::sync::SpinLock;
 use crate::tools::uninit_cell::UninitCell;
 use alloc::collections::VecDeque;
 
@@ -27,13 +29,13 @@ impl FrameTracker {
 impl Drop for FrameTracker {
     fn drop(&mut self) {
         unsafe {
-            FRAME_ALLOCATOR.dealloc(self.0);
+            FRAME_ALLOCATOR.lock().dealloc(self.0);
         }
     }
 }
 
 /// 全局物理页分配器实例
-static mut FRAME_ALLOCATOR: UninitCell<FrameAllocator> = UninitCell::uninit();
+static mut FRAME_ALLOCATOR: UninitCell<SpinLock<FrameAllocator>> = UninitCell::uninit();
 
 /// 栈式物理页面分配器
 pub struct FrameAllocator {
@@ -76,8 +78,16 @@ impl FrameAllocator {
     }
 }
 
+/// 分配物理页面，物理页耗尽时换出用户页面
 pub fn frame_alloc() -> Option<FrameTracker> {
-    unsafe { FRAME_ALLOCATOR.alloc() }
+    loop {
+        if let Some(frame) = unsafe { FRAME_ALLOCATOR.lock().alloc() } {
+            return Some(frame);
+        }
+        if !swap_out_one() {
+            return None;
+        }
+    }
 }
 
 pub fn init() {
@@ -87,10 +97,10 @@ pub fn init() {
     let kernel_end_addr = kernel_end as usize;
     let frame_start_num = PhysPageNum((kernel_end_addr + PAGE_SIZE - 1) / PAGE_SIZE);
     unsafe {
-        FRAME_ALLOCATOR = UninitCell::init(FrameAllocator::new(
+        FRAME_ALLOCATOR = UninitCell::init(SpinLock::new(FrameAllocator::new(
             frame_start_num,
             PhysAddr(MEMORY_END_ADDR).ppn(),
-        ));
+        )));
     }
 }
 
@@ -99,7 +109,7 @@ mod test {
     use super::*;
     test!(test_frame_allocator, {
         unsafe {
-            let start_ppn = FRAME_ALLOCATOR.curr_ppn;
+            let start_ppn = FRAME_ALLOCATOR.lock().curr_ppn;
             let f1 = frame_alloc().expect("No space");
             test_assert!(
                 f1.ppn() == PhysPageNum(start_ppn.0),
@@ -111,27 +121,30 @@ mod test {
                     f2.ppn() == PhysPageNum(start_ppn.0 + 1),
                     "Wrong frame allocated"
                 );
+                let allocator = FRAME_ALLOCATOR.lock();
                 test_assert!(
-                    FRAME_ALLOCATOR.curr_ppn == PhysPageNum(start_ppn.0 + 2)
-                        && FRAME_ALLOCATOR.recycled.is_empty(),
+                    allocator.curr_ppn == PhysPageNum(start_ppn.0 + 2)
+                        && allocator.recycled.is_empty(),
                     "Alloc error"
                 );
             }
+            let allocator = FRAME_ALLOCATOR.lock();
             test_assert!(
-                FRAME_ALLOCATOR.curr_ppn == PhysPageNum(start_ppn.0 + 2)
-                    && FRAME_ALLOCATOR.recycled.len() == 1,
+                allocator.curr_ppn == PhysPageNum(start_ppn.0 + 2) && allocator.recycled.len() == 1,
                 "Dealloc error"
             );
+            drop(allocator);
             let f2 = frame_alloc().expect("No space");
             test_assert!(
                 f2.ppn() == PhysPageNum(start_ppn.0 + 1),
                 "Wrong frame allocated"
             );
+            let allocator = FRAME_ALLOCATOR.lock();
             assert!(
-                FRAME_ALLOCATOR.curr_ppn == PhysPageNum(start_ppn.0 + 2)
-                    && FRAME_ALLOCATOR.recycled.is_empty(),
+                allocator.curr_ppn == PhysPageNum(start_ppn.0 + 2) && allocator.recycled.is_empty(),
                 "Alloc error"
             );
+            drop(allocator);
         }
         Ok("passed")
     });
@@ -2,12 +2,21 @@
 use core::ops::Range;
 
 use super::address::*;
-use super::page_table::{PageTable, R, U, W, X};
+use super::frame_allocator::frame_alloc;
+use super::page_table::{PTEFlags, PageTable, A, D, R, U, W, X};
 use super::segment::{MemorySegment, SegFlags};
-use crate::config::{MEMORY_END_ADDR, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use super::swap::{self, is_shared, Page};
+use crate::config::{MEMORY_END_ADDR, MMAP_BASE, MMIO, PAGE_SIZE, TRAMPOLINE};
+use crate::smp::tlb_shootdown;
+use crate::sync::SpinLock;
+use crate::sys_call::errno::{Errno, EFAULT, ENOEXEC, ENOMEM};
 use crate::tools::elf_decoder::ElfFile;
 use crate::tools::uninit_cell::UninitCell;
+use alloc::collections::btree_map::Entry;
+use alloc::sync::Arc;
 use alloc::{vec, vec::Vec};
+use core::mem::take;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 extern "C" {
     fn text_start();
@@ -22,23 +31,37 @@ extern "C" {
     fn kernel_end();
 }
 
-/// 内核地址空间
-pub static mut KERNEL_MEMORY_SET: UninitCell<MemorySet> = UninitCell::uninit();
+/// 内核地址空间，分配与释放内核栈时修改
+pub static mut KERNEL_MEMORY_SET: UninitCell<SpinLock<MemorySet>> = UninitCell::uninit();
+
+/// 内核地址空间的token，初始化后不变，读取时不需要加锁
+static KERNEL_TOKEN: AtomicUsize = AtomicUsize::new(0);
+
+/// 内核地址空间的token
+pub fn kernel_token() -> usize {
+    KERNEL_TOKEN.load(Ordering::Relaxed)
+}
 
 /// 地址空间
 #[derive(Debug)]
 pub struct MemorySet {
     page_table: PageTable,
     segments: Vec<MemorySegment>,
+    /// 堆段起始地址（紧接在elf之后）
+    heap_bottom: usize,
+    /// 当前堆顶
+    brk: usize,
 }
 
 impl MemorySet {
-    /// 创建空地址空间
-    pub fn new() -> Self {
-        Self {
-            page_table: PageTable::new(),
+    /// 创建空地址空间，内存不足时返回ENOMEM
+    pub fn new() -> Result<Self, Errno> {
+        Ok(Self {
+            page_table: PageTable::new().ok_or(ENOMEM)?,
             segments: vec![],
-        }
+            heap_bottom: 0,
+            brk: 0,
+        })
     }
 
     /// 创建新内核地址空间
@@ -51,47 +74,44 @@ impl MemorySet {
         // println!(".data [{:x}, {:x})", data_start as usize, data_end as usize);
         // println!(".bss [{:x}, {:x})", bss_start as usize, bss_end as usize);
         // println!("kernel end at {:x}", kernel_end as usize);
-        let mut memory_set = Self::new();
-        memory_set.map_trampoline();
-        for vpn in VirtAddr(text_start as usize).vpn()..VirtAddr(text_end as usize).vpn() {
-            memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R | X);
-        }
-        for vpn in VirtAddr(rodata_start as usize).vpn()..VirtAddr(rodata_end as usize).vpn() {
-            memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R);
-        }
-        for vpn in VirtAddr(data_start as usize).vpn()..VirtAddr(data_end as usize).vpn() {
-            memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R | W);
-        }
-        for vpn in VirtAddr(bss_start as usize).vpn()..VirtAddr(bss_end as usize).vpn() {
-            memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R | W);
-        }
-        for vpn in VirtAddr(kernel_end as usize).vpn()..VirtAddr(MEMORY_END_ADDR).vpn() {
-            memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R | W);
-        }
+        let mut memory_set = Self::new().expect("[kernel] Out of memory!");
+        memory_set
+            .map_trampoline()
+            .expect("[kernel] Out of memory!");
+        memory_set.map_identical(text_start as usize..text_end as usize, R | X);
+        memory_set.map_identical(rodata_start as usize..rodata_end as usize, R);
+        memory_set.map_identical(data_start as usize..data_end as usize, R | W);
+        memory_set.map_identical(bss_start as usize..bss_end as usize, R | W);
+        memory_set.map_identical(kernel_end as usize..MEMORY_END_ADDR, R | W);
         for pair in MMIO {
-            for vpn in VirtAddr(pair.0).vpn()..VirtAddr(pair.0 + pair.1).vpn() {
-                memory_set.page_table.map(vpn, PhysPageNum(vpn.0), R | W);
-            }
+            memory_set.map_identical(pair.0..pair.0 + pair.1, R | W);
         }
         memory_set
     }
 
-    /// 创建新用户程序地址空间
-    /// 返回用户地址空间，用户栈地址，用户程序入口
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new();
-        let elf = ElfFile::new(elf_data).expect("[kernel] Invalid elf file!");
-        let mut max_end_vpn = VirtPageNum(0);
-        memory_set.map_trampoline();
+    /// 恒等映射内核地址区间，仅在启动时使用
+    fn map_identical(&mut self, range: Range<usize>, flags: PTEFlags) {
+        for vpn in VirtAddr(range.start).vpn()..VirtAddr(range.end).vpn() {
+            assert!(
+                self.page_table.map(vpn, PhysPageNum(vpn.0), flags),
+                "[kernel] Out of memory!"
+            );
+        }
+    }
+
+    /// 创建新用户程序地址空间（用户栈与中断上下文由各线程自行映射）
+    /// 返回用户地址空间，用户程序入口；elf无效时返回ENOEXEC，内存不足时返回ENOMEM
+    pub fn from_elf(elf_data: &[u8]) -> Result<(Self, usize), Errno> {
+        let elf = ElfFile::new(elf_data).ok_or(ENOEXEC)?;
+        let mut memory_set = Self::new()?;
+        memory_set.map_trampoline()?;
+        let mut heap_start = VirtPageNum(0);
         for ph in elf.program_headers {
             if !ph.is_load() {
                 continue;
             }
             let start_vpn = VirtAddr(ph.vaddr()).vpn();
             let end_vpn = VirtPageNum(VirtAddr(ph.vaddr() + ph.mem_size()).vpn().0 + 1);
-            if end_vpn > max_end_vpn {
-                max_end_vpn = end_vpn;
-            }
             let mut perm = U;
             if ph.is_readable() {
                 perm |= R
@@ -102,38 +122,73 @@ impl MemorySet {
             if ph.is_executable() {
                 perm |= X;
             }
-            memory_set.insert_segment(
+            // bss部分在首次访问时分配
+            memory_set.insert_lazy_segment(
                 start_vpn..end_vpn,
                 perm,
                 Some(&elf_data[ph.offset()..ph.offset() + ph.file_size()]),
-            );
+            )?;
+            heap_start = heap_start.max(end_vpn);
         }
-        let user_stack_start_vpn = VirtPageNum(max_end_vpn.0 + 1);
-        let user_stack_end_vpn = VirtPageNum(user_stack_start_vpn.0 + USER_STACK_SIZE / PAGE_SIZE);
-        memory_set.insert_segment(user_stack_start_vpn..user_stack_end_vpn, U | R | W, None);
-        memory_set.insert_segment(
-            VirtAddr(TRAP_CONTEXT).vpn()..VirtAddr(TRAMPOLINE).vpn(),
-            R | W,
-            None,
-        );
-        (memory_set, user_stack_end_vpn.addr().0, elf.header.entry())
+        // 堆初始为空，通过brk扩展
+        memory_set.insert_lazy_segment(heap_start..heap_start, U | R | W, None)?;
+        memory_set.heap_bottom = heap_start.addr().0;
+        memory_set.brk = memory_set.heap_bottom;
+        Ok((memory_set, elf.header.entry()))
     }
 
-    /// 在此地址空间中添加映射并分配物理页
+    /// 在此地址空间中添加映射并分配物理页，内存不足时返回ENOMEM
     pub fn insert_segment(
         &mut self,
         vpn_range: Range<VirtPageNum>,
         seg_flags: SegFlags,
         data: Option<&[u8]>,
-    ) {
-        let segment = MemorySegment::new(vpn_range, seg_flags);
+    ) -> Result<(), Errno> {
+        let segment = MemorySegment::new(vpn_range, seg_flags).ok_or(ENOMEM)?;
+        self.push_segment(segment, data)
+    }
+
+    /// 在此地址空间中添加映射，仅为数据所在页面分配物理页，其余页面在首次访问时分配
+    pub fn insert_lazy_segment(
+        &mut self,
+        vpn_range: Range<VirtPageNum>,
+        seg_flags: SegFlags,
+        data: Option<&[u8]>,
+    ) -> Result<(), Errno> {
+        self.push_segment(MemorySegment::new_lazy(vpn_range, seg_flags), data)
+    }
+
+    fn push_segment(
+        &mut self,
+        mut segment: MemorySegment,
+        data: Option<&[u8]>,
+    ) -> Result<(), Errno> {
         if let Some(data) = data {
-            segment.copy_data(data);
+            if !segment.copy_data(data) {
+                return Err(ENOMEM);
+            }
         }
-        for (&vpn, frame) in &segment.data_frames {
-            self.page_table.map(vpn, frame.ppn(), seg_flags);
+        for (&vpn, page) in &segment.data_frames {
+            if !self.page_table.map(vpn, page.ppn().unwrap(), segment.flags) {
+                // 撤销已建立的映射，物理页随段一起释放
+                for &mapped in segment
+                    .data_frames
+                    .keys()
+                    .take_while(|&&mapped| mapped != vpn)
+                {
+                    self.page_table.unmap(mapped);
+                }
+                return Err(ENOMEM);
+            }
+        }
+        // 映射全部建立后才允许换出
+        if segment.flags & U != 0 {
+            for (&vpn, page) in &segment.data_frames {
+                swap::track(page, self.satp_token(), vpn);
+            }
         }
         self.segments.push(segment);
+        Ok(())
     }
 
     /// 从地址空间中删除指定映射
@@ -143,19 +198,318 @@ impl MemorySet {
             .iter()
             .position(|segment| segment.vpn_range.start == start_vpn)
             .unwrap();
-        for vpn in self.segments[segment_index].vpn_range.clone() {
+        for &vpn in self.segments[segment_index].data_frames.keys() {
             self.page_table.unmap(vpn);
         }
         self.segments.remove(segment_index);
+        self.flush_tlb();
+    }
+
+    /// 写时复制地址空间：用户可写段的物理页在两个地址空间中共享并改为只读，
+    /// 直到其中一方写入时再复制；内存不足时返回ENOMEM
+    pub fn fork(&mut self) -> Result<Self, Errno> {
+        let mut new_memory_set = MemorySet::new()?;
+        new_memory_set.map_trampoline()?;
+        new_memory_set.heap_bottom = self.heap_bottom;
+        new_memory_set.brk = self.brk;
+        for segment in &self.segments {
+            if segment.flags & U == 0 {
+                // 中断上下文等内核直接访问的页面不能共享
+                let mut data = Vec::new();
+                for vpn in segment.vpn_range.clone() {
+                    data.extend_from_slice(self.translate(vpn).unwrap().get_bytes_array());
+                }
+                new_memory_set.insert_segment(
+                    segment.vpn_range.clone(),
+                    segment.flags,
+                    Some(&data),
+                )?;
+                continue;
+            }
+            let mut new_segment = segment.share();
+            let flags = segment.flags & !W;
+            for (&vpn, page) in new_segment.data_frames.iter_mut() {
+                // 已换出的页面在各自缺页时换入
+                let pte = match self.page_table.translate_pte(vpn) {
+                    Some(pte) => pte,
+                    None => continue,
+                };
+                // 内核正在写入的页面不能共享，否则写入会落到子地址空间的副本中
+                if page.is_pinned() {
+                    let frame = frame_alloc().ok_or(ENOMEM)?;
+                    frame
+                        .ppn()
+                        .get_bytes_array()
+                        .copy_from_slice(pte.ppn().get_bytes_array());
+                    *page = Arc::new(Page::new(frame));
+                    if !new_memory_set
+                        .page_table
+                        .map(vpn, page.ppn().unwrap(), segment.flags)
+                    {
+                        return Err(ENOMEM);
+                    }
+                    swap::track(page, new_memory_set.satp_token(), vpn);
+                    continue;
+                }
+                // 子地址空间的页表项没有已修改标志，交换空间中的副本需要作废
+                if pte.flags() & D != 0 {
+                    page.mark_dirty();
+                }
+                self.page_table.remap(vpn, pte.ppn(), flags);
+                if !new_memory_set.page_table.map(vpn, pte.ppn(), flags) {
+                    return Err(ENOMEM);
+                }
+                swap::track(page, new_memory_set.satp_token(), vpn);
+            }
+            new_memory_set.segments.push(new_segment);
+        }
+        // 共享的页面已改为只读
+        self.flush_tlb();
+        Ok(new_memory_set)
+    }
+
+    /// 处理缺页：为惰性分配的页面分配物理页，为写时复制的页面复制物理页，
+    /// 或换入已被换出的页面；访问不合法时返回EFAULT，内存不足时返回ENOMEM
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: PTEFlags) -> Result<(), Errno> {
+        let token = self.satp_token();
+        let segment = match self
+            .segments
+            .iter_mut()
+            .find(|segment| segment.vpn_range.contains(&vpn))
+        {
+            Some(segment) if segment.flags & U != 0 && segment.flags & access == access => segment,
+            _ => return Err(EFAULT),
+        };
+        let dirty = if access & W != 0 { D } else { 0 };
+        let page = match segment.data_frames.entry(vpn) {
+            Entry::Vacant(entry) => {
+                let frame = frame_alloc().ok_or(ENOMEM)?;
+                let page = entry.insert(Arc::new(Page::new(frame)));
+                if !self
+                    .page_table
+                    .map(vpn, page.ppn().unwrap(), segment.flags | A | dirty)
+                {
+                    return Err(ENOMEM);
+                }
+                swap::track(page, token, vpn);
+                return Ok(());
+            }
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        let pte = self.page_table.translate_pte(vpn);
+        if let Some(pte) = pte {
+            if access & W == 0 || pte.writable() {
+                // 页面已映射且有权限，仅补上访问与修改标志
+                if access & W != 0 {
+                    page.mark_dirty();
+                }
+                self.page_table
+                    .remap(vpn, pte.ppn(), pte.flags() | A | dirty);
+                return Ok(());
+            }
+        }
+        let mut copied = false;
+        // 已没有其他地址空间共享此页时直接恢复写权限
+        if access & W != 0 && is_shared(page) {
+            let frame = frame_alloc().ok_or(ENOMEM)?;
+            let ppn = page.swap_in().ok_or(ENOMEM)?;
+            frame
+                .ppn()
+                .get_bytes_array()
+                .copy_from_slice(ppn.get_bytes_array());
+            *page = Arc::new(Page::new(frame));
+            copied = true;
+        }
+        let ppn = page.swap_in().ok_or(ENOMEM)?;
+        let flags = if is_shared(page) {
+            segment.flags & !W
+        } else {
+            segment.flags
+        };
+        if pte.is_some() {
+            self.page_table.remap(vpn, ppn, flags | A | dirty);
+        } else if !self.page_table.map(vpn, ppn, flags | A | dirty) {
+            return Err(ENOMEM);
+        }
+        if pte.is_none() || copied {
+            swap::track(page, token, vpn);
+        }
+        // 其余核上的线程可能缓存了指向原物理页的页表项
+        if copied {
+            tlb_shootdown(token);
+        }
+        if access & W != 0 {
+            page.mark_dirty();
+        }
+        Ok(())
+    }
+
+    /// 虚拟页对应的用户页面
+    pub fn page(&self, vpn: VirtPageNum) -> Option<Arc<Page>> {
+        self.segments
+            .iter()
+            .find(|segment| segment.vpn_range.contains(&vpn))
+            .and_then(|segment| segment.data_frames.get(&vpn))
+            .cloned()
+    }
+
+    /// 虚拟页区间是否未被任何段或跳板页占用
+    pub fn is_free(&self, vpn_range: &Range<VirtPageNum>) -> bool {
+        !vpn_range.contains(&VirtAddr(TRAMPOLINE).vpn())
+            && self.segments.iter().all(|segment| {
+                segment.vpn_range.end <= vpn_range.start || vpn_range.end <= segment.vpn_range.start
+            })
+    }
+
+    /// 在指定区域中寻找能容纳page_count页的最低空闲区间
+    pub fn find_free_area(
+        &self,
+        area: Range<VirtPageNum>,
+        page_count: usize,
+    ) -> Option<Range<VirtPageNum>> {
+        let mut used = self
+            .segments
+            .iter()
+            .map(|segment| segment.vpn_range.clone())
+            .collect::<Vec<_>>();
+        used.sort_by_key(|range| range.start);
+        let mut start = area.start;
+        for range in used {
+            if range.start.0 >= start.0 + page_count {
+                break;
+            }
+            if range.end > start {
+                start = range.end;
+            }
+        }
+        let end = VirtPageNum(start.0 + page_count);
+        if end <= area.end {
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+
+    /// 虚拟页区间是否完全被已有的段覆盖
+    pub fn is_covered(&self, vpn_range: &Range<VirtPageNum>) -> bool {
+        let covered: usize = self
+            .segments
+            .iter()
+            .map(|segment| {
+                let start = segment.vpn_range.start.max(vpn_range.start);
+                let end = segment.vpn_range.end.min(vpn_range.end);
+                end.0.saturating_sub(start.0)
+            })
+            .sum();
+        covered == vpn_range.end.0 - vpn_range.start.0
+    }
+
+    /// 将与区间相交的段在区间边界处切开，取出并返回区间内的部分
+    fn take_area(&mut self, vpn_range: &Range<VirtPageNum>) -> Vec<MemorySegment> {
+        let mut taken = vec![];
+        for mut segment in take(&mut self.segments) {
+            if segment.vpn_range.end <= vpn_range.start || vpn_range.end <= segment.vpn_range.start
+            {
+                self.segments.push(segment);
+                continue;
+            }
+            if segment.vpn_range.end > vpn_range.end {
+                self.segments.push(segment.split_off(vpn_range.end));
+            }
+            if segment.vpn_range.start < vpn_range.start {
+                taken.push(segment.split_off(vpn_range.start));
+                self.segments.push(segment);
+            } else {
+                taken.push(segment);
+            }
+        }
+        taken
+    }
+
+    /// 删除区间内的所有映射（可以只删除段的一部分）
+    pub fn remove_area(&mut self, vpn_range: Range<VirtPageNum>) {
+        for segment in self.take_area(&vpn_range) {
+            for &vpn in segment.data_frames.keys() {
+                self.page_table.unmap(vpn);
+            }
+        }
+        self.flush_tlb();
+    }
+
+    /// 修改区间内所有映射的权限（可以只修改段的一部分）
+    /// 改为不可访问时删除页表项，页面仍属于段，恢复权限后在缺页时重新映射
+    pub fn protect_area(&mut self, vpn_range: Range<VirtPageNum>, seg_flags: SegFlags) {
+        for mut segment in self.take_area(&vpn_range) {
+            segment.flags = seg_flags;
+            for (&vpn, page) in &segment.data_frames {
+                // 已换出的页面在换入时使用新权限
+                let pte = match self.page_table.translate_pte(vpn) {
+                    Some(pte) => pte,
+                    None => continue,
+                };
+                // R、W、X全为0的有效页表项会被当作指向下一级页表
+                if seg_flags & (R | W | X) == 0 {
+                    self.page_table.unmap(vpn);
+                    continue;
+                }
+                // 仍被共享的页面保持只读，写入时再复制
+                let flags = if is_shared(page) {
+                    seg_flags & !W
+                } else {
+                    seg_flags
+                };
+                self.page_table.remap(vpn, pte.ppn(), flags);
+            }
+            self.segments.push(segment);
+        }
+        self.flush_tlb();
+    }
+
+    /// 当前堆顶
+    pub fn brk(&self) -> usize {
+        self.brk
+    }
+
+    /// 将堆顶调整到new_brk，返回是否成功
+    pub fn set_brk(&mut self, new_brk: usize) -> bool {
+        if new_brk < self.heap_bottom || new_brk > MMAP_BASE {
+            return false;
+        }
+        let heap_start = VirtAddr(self.heap_bottom).vpn();
+        let new_end = VirtAddr(new_brk + PAGE_SIZE - 1).vpn();
+        let index = self
+            .segments
+            .iter()
+            .position(|segment| segment.vpn_range.start == heap_start)
+            .unwrap();
+        let old_end = self.segments[index].vpn_range.end;
+        if new_end > old_end {
+            if !self.is_free(&(old_end..new_end)) {
+                return false;
+            }
+            self.segments[index].vpn_range.end = new_end;
+        } else if new_end < old_end {
+            let freed = self.segments[index].split_off(new_end);
+            for &vpn in freed.data_frames.keys() {
+                self.page_table.unmap(vpn);
+            }
+            self.flush_tlb();
+        }
+        self.brk = new_brk;
+        true
     }
 
     /// 映射跳板页
-    fn map_trampoline(&mut self) {
-        self.page_table.map(
+    fn map_trampoline(&mut self) -> Result<(), Errno> {
+        if self.page_table.map(
             VirtAddr(TRAMPOLINE).vpn(),
             PhysAddr(trampoline_start as usize).ppn(),
             R | X,
-        );
+        ) {
+            Ok(())
+        } else {
+            Err(ENOMEM)
+        }
     }
 
     /// 查找虚拟页号对应的物理页号
@@ -163,6 +517,11 @@ impl MemorySet {
         self.page_table.translate(vpn)
     }
 
+    /// 地址空间中的全部段
+    pub fn segments(&self) -> &[MemorySegment] {
+        &self.segments
+    }
+
     pub fn get_size(&self) -> usize {
         self.segments
             .iter()
@@ -175,6 +534,11 @@ impl MemorySet {
         self.page_table.satp_token()
     }
 
+    /// 页表项被删除或降低权限后，使其余正在运行此地址空间的核刷新TLB
+    fn flush_tlb(&self) {
+        tlb_shootdown(self.satp_token());
+    }
+
     /// 切换到此地址空间
     pub fn activate(&self) {
         let satp = self.page_table.satp_token();
@@ -184,33 +548,25 @@ impl MemorySet {
     }
 }
 
-impl Clone for MemorySet {
-    fn clone(&self) -> Self {
-        let mut new_memory_set = MemorySet::new();
-        new_memory_set.map_trampoline();
-        for segment in &self.segments {
-            let mut data = Vec::new();
-            for vpn in segment.vpn_range.clone() {
-                data.extend_from_slice(self.translate(vpn).unwrap().get_bytes_array());
-            }
-            new_memory_set.insert_segment(segment.vpn_range.clone(), segment.flags, Some(&data));
-            // copy data from another space
-            // for vpn in segment.vpn_range {
-            //     let src_ppn = self.translate(vpn).unwrap();
-            //     let dst_ppn = new_memory_set.translate(vpn).unwrap();
-            //     dst_ppn
-            //         .get_bytes_array()
-            //         .copy_from_slice(src_ppn.get_bytes_array());
-            // }
-        }
-        new_memory_set
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        swap::untrack(self.satp_token());
     }
 }
 
 pub fn init() {
     unsafe {
-        KERNEL_MEMORY_SET = UninitCell::init(MemorySet::new_kernel());
-        KERNEL_MEMORY_SET.activate();
+        let memory_set = MemorySet::new_kernel();
+        KERNEL_TOKEN.store(memory_set.satp_token(), Ordering::Relaxed);
+        KERNEL_MEMORY_SET = UninitCell::init(SpinLock::new(memory_set));
+    }
+    activate_kernel();
+}
+
+/// 在当前核上启用内核地址空间
+pub fn activate_kernel() {
+    unsafe {
+        KERNEL_MEMORY_SET.lock().activate();
     }
 }
 
@@ -219,22 +575,35 @@ mod test {
     use super::*;
     test!(test_memory_set_kernel, {
         for vpn in VirtAddr(text_start as usize).vpn()..VirtAddr(MEMORY_END_ADDR).vpn() {
-            let ppn = unsafe { KERNEL_MEMORY_SET.translate(vpn) };
+            let ppn = unsafe { KERNEL_MEMORY_SET.lock().translate(vpn) };
             test_assert!(ppn.is_some() && ppn.unwrap().0 == vpn.0);
         }
         Ok("passed")
     });
 
-    test!(test_memory_set_clone, {
-        let mut memory_set = MemorySet::new();
+    test!(test_memory_set_fork, {
+        let mut memory_set = MemorySet::new().unwrap();
         let data = [u8::MAX; PAGE_SIZE];
-        memory_set.insert_segment(VirtPageNum(0)..VirtPageNum(2), R | W, Some(&data));
-        let new_memory_set = memory_set.clone();
+        memory_set
+            .insert_segment(VirtPageNum(0)..VirtPageNum(2), U | R | W, Some(&data))
+            .unwrap();
+        let mut new_memory_set = memory_set.fork().unwrap();
         let ppn = new_memory_set.translate(VirtPageNum(0));
-        test_assert!(ppn.is_some());
-        for byte in ppn.unwrap().get_bytes_array() {
+        test_assert!(ppn.is_some() && ppn == memory_set.translate(VirtPageNum(0)));
+        test_assert!(!memory_set
+            .page_table
+            .translate_pte(VirtPageNum(0))
+            .unwrap()
+            .writable());
+        test_assert!(new_memory_set.handle_page_fault(VirtPageNum(0), W).is_ok());
+        let new_ppn = new_memory_set.translate(VirtPageNum(0)).unwrap();
+        test_assert!(new_ppn != ppn.unwrap());
+        for byte in new_ppn.get_bytes_array() {
             test_assert!(*byte == u8::MAX);
         }
+        // 父地址空间中的页面已不再共享，无需复制
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(0), W).is_ok());
+        test_assert!(memory_set.translate(VirtPageNum(0)) == ppn);
         let ppn = new_memory_set.translate(VirtPageNum(1));
         test_assert!(ppn.is_some());
         for byte in ppn.unwrap().get_bytes_array() {
@@ -242,4 +611,76 @@ mod test {
         }
         Ok("passed")
     });
+
+    test!(test_memory_set_lazy, {
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_lazy_segment(VirtPageNum(0)..VirtPageNum(2), U | R, None)
+            .unwrap();
+        test_assert!(memory_set.translate(VirtPageNum(0)).is_none());
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(0), W).is_err());
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(2), R).is_err());
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(0), R).is_ok());
+        test_assert!(memory_set.translate(VirtPageNum(0)).is_some());
+        test_assert!(memory_set.translate(VirtPageNum(1)).is_none());
+        memory_set.remove_segment(VirtPageNum(0));
+        test_assert!(memory_set.translate(VirtPageNum(0)).is_none());
+        Ok("passed")
+    });
+
+    test!(test_memory_set_area, {
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(1)..VirtPageNum(3), U | R | W, None)
+            .unwrap();
+        memory_set
+            .insert_lazy_segment(VirtPageNum(5)..VirtPageNum(6), U | R, None)
+            .unwrap();
+        test_assert!(!memory_set.is_free(&(VirtPageNum(0)..VirtPageNum(2))));
+        test_assert!(memory_set.is_free(&(VirtPageNum(3)..VirtPageNum(5))));
+        let area = memory_set.find_free_area(VirtPageNum(0)..VirtPageNum(8), 2);
+        test_assert!(area == Some(VirtPageNum(3)..VirtPageNum(5)));
+        let area = memory_set.find_free_area(VirtPageNum(0)..VirtPageNum(8), 3);
+        test_assert!(area.is_none());
+        test_assert!(!memory_set.is_covered(&(VirtPageNum(2)..VirtPageNum(4))));
+        test_assert!(memory_set.is_covered(&(VirtPageNum(2)..VirtPageNum(3))));
+        memory_set.protect_area(VirtPageNum(2)..VirtPageNum(3), U | R);
+        test_assert!(!memory_set
+            .page_table
+            .translate_pte(VirtPageNum(2))
+            .unwrap()
+            .writable());
+        test_assert!(memory_set
+            .page_table
+            .translate_pte(VirtPageNum(1))
+            .unwrap()
+            .writable());
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(2), W).is_err());
+        memory_set.remove_area(VirtPageNum(0)..VirtPageNum(2));
+        test_assert!(memory_set.translate(VirtPageNum(1)).is_none());
+        test_assert!(memory_set.translate(VirtPageNum(2)).is_some());
+        test_assert!(memory_set.segments.len() == 2);
+        Ok("passed")
+    });
+
+    test!(test_memory_set_brk, {
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_lazy_segment(VirtPageNum(1)..VirtPageNum(1), U | R | W, None)
+            .unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(4)..VirtPageNum(5), U | R, None)
+            .unwrap();
+        memory_set.heap_bottom = PAGE_SIZE;
+        memory_set.brk = PAGE_SIZE;
+        test_assert!(!memory_set.set_brk(0));
+        test_assert!(memory_set.set_brk(PAGE_SIZE + 1));
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(1), W).is_ok());
+        test_assert!(!memory_set.set_brk(4 * PAGE_SIZE + 1));
+        test_assert!(memory_set.set_brk(4 * PAGE_SIZE) && memory_set.brk() == 4 * PAGE_SIZE);
+        test_assert!(memory_set.set_brk(PAGE_SIZE));
+        test_assert!(memory_set.translate(VirtPageNum(1)).is_none());
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(1), R).is_err());
+        Ok("passed")
+    });
 }
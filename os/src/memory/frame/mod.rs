@@ -4,4 +4,5 @@ pub mod frame_allocator;
 pub mod memory_set;
 pub mod page_table;
 pub mod segment;
+pub mod swap;
 pub mod user_buffer;
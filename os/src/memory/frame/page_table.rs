@@ -5,6 +5,7 @@ use super::frame_allocator::{frame_alloc, FrameTracker};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// SV39页表项有效标志位
 const V: u8 = 1 << 0;
@@ -18,10 +19,10 @@ pub const X: u8 = 1 << 3;
 pub const U: u8 = 1 << 4;
 // /// SV39页表项全局标志位
 // const G: u8 = 1 << 5;
-// /// SV39页表项已使用标志位
-// const A: u8 = 1 << 6;
-// /// SV39页表项已修改标志位
-// const D: u8 = 1 << 7;
+/// SV39页表项已访问标志位
+pub const A: u8 = 1 << 6;
+/// SV39页表项已修改标志位
+pub const D: u8 = 1 << 7;
 
 /// SV39页表项标志位段
 pub type PTEFlags = u8;
@@ -61,16 +62,21 @@ impl PageTableEntry {
     pub fn valid(&self) -> bool {
         self.flags() & V != 0
     }
+
+    /// 页表项是否可写
+    pub fn writable(&self) -> bool {
+        self.flags() & W != 0
+    }
 }
 
 impl PageTable {
-    /// 创建空页表
-    pub fn new() -> Self {
-        let frame = frame_alloc().unwrap();
-        Self {
+    /// 创建空页表，物理页不足时返回None
+    pub fn new() -> Option<Self> {
+        let frame = frame_alloc()?;
+        Some(Self {
             root_ppn: frame.ppn(),
             frames: vec![frame],
-        }
+        })
     }
 
     /// 根据token获取指定页表（不会获取页表页的所有权）
@@ -81,16 +87,46 @@ impl PageTable {
         }
     }
 
-    /// 添加指定虚拟页到物理页的映射
-    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.create_pte(vpn).unwrap();
-        *pte = PageTableEntry::new(ppn, flags | V);
+    /// 添加指定虚拟页到物理页的映射（重新映射同一物理页时保留已修改标志）
+    /// 没有物理页存放页表时返回false
+    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> bool {
+        let pte = match self.create_pte(vpn) {
+            Some(pte) => pte,
+            None => return false,
+        };
+        Self::set_pte(pte, ppn, flags);
+        true
+    }
+
+    /// 修改已存在的映射，不需要分配页表（未映射时忽略）
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        if let Some(pte) = self.find_pte(vpn) {
+            Self::set_pte(pte, ppn, flags);
+        }
     }
 
-    /// 删除指定虚拟页到物理页的映射
+    fn set_pte(pte: &mut PageTableEntry, ppn: PhysPageNum, flags: PTEFlags) {
+        let dirty = if pte.valid() && pte.ppn() == ppn {
+            pte.flags() & D
+        } else {
+            0
+        };
+        *pte = PageTableEntry::new(ppn, flags | dirty | V);
+    }
+
+    /// 删除指定虚拟页的映射（未映射时忽略）
     pub fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte(vpn).unwrap();
-        *pte = PageTableEntry::empty();
+        if let Some(pte) = self.find_pte(vpn) {
+            *pte = PageTableEntry::empty();
+        }
+    }
+
+    /// 删除指定虚拟页的映射并返回原页表项（未映射时返回None）
+    /// 以原子操作清除页表项，其他核同时设置的访问与修改标志不会丢失
+    pub fn take_pte(&mut self, vpn: VirtPageNum) -> Option<PageTableEntry> {
+        let pte = self.find_pte(vpn)?;
+        let bits = unsafe { &*(pte as *mut PageTableEntry as *const AtomicUsize) };
+        Some(PageTableEntry(bits.swap(0, Ordering::SeqCst)))
     }
 
     /// 在页表中找到指定虚拟页的页表项
@@ -111,7 +147,7 @@ impl PageTable {
         None
     }
 
-    /// 在页表中创建指定虚拟页的页表项
+    /// 在页表中创建指定虚拟页的页表项，物理页不足时返回None
     fn create_pte(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
         let indices = vpn.indices();
         let mut ppn = self.root_ppn;
@@ -122,7 +158,7 @@ impl PageTable {
                 return Some(pte);
             }
             if !pte.valid() {
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc()?;
                 *pte = PageTableEntry::new(frame.ppn(), V);
                 self.frames.push(frame);
             }
@@ -136,6 +172,11 @@ impl PageTable {
         Some(self.find_pte(vpn)?.ppn())
     }
 
+    /// 查找虚拟页号对应的页表项
+    pub fn translate_pte(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
+        self.find_pte(vpn).map(|pte| *pte)
+    }
+
     /// 获取页表token
     pub fn satp_token(&self) -> usize {
         8usize << 60 | self.root_ppn.0
@@ -173,9 +214,9 @@ mod test {
     use super::*;
     use crate::memory::frame::frame_allocator::frame_alloc;
     test!(test_page_table, {
-        let mut page_table = PageTable::new();
+        let mut page_table = PageTable::new().unwrap();
         let frame = frame_alloc().unwrap();
-        page_table.map(VirtPageNum(0), frame.ppn(), R);
+        test_assert!(page_table.map(VirtPageNum(0), frame.ppn(), R));
         let ppn = page_table.translate(VirtPageNum(0));
         test_assert!(ppn.is_some() && ppn.unwrap() == frame.ppn());
         page_table.unmap(VirtPageNum(0));
@@ -1,7 +1,10 @@
 use super::address::VirtPageNum;
-use super::frame_allocator::{frame_alloc, FrameTracker};
+use super::frame_allocator::frame_alloc;
+use super::swap::Page;
 use crate::config::PAGE_SIZE;
+use alloc::collections::btree_map::Entry;
 use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
 use core::cmp::min;
 use core::ops::Range;
 
@@ -10,34 +13,79 @@ pub type SegFlags = u8;
 #[derive(Debug)]
 pub struct MemorySegment {
     pub vpn_range: Range<VirtPageNum>,
-    pub data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// 已分配的页面，可能与fork出的地址空间共享（写时复制）
+    pub data_frames: BTreeMap<VirtPageNum, Arc<Page>>,
     pub flags: SegFlags,
 }
 
 impl MemorySegment {
-    pub fn new(vpn_range: Range<VirtPageNum>, flags: SegFlags) -> Self {
+    /// 创建段并分配全部物理页，物理页不足时返回None
+    pub fn new(vpn_range: Range<VirtPageNum>, flags: SegFlags) -> Option<Self> {
         let mut data_frames = BTreeMap::new();
         for vpn in vpn_range.clone() {
-            let frame = frame_alloc().unwrap();
-            data_frames.insert(vpn, frame);
+            let frame = frame_alloc()?;
+            data_frames.insert(vpn, Arc::new(Page::new(frame)));
         }
-        Self {
+        Some(Self {
             vpn_range,
             data_frames,
             flags,
+        })
+    }
+
+    /// 创建不立即分配物理页的段，物理页在首次访问时分配
+    pub fn new_lazy(vpn_range: Range<VirtPageNum>, flags: SegFlags) -> Self {
+        Self {
+            vpn_range,
+            data_frames: BTreeMap::new(),
+            flags,
+        }
+    }
+
+    /// 创建与此段共享物理页帧的新段
+    pub fn share(&self) -> Self {
+        Self {
+            vpn_range: self.vpn_range.clone(),
+            data_frames: self.data_frames.clone(),
+            flags: self.flags,
+        }
+    }
+
+    /// 在指定页处将段一分为二，本段保留前半部分，返回后半部分
+    pub fn split_off(&mut self, at: VirtPageNum) -> Self {
+        let data_frames = self.data_frames.split_off(&at);
+        let vpn_range = at..self.vpn_range.end;
+        self.vpn_range.end = at;
+        Self {
+            vpn_range,
+            data_frames,
+            flags: self.flags,
         }
     }
 
-    pub fn copy_data(&self, data: &[u8]) {
+    /// 复制数据到段中（为尚未分配的页面分配物理页），物理页不足时返回false
+    pub fn copy_data(&mut self, data: &[u8]) -> bool {
         let mut current_start = 0;
         for vpn in self.vpn_range.clone() {
-            let src = &data[current_start..min(data.len(), current_start + PAGE_SIZE)];
-            self.data_frames[&vpn].ppn().get_bytes_array()[..src.len()].copy_from_slice(src);
-            current_start += PAGE_SIZE;
             if current_start >= data.len() {
                 break;
             }
+            let src = &data[current_start..min(data.len(), current_start + PAGE_SIZE)];
+            let page = match self.data_frames.entry(vpn) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => match frame_alloc() {
+                    Some(frame) => entry.insert(Arc::new(Page::new(frame))),
+                    None => return false,
+                },
+            };
+            let ppn = match page.swap_in() {
+                Some(ppn) => ppn,
+                None => return false,
+            };
+            ppn.get_bytes_array()[..src.len()].copy_from_slice(src);
+            current_start += PAGE_SIZE;
         }
+        true
     }
 }
 
@@ -46,17 +94,58 @@ mod test {
     use super::*;
     use crate::memory::frame::page_table::*;
     test!(test_memory_segment, {
-        let seg = MemorySegment::new(VirtPageNum(0)..VirtPageNum(1), R | W);
+        let mut seg = MemorySegment::new(VirtPageNum(0)..VirtPageNum(1), R | W).unwrap();
         let mut data = [0u8; PAGE_SIZE];
         for byte in data.iter_mut().step_by(2) {
             *byte = u8::MAX;
         }
-        seg.copy_data(&data);
+        test_assert!(seg.copy_data(&data));
         let mut should_be = u8::MAX;
-        for byte in seg.data_frames[&VirtPageNum(0)].ppn().get_bytes_array() {
+        for byte in seg.data_frames[&VirtPageNum(0)]
+            .ppn()
+            .unwrap()
+            .get_bytes_array()
+        {
             test_assert!(*byte == should_be);
             should_be = !should_be;
         }
         Ok("passed")
     });
+
+    test!(test_memory_segment_lazy, {
+        let mut seg = MemorySegment::new_lazy(VirtPageNum(0)..VirtPageNum(3), U | R | W);
+        test_assert!(seg.data_frames.is_empty());
+        test_assert!(seg.copy_data(&[u8::MAX; PAGE_SIZE + 1]));
+        test_assert!(seg.data_frames.len() == 2);
+        test_assert!(
+            seg.data_frames[&VirtPageNum(1)]
+                .ppn()
+                .unwrap()
+                .get_bytes_array()[0]
+                == u8::MAX
+        );
+        test_assert!(
+            seg.data_frames[&VirtPageNum(1)]
+                .ppn()
+                .unwrap()
+                .get_bytes_array()[1]
+                == 0
+        );
+        Ok("passed")
+    });
+
+    test!(test_memory_segment_split, {
+        let mut seg = MemorySegment::new(VirtPageNum(0)..VirtPageNum(4), U | R | W).unwrap();
+        let right = seg.split_off(VirtPageNum(1));
+        test_assert!(
+            seg.vpn_range == (VirtPageNum(0)..VirtPageNum(1)) && seg.data_frames.len() == 1
+        );
+        test_assert!(right.vpn_range == (VirtPageNum(1)..VirtPageNum(4)));
+        test_assert!(right
+            .data_frames
+            .keys()
+            .copied()
+            .eq(VirtPageNum(1)..VirtPageNum(4)));
+        Ok("passed")
+    });
 }
@@ -0,0 +1,332 @@
+//! 页面置换子模块
+//!
+//! 物理页耗尽时使用时钟算法换出用户页面到交换文件中，缺页时再换入
+//! 换入换出时可能持有进程的借用，不能阻塞：交换区在初始化时一次分配，
+//! 页面直接读写交换文件所在的磁盘块，不经过文件系统
+
+use super::address::{PhysPageNum, VirtPageNum};
+use super::frame_allocator::{frame_alloc, FrameTracker};
+use super::page_table::{PageTable, A, D};
+use crate::config::{PAGE_SIZE, SWAP_PAGE_LIMIT};
+use crate::drivers::{without_sleep, BLOCK_DEVICE};
+use crate::fs::rfs::block_dev::BlockDevice;
+use crate::fs::rfs::layout::InodeType;
+use crate::fs::rfs::{find_inode, InodeHandler, BLOCK_SZ, ROOT_INODE};
+use crate::smp::tlb_shootdown;
+use crate::sync::SpinLock;
+use crate::tools::uninit_cell::UninitCell;
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// 全局交换空间实例
+static mut SWAP_SPACE: UninitCell<SwapSpace> = UninitCell::uninit();
+
+/// 用户页面，位于物理页中或被换出到交换空间
+#[derive(Debug)]
+pub struct Page {
+    inner: SpinLock<PageInner>,
+}
+
+#[derive(Debug)]
+struct PageInner {
+    frame: Option<FrameTracker>,
+    /// 交换空间中的副本，页面被修改后失效
+    slot: Option<usize>,
+    /// 内核正在直接访问此页面的次数
+    pins: usize,
+}
+
+/// 内核通过UserBuffer直接访问的页面：持有期间页面不会被换出、释放或在fork时共享
+pub struct PagePin(Arc<Page>);
+
+impl PagePin {
+    pub fn new(page: Arc<Page>) -> Self {
+        page.inner.lock().pins += 1;
+        Self(page)
+    }
+}
+
+impl Drop for PagePin {
+    fn drop(&mut self) {
+        self.0.inner.lock().pins -= 1;
+    }
+}
+
+/// 页面是否被多个地址空间共享（不计内核持有的PagePin）
+pub fn is_shared(page: &Arc<Page>) -> bool {
+    Arc::strong_count(page) - page.inner.lock().pins > 1
+}
+
+impl Page {
+    /// 使用已分配的物理页创建页面
+    pub fn new(frame: FrameTracker) -> Self {
+        Self {
+            inner: SpinLock::new(PageInner {
+                frame: Some(frame),
+                slot: None,
+                pins: 0,
+            }),
+        }
+    }
+
+    /// 页面所在的物理页号，已被换出时为None
+    pub fn ppn(&self) -> Option<PhysPageNum> {
+        self.inner.lock().frame.as_ref().map(|frame| frame.ppn())
+    }
+
+    /// 确保页面位于内存中，返回其物理页号，物理页不足时返回None
+    pub fn swap_in(&self) -> Option<PhysPageNum> {
+        if let Some(ppn) = self.ppn() {
+            return Some(ppn);
+        }
+        // 分配物理页时可能换出其他页面，不能持有锁
+        let frame = frame_alloc()?;
+        let mut inner = self.inner.lock();
+        if let Some(ppn) = inner.frame.as_ref().map(|frame| frame.ppn()) {
+            // 其他核已经换入，多分配的物理页在释放锁后归还
+            drop(inner);
+            drop(frame);
+            return Some(ppn);
+        }
+        unsafe {
+            SWAP_SPACE.read(inner.slot.unwrap(), frame.ppn());
+        }
+        let ppn = frame.ppn();
+        inner.frame = Some(frame);
+        Some(ppn)
+    }
+
+    /// 内核是否正在直接访问此页面
+    pub fn is_pinned(&self) -> bool {
+        self.inner.lock().pins > 0
+    }
+
+    /// 页面被修改，交换空间中的副本失效
+    pub fn mark_dirty(&self) {
+        let slot = self.inner.lock().slot.take();
+        if let Some(slot) = slot {
+            unsafe {
+                SWAP_SPACE.dealloc(slot);
+            }
+        }
+    }
+
+    /// 换出页面并释放物理页，dirty表示页面自换入后是否被修改过
+    /// 调用前映射必须已删除且各核已刷新TLB，写出期间页面不会再被修改
+    fn swap_out(&self, dirty: bool) -> bool {
+        let mut inner = self.inner.lock();
+        if dirty || inner.slot.is_none() {
+            let slot = match inner.slot.or_else(|| unsafe { SWAP_SPACE.alloc() }) {
+                Some(slot) => slot,
+                // swap space is full
+                None => return false,
+            };
+            unsafe {
+                SWAP_SPACE.write(slot, inner.frame.as_ref().unwrap().ppn());
+            }
+            inner.slot = Some(slot);
+        }
+        inner.frame = None;
+        true
+    }
+}
+
+impl Drop for PageInner {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            unsafe {
+                SWAP_SPACE.dealloc(slot);
+            }
+        }
+    }
+}
+
+/// 时钟算法中的一项：某地址空间中映射了该页面的虚拟页
+struct ClockEntry {
+    page: Weak<Page>,
+    token: usize,
+    vpn: VirtPageNum,
+}
+
+/// 每页占用的磁盘块数
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+
+/// 交换空间，以页为单位存放在交换文件中
+/// 加锁顺序：页面 -> 空闲页槽，时钟队列的锁不与其他锁同时持有
+struct SwapSpace {
+    file: Arc<InodeHandler>,
+    block_device: Arc<dyn BlockDevice>,
+    /// 交换文件按顺序占用的磁盘块
+    blocks: Vec<u32>,
+    /// 空闲的页槽
+    free: SpinLock<Vec<usize>>,
+    clock: SpinLock<VecDeque<ClockEntry>>,
+}
+
+impl SwapSpace {
+    fn alloc(&self) -> Option<usize> {
+        self.free.lock().pop()
+    }
+
+    fn dealloc(&self, slot: usize) {
+        self.free.lock().push(slot);
+    }
+
+    fn read(&self, slot: usize, ppn: PhysPageNum) {
+        let blocks = &self.blocks[slot * BLOCKS_PER_PAGE..(slot + 1) * BLOCKS_PER_PAGE];
+        let chunks = ppn.get_bytes_array().chunks_mut(BLOCK_SZ);
+        without_sleep(|| {
+            for (&block_id, chunk) in blocks.iter().zip(chunks) {
+                self.block_device.read_block(block_id as usize, chunk);
+            }
+        });
+    }
+
+    fn write(&self, slot: usize, ppn: PhysPageNum) {
+        let blocks = &self.blocks[slot * BLOCKS_PER_PAGE..(slot + 1) * BLOCKS_PER_PAGE];
+        let chunks = ppn.get_bytes_array().chunks(BLOCK_SZ);
+        without_sleep(|| {
+            for (&block_id, chunk) in blocks.iter().zip(chunks) {
+                self.block_device.write_block(block_id as usize, chunk);
+            }
+        });
+    }
+
+    /// 时钟算法选择并换出一个页面
+    fn swap_out_one(&self) -> bool {
+        // 最多扫描两轮，第一轮清除访问标志
+        let len = self.clock.lock().len();
+        for _ in 0..2 * len {
+            let entry = match self.clock.lock().pop_front() {
+                Some(entry) => entry,
+                None => break,
+            };
+            // 页面已被释放或换出时丢弃此项
+            let page = match entry.page.upgrade() {
+                Some(page) => page,
+                None => continue,
+            };
+            let ppn = match page.ppn() {
+                Some(ppn) => ppn,
+                None => continue,
+            };
+            let mut page_table = PageTable::from_token(entry.token);
+            let pte = match page_table.translate_pte(entry.vpn) {
+                Some(pte) if pte.ppn() == ppn => pte,
+                // 映射已失效
+                _ => continue,
+            };
+            // 被多个地址空间共享或被内核固定的页面暂不换出
+            if page.is_pinned() || Arc::strong_count(&page) > 2 {
+                self.clock.lock().push_back(entry);
+                continue;
+            }
+            if pte.flags() & A != 0 {
+                page_table.remap(entry.vpn, ppn, pte.flags() & !A);
+                self.clock.lock().push_back(entry);
+                continue;
+            }
+            // 先删除映射并让其余核刷新TLB，此后不会再有写入，修改标志才是准确的
+            let pte = page_table.take_pte(entry.vpn).unwrap();
+            tlb_shootdown(entry.token);
+            if !page.swap_out(pte.flags() & D != 0) {
+                // 交换空间已满，恢复原映射（页表页仍在，不需要分配）
+                page_table.map(entry.vpn, ppn, pte.flags());
+                self.clock.lock().push_back(entry);
+                return false;
+            }
+            return true;
+        }
+        false
+    }
+}
+
+/// 将映射到用户地址空间的页面加入置换候选
+pub fn track(page: &Arc<Page>, token: usize, vpn: VirtPageNum) {
+    unsafe {
+        if SWAP_SPACE.is_init() {
+            SWAP_SPACE.clock.lock().push_back(ClockEntry {
+                page: Arc::downgrade(page),
+                token,
+                vpn,
+            });
+        }
+    }
+}
+
+/// 移除地址空间的所有置换候选（地址空间销毁时调用）
+pub fn untrack(token: usize) {
+    unsafe {
+        if SWAP_SPACE.is_init() {
+            SWAP_SPACE.clock.lock().retain(|entry| entry.token != token);
+        }
+    }
+}
+
+/// 换出一个用户页面以释放物理页，返回是否成功
+pub fn swap_out_one() -> bool {
+    unsafe { SWAP_SPACE.is_init() && SWAP_SPACE.swap_out_one() }
+}
+
+/// 文件是否为交换文件，交换文件不能通过文件系统写入或删除
+pub fn is_swap_file(inode: &InodeHandler) -> bool {
+    unsafe { SWAP_SPACE.is_init() && SWAP_SPACE.file.get_inode_id() == inode.get_inode_id() }
+}
+
+/// 创建交换文件并分配全部交换区（需要在文件系统初始化之后）
+pub fn init() {
+    // 上次运行遗留的数据没有意义，页槽写入前不会被读取
+    let file = match find_inode("/swap") {
+        Some(file) => file,
+        None => Arc::new(unsafe { ROOT_INODE.create("swap", InodeType::File).unwrap() }),
+    };
+    let size = SWAP_PAGE_LIMIT * PAGE_SIZE;
+    if file.get_file_size() < size as u32 {
+        file.write_at(size - 1, &[0]);
+    }
+    let blocks = file.data_block_ids();
+    assert!(
+        blocks.len() >= SWAP_PAGE_LIMIT * BLOCKS_PER_PAGE,
+        "[kernel] No space for swap file!"
+    );
+    unsafe {
+        SWAP_SPACE = UninitCell::init(SwapSpace {
+            file,
+            block_device: BLOCK_DEVICE.clone(),
+            blocks,
+            free: SpinLock::new((0..SWAP_PAGE_LIMIT).rev().collect()),
+            clock: SpinLock::new(VecDeque::new()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::frame::memory_set::MemorySet;
+    use crate::memory::frame::page_table::*;
+    test!(test_swap, {
+        let mut memory_set = MemorySet::new().unwrap();
+        let data = [u8::MAX; PAGE_SIZE];
+        memory_set
+            .insert_segment(VirtPageNum(0)..VirtPageNum(1), U | R | W, Some(&data))
+            .unwrap();
+        // 新映射的页面没有访问标志，可以直接换出
+        let mut swapped = false;
+        while unsafe { !SWAP_SPACE.clock.lock().is_empty() } {
+            test_assert!(swap_out_one());
+            if memory_set.translate(VirtPageNum(0)).is_none() {
+                swapped = true;
+                break;
+            }
+        }
+        test_assert!(swapped);
+        test_assert!(memory_set.handle_page_fault(VirtPageNum(0), R).is_ok());
+        let ppn = memory_set.translate(VirtPageNum(0)).unwrap();
+        for byte in ppn.get_bytes_array() {
+            test_assert!(*byte == u8::MAX);
+        }
+        Ok("passed")
+    });
+}
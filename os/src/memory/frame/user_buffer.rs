@@ -1,15 +1,21 @@
 //! 用户地址空间的封装
+//!
+//! 所有访问都会检查页面是否带有U标志及所需的读写权限，非法地址返回EFAULT
 use core::mem::size_of;
 use core::ptr::{slice_from_raw_parts, slice_from_raw_parts_mut};
 
 use super::address::*;
-use super::page_table::PageTable;
+use super::memory_set::MemorySet;
+use super::page_table::{PTEFlags, R, W};
+use super::swap::PagePin;
+use crate::sys_call::errno::{Errno, EFAULT};
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 
 /// 用户空间的地址在内核空间中的映射
-pub struct UserBuffer(pub Vec<&'static mut [u8]>);
+/// 同时固定所涉及的页面，使内核在阻塞期间仍可安全访问
+pub struct UserBuffer(pub Vec<&'static mut [u8]>, Vec<PagePin>);
 
 impl UserBuffer {
     /// 获取Buffer长度
@@ -24,6 +30,7 @@ impl IntoIterator for UserBuffer {
     fn into_iter(self) -> Self::IntoIter {
         Iter {
             segments: self.0,
+            _pins: self.1,
             current_segment: 0,
             current_idx: 0,
         }
@@ -32,6 +39,7 @@ impl IntoIterator for UserBuffer {
 
 pub struct Iter {
     segments: Vec<&'static mut [u8]>,
+    _pins: Vec<PagePin>,
     current_segment: usize,
     current_idx: usize,
 }
@@ -53,17 +61,32 @@ impl Iterator for Iter {
     }
 }
 
-/// 通过指定token获取用户数据在内核中的映射
-pub fn get_user_buffer(user_token: usize, ptr: *const u8, len: usize) -> UserBuffer {
+/// 获取用户虚拟页对应的物理页，必要时先处理缺页
+/// 页面不属于用户或没有access权限时返回EFAULT，内存不足时返回ENOMEM
+fn translate_user(
+    memory_set: &mut MemorySet,
+    vpn: VirtPageNum,
+    access: PTEFlags,
+) -> Result<PhysPageNum, Errno> {
+    memory_set.handle_page_fault(vpn, access)?;
+    Ok(memory_set.translate(vpn).unwrap())
+}
+
+fn translated_buffer(
+    memory_set: &mut MemorySet,
+    ptr: *const u8,
+    len: usize,
+    access: PTEFlags,
+) -> Result<UserBuffer, Errno> {
     let mut data_segments = vec![];
-    let user_page_table = PageTable::from_token(user_token);
+    let mut pins = vec![];
     let mut current_start = ptr as usize;
-    let end = current_start + len;
+    let end = current_start.checked_add(len).ok_or(EFAULT)?;
     while current_start < end {
         let start_va = VirtAddr(current_start);
-        let ppn = user_page_table
-            .translate(start_va.vpn())
-            .expect("[kernel] User space address not mapped!");
+        // 写入时复制的页面已在缺页处理中复制；立即固定，处理后续页面的缺页时不会被换出
+        let ppn = translate_user(memory_set, start_va.vpn(), access)?;
+        pins.push(PagePin::new(memory_set.page(start_va.vpn()).ok_or(EFAULT)?));
         let end_va = core::cmp::min(VirtAddr(end), VirtPageNum(start_va.vpn().0 + 1).addr());
         if end_va.page_offset() == 0 {
             data_segments.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
@@ -73,72 +96,136 @@ pub fn get_user_buffer(user_token: usize, ptr: *const u8, len: usize) -> UserBuf
         }
         current_start = end_va.0;
     }
-    UserBuffer(data_segments)
+    Ok(UserBuffer(data_segments, pins))
+}
+
+/// 获取用户数据在内核中的映射（内核只读取）
+pub fn get_user_buffer(
+    memory_set: &mut MemorySet,
+    ptr: *const u8,
+    len: usize,
+) -> Result<UserBuffer, Errno> {
+    translated_buffer(memory_set, ptr, len, R)
+}
+
+/// 获取用户数据在内核中的映射（内核将写入）
+pub fn get_user_buffer_mut(
+    memory_set: &mut MemorySet,
+    ptr: *mut u8,
+    len: usize,
+) -> Result<UserBuffer, Errno> {
+    translated_buffer(memory_set, ptr, len, W)
 }
 
-/// 通过指定token获取用户字符串
-pub fn get_user_string(user_token: usize, ptr: *const u8) -> String {
-    let user_page_table = PageTable::from_token(user_token);
+/// 获取用户字符串
+pub fn get_user_string(memory_set: &mut MemorySet, ptr: *const u8) -> Result<String, Errno> {
     let mut string = String::new();
     let mut va = VirtAddr(ptr as usize);
     loop {
-        let ppn = user_page_table
-            .translate(va.vpn())
-            .expect("[kernel] User space address not mapped!");
+        let ppn = translate_user(memory_set, va.vpn(), R)?;
         let ch = *(PhysAddr(ppn.addr().0 + va.page_offset()).get_mut::<u8>());
         if ch == 0 {
             break;
         }
         string.push(ch as char);
-        va = VirtAddr(va.0 + 1);
+        va = VirtAddr(va.0.checked_add(1).ok_or(EFAULT)?);
     }
-    string
+    Ok(string)
 }
 
-pub fn get_user_value<T: Copy>(user_token: usize, ptr: *const u8, value: &mut T) {
+pub fn get_user_value<T: Copy>(
+    memory_set: &mut MemorySet,
+    ptr: *const u8,
+    value: &mut T,
+) -> Result<(), Errno> {
     let value_buffer = slice_from_raw_parts_mut(value as *mut _ as *mut u8, size_of::<T>());
-    let user_buffer = get_user_buffer(user_token, ptr, size_of::<T>());
+    let user_buffer = get_user_buffer(memory_set, ptr, size_of::<T>())?;
     for (i, byte) in user_buffer.into_iter().enumerate() {
         unsafe {
             (*value_buffer)[i] = *byte;
         }
     }
+    Ok(())
 }
 
-pub fn put_user_value<T: Copy>(user_token: usize, value: T, ptr: *mut u8) {
-    let user_buffer = get_user_buffer(user_token, ptr, size_of::<T>());
+pub fn put_user_value<T: Copy>(
+    memory_set: &mut MemorySet,
+    value: T,
+    ptr: *mut u8,
+) -> Result<(), Errno> {
+    let user_buffer = get_user_buffer_mut(memory_set, ptr, size_of::<T>())?;
     let value_buffer = slice_from_raw_parts(&value as *const _ as *const u8, size_of::<T>());
     for (i, byte) in user_buffer.into_iter().enumerate() {
         unsafe {
             *byte = (*value_buffer)[i];
         }
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::memory::frame::memory_set::MemorySet;
+    use crate::config::PAGE_SIZE;
     use crate::memory::frame::page_table::*;
     test!(test_user_buffer, {
-        let mut memory_set = MemorySet::new();
-        memory_set.insert_segment(VirtPageNum(0)..VirtPageNum(2), R | W, None);
-        let user_buffer = get_user_buffer(memory_set.satp_token(), 0xff0 as *const u8, 32);
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_lazy_segment(VirtPageNum(0)..VirtPageNum(2), U | R | W, None)
+            .unwrap();
+        let user_buffer = get_user_buffer(&mut memory_set, 0xff0 as *const u8, 32).unwrap();
         test_assert!(user_buffer.0.len() == 2);
         test_assert!(user_buffer.0[0].len() == 16 && user_buffer.0[1].len() == 16);
         Ok("passed")
     });
 
+    test!(test_user_buffer_pin, {
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(0)..VirtPageNum(1), U | R | W, None)
+            .unwrap();
+        let user_buffer = get_user_buffer_mut(&mut memory_set, 0 as *mut u8, 1).unwrap();
+        let page = memory_set.page(VirtPageNum(0)).unwrap();
+        test_assert!(page.is_pinned());
+        // 内核将写入的页面在fork时复制给子地址空间，而不是共享
+        let child = memory_set.fork().unwrap();
+        test_assert!(child.translate(VirtPageNum(0)) != memory_set.translate(VirtPageNum(0)));
+        drop(user_buffer);
+        test_assert!(!page.is_pinned());
+        Ok("passed")
+    });
+
     test!(test_user_string, {
-        let mut memory_set = MemorySet::new();
-        memory_set.insert_segment(VirtPageNum(0)..VirtPageNum(1), R | W, None);
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(0)..VirtPageNum(1), U | R | W, None)
+            .unwrap();
         let string = String::from("hello world\0123");
-        let user_buffer = get_user_buffer(memory_set.satp_token(), 0 as *const u8, string.len());
+        let user_buffer = get_user_buffer_mut(&mut memory_set, 0 as *mut u8, string.len()).unwrap();
         for (i, byte) in user_buffer.into_iter().enumerate() {
             *byte = string.as_bytes()[i];
         }
-        let result = get_user_string(memory_set.satp_token(), 0 as *const u8);
-        test_assert!(result == "hello world");
+        let result = get_user_string(&mut memory_set, 0 as *const u8);
+        test_assert!(result.as_deref() == Ok("hello world"));
+        Ok("passed")
+    });
+
+    test!(test_user_buffer_fault, {
+        let mut memory_set = MemorySet::new().unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(0)..VirtPageNum(1), U | R, None)
+            .unwrap();
+        memory_set
+            .insert_segment(VirtPageNum(1)..VirtPageNum(2), R | W, None)
+            .unwrap();
+        test_assert!(get_user_buffer(&mut memory_set, 0 as *const u8, PAGE_SIZE).is_ok());
+        // 只读页面不能写入
+        test_assert!(get_user_buffer_mut(&mut memory_set, 0 as *mut u8, 1).err() == Some(EFAULT));
+        // 没有U标志的页面不能访问
+        test_assert!(get_user_buffer(&mut memory_set, 0xff0 as *const u8, 32).is_err());
+        // 未映射的页面
+        test_assert!(get_user_string(&mut memory_set, 0x2000 as *const u8).is_err());
+        test_assert!(get_user_buffer(&mut memory_set, usize::MAX as *const u8, 2).is_err());
         Ok("passed")
     });
 }
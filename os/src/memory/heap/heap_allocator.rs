@@ -2,9 +2,9 @@
 
 use super::linked_list::LinkedList;
 use crate::config::KERNEL_HEAP_SIZE;
+use crate::sync::SpinLock;
 use crate::tools::uninit_cell::UninitCell;
 use alloc::alloc::{GlobalAlloc, Layout};
-use core::cell::RefCell;
 use core::cmp::{max, min};
 use core::mem::size_of;
 use core::ops::Deref;
@@ -112,21 +112,21 @@ fn get_size(layout: Layout) -> usize {
     )
 }
 
-/// 由于GlobalAlloc的限制导致无法使用mut方法，使用RefCell包装
-pub struct HeapAllocator(RefCell<BuddySystemAllocator<32>>);
+/// 由于GlobalAlloc的限制导致无法使用mut方法，且各核共享同一个堆，使用自旋锁包装
+pub struct HeapAllocator(SpinLock<BuddySystemAllocator<32>>);
 
 unsafe impl GlobalAlloc for UninitCell<HeapAllocator> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.0.borrow_mut().alloc(layout)
+        self.0.lock().alloc(layout)
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        self.0.borrow_mut().dealloc(ptr, layout)
+        self.0.lock().dealloc(ptr, layout)
     }
 }
 
 impl Deref for HeapAllocator {
-    type Target = RefCell<BuddySystemAllocator<32>>;
+    type Target = SpinLock<BuddySystemAllocator<32>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -148,7 +148,7 @@ fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
 
 pub fn init() {
     unsafe {
-        HEAP_ALLOCATOR = UninitCell::init(HeapAllocator(RefCell::new(
+        HEAP_ALLOCATOR = UninitCell::init(HeapAllocator(SpinLock::new(
             BuddySystemAllocator::<32>::new(KERNEL_HEAP.as_ptr() as usize, KERNEL_HEAP_SIZE),
         )));
     }
@@ -13,3 +13,15 @@ pub fn init() {
     frame::memory_set::init();
     println!("mod memory initialized!");
 }
+
+/// 初始化页面置换（交换文件位于文件系统中，需要在文件系统初始化之后调用）
+/// - [`frame::swap::init`]
+pub fn init_swap() {
+    frame::swap::init();
+    println!("mod swap initialized!");
+}
+
+/// 在其余核上启用内核地址空间
+pub fn init_hart() {
+    frame::memory_set::activate_kernel();
+}
@@ -5,6 +5,13 @@ const SBI_CONSOLE_PUTCHAR: usize = 1;
 const SBI_CONSOLE_GETCHAR: usize = 2;
 const SBI_SHUTDOWN: usize = 8;
 
+/// 核状态管理扩展
+const SBI_EXT_HSM: usize = 0x48534d;
+const SBI_HSM_HART_START: usize = 0;
+/// 核间中断扩展
+const SBI_EXT_IPI: usize = 0x735049;
+const SBI_IPI_SEND_IPI: usize = 0;
+
 #[inline(always)]
 fn sbi_call(which: usize, args: [usize; 3]) -> usize {
     let mut ret;
@@ -20,6 +27,23 @@ fn sbi_call(which: usize, args: [usize; 3]) -> usize {
     ret
 }
 
+/// 调用SBI扩展的函数，返回(错误码, 返回值)
+#[inline(always)]
+fn sbi_call_ext(eid: usize, fid: usize, args: [usize; 3]) -> (isize, usize) {
+    let (mut error, mut value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            inlateout("x10") args[0] => error,
+            inlateout("x11") args[1] => value,
+            in("x12") args[2],
+            in("x16") fid,
+            in("x17") eid,
+        );
+    }
+    (error, value)
+}
+
 pub fn set_timer(timer: usize) {
     sbi_call(SBI_SET_TIMER, [timer, 0, 0]);
 }
@@ -36,3 +60,18 @@ pub fn shutdown() -> ! {
     sbi_call(SBI_SHUTDOWN, [0, 0, 0]);
     panic!("It should shutdown!");
 }
+
+/// 启动核hart_id，从物理地址start_addr开始执行（a0为核号，a1为opaque），返回是否成功
+pub fn hart_start(hart_id: usize, start_addr: usize, opaque: usize) -> bool {
+    let (error, _) = sbi_call_ext(
+        SBI_EXT_HSM,
+        SBI_HSM_HART_START,
+        [hart_id, start_addr, opaque],
+    );
+    error == 0
+}
+
+/// 向hart_mask中的核发送核间中断
+pub fn send_ipi(hart_mask: usize) {
+    sbi_call_ext(SBI_EXT_IPI, SBI_IPI_SEND_IPI, [hart_mask, 0, 0]);
+}
@@ -0,0 +1,118 @@
+//! 多核支持
+//!
+//! 其余核通过SBI HSM扩展启动。内核中的全局数据由内核锁保护：
+//! 进入内核时获取，返回用户态或空闲等待时释放，同一时刻只有一个核在内核中运行
+//! 各核共享的数据另由自旋锁或原子变量保护（自旋锁不可重入，同一对象不能嵌套加锁）：
+//! 任务管理器、线程与进程控制块、同步原语、块缓存、文件系统实例与挂载表、交换空间、
+//! 睡眠队列、控制台以及各分配器，共享的对象须满足Send + Sync
+
+use crate::config::MAX_HARTS;
+use crate::sbi::{hart_start, send_ipi};
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 内核锁，保存持有者的核号加1，为0时未被持有
+static KERNEL_LOCK: AtomicUsize = AtomicUsize::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NOT_IN_USER: AtomicUsize = AtomicUsize::new(0);
+
+/// 各核正在运行的用户地址空间token，不在用户态时为0
+static USER_TOKENS: [AtomicUsize; MAX_HARTS] = [NOT_IN_USER; MAX_HARTS];
+
+/// 当前核的核号（保存在tp中）
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let id;
+    unsafe {
+        core::arch::asm!("mv {}, tp", out(reg) id);
+    }
+    id
+}
+
+/// 获取内核锁
+pub fn lock_kernel() {
+    let owner = hart_id() + 1;
+    while KERNEL_LOCK
+        .compare_exchange_weak(0, owner, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        spin_loop();
+    }
+    // 持锁的核可能修改了内核页表（如回收内核栈），等待期间缓存的页表项需要作废
+    unsafe {
+        core::arch::asm!("sfence.vma");
+    }
+}
+
+/// 释放内核锁
+pub fn unlock_kernel() {
+    assert_eq!(KERNEL_LOCK.load(Ordering::Relaxed), hart_id() + 1);
+    KERNEL_LOCK.store(0, Ordering::Release);
+}
+
+/// 即将返回用户态，记录当前核运行的地址空间（持有内核锁时调用）
+pub fn enter_user(token: usize) {
+    USER_TOKENS[hart_id()].store(token, Ordering::Release);
+}
+
+/// 从用户态进入内核（获取内核锁之前调用）
+pub fn leave_user() {
+    USER_TOKENS[hart_id()].store(0, Ordering::Release);
+}
+
+/// 使正在运行地址空间token的其余核离开用户态，在修改用户页表（解除映射、降低权限）后调用
+/// 这些核在取得内核锁之前不会回到用户态，返回用户态时会刷新TLB
+pub fn tlb_shootdown(token: usize) {
+    let current = hart_id();
+    let targets = (0..MAX_HARTS)
+        .filter(|&id| id != current && USER_TOKENS[id].load(Ordering::Acquire) == token)
+        .fold(0, |mask, id| mask | 1 << id);
+    if targets == 0 {
+        return;
+    }
+    send_ipi(targets);
+    for id in (0..MAX_HARTS).filter(|id| targets & 1 << id != 0) {
+        while USER_TOKENS[id].load(Ordering::Acquire) == token {
+            spin_loop();
+        }
+    }
+}
+
+/// 唤醒空闲等待中的核
+pub fn wake_hart(id: usize) {
+    send_ipi(1 << id);
+}
+
+/// 清除当前核挂起的核间中断
+pub fn clear_ipi() {
+    unsafe {
+        // clear SSIP bit
+        core::arch::asm!("csrc sip, {}", in(reg) 1 << 1);
+    }
+}
+
+/// 初始化当前核：开启核间中断
+pub fn init_hart() {
+    unsafe {
+        // set SSIE bit
+        core::arch::asm!("csrs sie, {}", in(reg) 1 << 1);
+    }
+}
+
+/// 启动其余核，它们从_start_secondary进入rust_main_secondary
+pub fn init() {
+    extern "C" {
+        fn _start_secondary();
+    }
+    init_hart();
+    let current = hart_id();
+    let mut online = 1;
+    for id in (0..MAX_HARTS).filter(|&id| id != current) {
+        // 内核恒等映射，虚拟地址即物理地址
+        if hart_start(id, _start_secondary as usize, 0) {
+            online += 1;
+        }
+    }
+    println!("[kernel] {} harts online", online);
+}
@@ -0,0 +1,41 @@
+//! 条件变量子模块
+
+use super::Mutex;
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_signal_pending, get_current_task, WaitQueue,
+};
+
+/// 条件变量
+pub struct Condvar {
+    wait_queue: SpinLock<WaitQueue>,
+}
+
+impl Condvar {
+    /// 创建条件变量
+    pub fn new() -> Self {
+        Self {
+            wait_queue: SpinLock::new(WaitQueue::new()),
+        }
+    }
+
+    /// 唤醒一个等待的线程
+    pub fn signal(&self) {
+        self.wait_queue.lock().wake_one();
+    }
+
+    /// 释放互斥锁并阻塞当前线程，被唤醒后重新加锁
+    /// 重新加锁时收到信号返回false，此时未持有锁
+    pub fn wait(&self, mutex: &Mutex) -> bool {
+        let task = get_current_task();
+        let tid = task.inner.lock().tid();
+        mutex.unlock(tid);
+        // 有待处理信号时不再等待，视为虚假唤醒
+        if !current_signal_pending() {
+            self.wait_queue.lock().push(task.clone());
+            block_current_and_run_next();
+            self.wait_queue.lock().remove(&task);
+        }
+        mutex.lock(tid)
+    }
+}
@@ -0,0 +1,12 @@
+//! 同步原语模块
+mod condvar;
+mod mutex;
+mod semaphore;
+mod sleep_lock;
+mod spin;
+
+pub use condvar::Condvar;
+pub use mutex::Mutex;
+pub use semaphore::Semaphore;
+pub use sleep_lock::{SleepLock, SleepLockGuard};
+pub use spin::SpinLock;
@@ -0,0 +1,75 @@
+//! 互斥锁子模块
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_signal_pending, get_current_task, WaitQueue,
+};
+
+/// 阻塞式互斥锁
+pub struct Mutex {
+    inner: SpinLock<MutexInner>,
+}
+
+struct MutexInner {
+    /// 持有锁的线程号，未加锁时为None
+    owner: Option<usize>,
+    wait_queue: WaitQueue,
+}
+
+impl Mutex {
+    /// 创建未加锁的互斥锁
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(MutexInner {
+                owner: None,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+
+    /// 以tid号线程的身份加锁，锁被占用时阻塞当前线程
+    /// 等待期间收到信号时返回false，此时未持有锁
+    pub fn lock(&self, tid: usize) -> bool {
+        loop {
+            let mut inner = self.inner.lock();
+            if inner.owner.is_none() {
+                inner.owner = Some(tid);
+                return true;
+            }
+            if current_signal_pending() {
+                return false;
+            }
+            let task = get_current_task();
+            inner.wait_queue.push(task.clone());
+            drop(inner);
+            block_current_and_run_next();
+            self.inner.lock().wait_queue.remove(&task);
+        }
+    }
+
+    /// 解锁并唤醒一个等待的线程，锁不由tid号线程持有时返回false
+    pub fn unlock(&self, tid: usize) -> bool {
+        let mut inner = self.inner.lock();
+        if inner.owner != Some(tid) {
+            return false;
+        }
+        inner.owner = None;
+        inner.wait_queue.wake_one();
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    test!(test_mutex, {
+        let mutex = Mutex::new();
+        test_assert!(!mutex.unlock(0));
+        test_assert!(mutex.lock(0));
+        // 只有持有者能解锁
+        test_assert!(!mutex.unlock(1));
+        test_assert!(mutex.unlock(0));
+        test_assert!(mutex.lock(1) && mutex.unlock(1));
+        Ok("passed")
+    });
+}
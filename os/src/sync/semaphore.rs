@@ -0,0 +1,69 @@
+//! 信号量子模块
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_signal_pending, get_current_task, WaitQueue,
+};
+
+/// 阻塞式计数信号量
+pub struct Semaphore {
+    inner: SpinLock<SemaphoreInner>,
+}
+
+struct SemaphoreInner {
+    count: usize,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// 创建初值为count的信号量
+    pub fn new(count: usize) -> Self {
+        Self {
+            inner: SpinLock::new(SemaphoreInner {
+                count,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+
+    /// V操作：计数加一并唤醒一个等待的线程
+    pub fn up(&self) {
+        let mut inner = self.inner.lock();
+        inner.count += 1;
+        inner.wait_queue.wake_one();
+    }
+
+    /// P操作：计数为零时阻塞当前线程
+    /// 等待期间收到信号时返回false，此时计数未被减少
+    pub fn down(&self) -> bool {
+        loop {
+            let mut inner = self.inner.lock();
+            if inner.count > 0 {
+                inner.count -= 1;
+                return true;
+            }
+            if current_signal_pending() {
+                return false;
+            }
+            let task = get_current_task();
+            inner.wait_queue.push(task.clone());
+            drop(inner);
+            block_current_and_run_next();
+            self.inner.lock().wait_queue.remove(&task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    test!(test_semaphore, {
+        let semaphore = Semaphore::new(2);
+        test_assert!(semaphore.down());
+        test_assert!(semaphore.down());
+        semaphore.up();
+        test_assert!(semaphore.down());
+        test_assert!(semaphore.inner.lock().count == 0);
+        Ok("passed")
+    });
+}
@@ -0,0 +1,99 @@
+//! 睡眠锁子模块
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, try_get_current_task, ThreadControlBlock, WaitQueue,
+};
+use alloc::sync::Arc;
+
+/// 内核数据结构使用的可重入睡眠锁
+/// 持有者可以在持锁期间阻塞（如等待磁盘），其他线程阻塞等待且不会被信号打断
+pub struct SleepLock {
+    inner: SpinLock<SleepLockInner>,
+}
+
+struct SleepLockInner {
+    owner: Option<Arc<ThreadControlBlock>>,
+    /// 持有者的重入层数
+    depth: usize,
+    wait_queue: WaitQueue,
+}
+
+/// 睡眠锁的持有凭证，离开作用域时解锁
+pub struct SleepLockGuard<'a> {
+    lock: &'a SleepLock,
+    /// 没有当前线程（如启动阶段）时不记录持有者
+    held: bool,
+}
+
+impl SleepLock {
+    /// 创建未加锁的睡眠锁
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(SleepLockInner {
+                owner: None,
+                depth: 0,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+
+    /// 加锁，锁被其他线程持有时阻塞当前线程
+    pub fn lock(&self) -> SleepLockGuard<'_> {
+        let task = match try_get_current_task() {
+            Some(task) => task,
+            None => {
+                return SleepLockGuard {
+                    lock: self,
+                    held: false,
+                }
+            }
+        };
+        loop {
+            let mut inner = self.inner.lock();
+            match &inner.owner {
+                None => inner.owner = Some(task),
+                Some(owner) if Arc::ptr_eq(owner, &task) => {}
+                Some(_) => {
+                    inner.wait_queue.push(task.clone());
+                    drop(inner);
+                    block_current_and_run_next();
+                    self.inner.lock().wait_queue.remove(&task);
+                    continue;
+                }
+            }
+            inner.depth += 1;
+            return SleepLockGuard {
+                lock: self,
+                held: true,
+            };
+        }
+    }
+}
+
+impl Drop for SleepLockGuard<'_> {
+    fn drop(&mut self) {
+        if !self.held {
+            return;
+        }
+        let mut inner = self.lock.inner.lock();
+        inner.depth -= 1;
+        if inner.depth == 0 {
+            inner.owner = None;
+            inner.wait_queue.wake_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    test!(test_sleep_lock, {
+        let lock = SleepLock::new();
+        let guard = lock.lock();
+        // 测试时没有当前线程，加锁不记录持有者
+        test_assert!(!guard.held && lock.inner.lock().owner.is_none());
+        drop(guard);
+        Ok("passed")
+    });
+}
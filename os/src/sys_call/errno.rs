@@ -0,0 +1,65 @@
+//! 系统调用错误码子模块
+//!
+//! 错误码与Linux一致，系统调用失败时以负值返回给用户程序
+
+/// 系统调用错误码
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(isize)]
+pub enum Errno {
+    /// 操作不被允许
+    EPERM = 1,
+    /// 文件或目录不存在
+    ENOENT = 2,
+    /// 进程或线程不存在
+    ESRCH = 3,
+    /// 被信号打断
+    EINTR = 4,
+    /// 参数列表过长
+    E2BIG = 7,
+    /// 不是可执行文件
+    ENOEXEC = 8,
+    /// 文件描述符无效
+    EBADF = 9,
+    /// 没有子进程
+    ECHILD = 10,
+    /// 资源暂时不可用
+    EAGAIN = 11,
+    /// 内存不足
+    ENOMEM = 12,
+    /// 权限不足
+    EACCES = 13,
+    /// 地址无效
+    EFAULT = 14,
+    /// 设备或资源忙
+    EBUSY = 16,
+    /// 文件已存在
+    EEXIST = 17,
+    /// 不支持的文件系统类型
+    ENODEV = 19,
+    /// 不是目录
+    ENOTDIR = 20,
+    /// 是目录
+    EISDIR = 21,
+    /// 参数无效
+    EINVAL = 22,
+    /// 不是终端设备
+    ENOTTY = 25,
+    /// 设备上没有剩余空间
+    ENOSPC = 28,
+    /// 结果超出范围
+    ERANGE = 34,
+    /// 会导致死锁
+    EDEADLK = 35,
+    /// 文件名过长
+    ENAMETOOLONG = 36,
+    /// 系统调用不存在
+    ENOSYS = 38,
+    /// 目录非空
+    ENOTEMPTY = 39,
+}
+
+pub use Errno::*;
+
+/// 系统调用结果，成功时为返回值
+pub type SysResult = Result<isize, Errno>;
@@ -1,109 +1,119 @@
 //! 文件相关系统调用子模块
+use alloc::string::String;
 use core::mem::size_of;
 use core::ptr::slice_from_raw_parts;
 
-use crate::fs::inode::{open_file, OpenFlags};
+use crate::fs::inode::{open, OpenFlags};
 use crate::fs::pipe::make_pipe;
+use crate::fs::rfs::get_full_path;
 use crate::fs::rfs::layout::DIRENT_SZ;
-use crate::fs::rfs::{find_inode, get_full_path, layout::InodeType};
-use crate::fs::Stat;
-use crate::memory::frame::user_buffer::{get_user_buffer, get_user_string, put_user_value};
-use crate::task::get_current_process;
+use crate::fs::stdio::{foreground_pgid, set_foreground_pgid};
+use crate::fs::vfs::{self, find_inode, is_mount_point};
+use crate::fs::{create_fs, Stat, REG};
+use crate::memory::frame::user_buffer::{
+    get_user_buffer, get_user_buffer_mut, get_user_string, get_user_value, put_user_value,
+};
+use crate::task::{all_processes, find_process_group, get_current_process};
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
+use super::errno::*;
+
+/// ioctl: 获取终端的前台进程组
+const TIOCGPGRP: usize = 0x540f;
+/// ioctl: 设置终端的前台进程组
+const TIOCSPGRP: usize = 0x5410;
+
+/// 读取用户传入的路径并转换为绝对路径
+fn get_user_path(path: *const u8) -> Result<String, Errno> {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
-    let path = get_user_string(proc_inner.token(), path);
-    let path = get_full_path(&proc_inner.cwd, &path);
-
-    if let Some(inode) = open_file(&path, OpenFlags(flags)) {
-        let fd = proc_inner.alloc_fd();
-        proc_inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
-    }
+    let mut proc_inner = proc.inner.lock();
+    let path = get_user_string(&mut proc_inner.memory_set, path)?;
+    Ok(get_full_path(&proc_inner.cwd, &path))
+}
+
+pub fn sys_open(path: *const u8, flags: u32) -> SysResult {
+    // 路径查找可能进入procfs，需要借用各进程控制块
+    let path = get_user_path(path)?;
+    let file = open(&path, OpenFlags(flags))?;
+    let proc = get_current_process();
+    let mut proc_inner = proc.inner.lock();
+    let fd = proc_inner.alloc_fd();
+    proc_inner.fd_table[fd] = Some(file);
+    Ok(fd as isize)
 }
 
-pub fn sys_close(fd: usize) -> isize {
+pub fn sys_close(fd: usize) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
+    let mut proc_inner = proc.inner.lock();
     let fd_table = &mut proc_inner.fd_table;
 
     if fd >= fd_table.len() || fd_table[fd].is_none() {
-        return -1;
+        return Err(EBADF);
     }
     fd_table[fd].take();
-    0
+    Ok(0)
 }
 
-pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
-    let user_buffer = get_user_buffer(proc_inner.token(), buf, len);
+    let mut proc_inner = proc.inner.lock();
+    let user_buffer = get_user_buffer_mut(&mut proc_inner.memory_set, buf, len)?;
     let fd_table = &mut proc_inner.fd_table;
     if fd >= fd_table.len() {
-        return -1;
+        return Err(EBADF);
     }
     if let Some(file) = fd_table[fd].clone() {
         if !file.readable() {
-            return -1;
+            return Err(EBADF);
         }
         drop(proc_inner);
-        file.read(user_buffer) as isize
+        Ok(file.read(user_buffer) as isize)
     } else {
-        -1
+        Err(EBADF)
     }
 }
 
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
-    let user_buffer = get_user_buffer(proc_inner.token(), buf, len);
+    let mut proc_inner = proc.inner.lock();
+    let user_buffer = get_user_buffer(&mut proc_inner.memory_set, buf, len)?;
     let fd_table = &mut proc_inner.fd_table;
 
     if fd >= fd_table.len() {
-        return -1;
+        return Err(EBADF);
     }
     if let Some(file) = fd_table[fd].clone() {
         if !file.writable() {
-            return -1;
+            return Err(EBADF);
         }
         drop(proc_inner);
-        file.write(user_buffer) as isize
+        let write_size = file.write(user_buffer);
+        // 普通文件一个字节都没有写入说明文件系统空间已满
+        if write_size == 0 && len > 0 && file.get_mode() == REG {
+            return Err(ENOSPC);
+        }
+        Ok(write_size as isize)
     } else {
-        -1
+        Err(EBADF)
     }
 }
 
-pub fn sys_chdir(path: *const u8) -> isize {
-    let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
-    let path = get_user_string(proc_inner.token(), path);
-    let path = get_full_path(&proc_inner.cwd, &path);
-
-    if let Some(inode) = find_inode(&path) {
-        if inode.is_dir() {
-            proc_inner.cwd = path;
-            0
-        } else {
-            // not dir
-            -2
-        }
-    } else {
-        // no such file
-        -1
+pub fn sys_chdir(path: *const u8) -> SysResult {
+    let path = get_user_path(path)?;
+    if !find_inode(&path).ok_or(ENOENT)?.is_dir() {
+        return Err(ENOTDIR);
     }
+    get_current_process().inner.lock().cwd = path;
+    Ok(0)
 }
 
-pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> SysResult {
     let proc = get_current_process();
-    let proc_inner = proc.inner.borrow_mut();
-    let user_buffer = get_user_buffer(proc_inner.token(), buf, len);
+    let mut proc_inner = proc.inner.lock();
+    let user_buffer = get_user_buffer_mut(&mut proc_inner.memory_set, buf, len)?;
     let cwd = proc_inner.cwd.as_bytes();
 
     if cwd.len() > len {
-        return -1;
+        return Err(ERANGE);
     }
     let mut cur_offset = 0;
     for slice in user_buffer.0.into_iter() {
@@ -111,117 +121,97 @@ pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
         slice[..len].copy_from_slice(&cwd[cur_offset..cur_offset + len]);
         cur_offset += len;
     }
-    cwd.len() as isize
+    Ok(cwd.len() as isize)
 }
 
-pub fn sys_mkdir(path: *const u8) -> isize {
-    let proc = get_current_process();
-    let proc_inner = proc.inner.borrow_mut();
-    let path = get_user_string(proc_inner.token(), path);
-    let path = get_full_path(&proc_inner.cwd, &path);
-
+pub fn sys_mkdir(path: *const u8) -> SysResult {
+    let path = get_user_path(path)?;
     let (parent_path, target) = path.rsplit_once('/').unwrap();
-    if let Some(parent_inode) = find_inode(parent_path) {
-        if let Some(cur_inode) = parent_inode.create(target, InodeType::Directory) {
-            cur_inode.set_default_dirent(parent_inode.get_inode_id());
-            0
-        } else {
-            // file exists
-            -2
-        }
-    } else {
-        // no such file
-        -1
+    let parent_inode = find_inode(parent_path).ok_or(ENOENT)?;
+    if target.is_empty() || parent_inode.lookup(target).is_some() {
+        return Err(EEXIST);
     }
+    parent_inode.mkdir(target)?;
+    Ok(0)
 }
 
-pub fn sys_pipe(pipe: *mut usize) -> isize {
+pub fn sys_pipe(pipe: *mut usize) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
+    let mut proc_inner = proc.inner.lock();
     let (pipe_read, pipe_write) = make_pipe();
     let read_fd = proc_inner.alloc_fd();
     proc_inner.fd_table[read_fd] = Some(pipe_read);
     let write_fd = proc_inner.alloc_fd();
     proc_inner.fd_table[write_fd] = Some(pipe_write);
 
-    put_user_value(proc_inner.token(), read_fd, pipe as *mut u8);
-    put_user_value(proc_inner.token(), write_fd, unsafe { pipe.add(1) }
-        as *mut u8);
-    0
+    put_user_value(&mut proc_inner.memory_set, read_fd, pipe as *mut u8)?;
+    put_user_value(&mut proc_inner.memory_set, write_fd, unsafe { pipe.add(1) }
+        as *mut u8)?;
+    Ok(0)
 }
 
 const SEEK_SET: u32 = 0;
 const SEEK_CUR: u32 = 1;
 const SEEK_END: u32 = 2;
 
-pub fn sys_lseek(fd: usize, offset: isize, whence: u32) -> isize {
+pub fn sys_lseek(fd: usize, offset: isize, whence: u32) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
+    let mut proc_inner = proc.inner.lock();
     let fd_table = &mut proc_inner.fd_table;
 
     if fd >= fd_table.len() || fd_table[fd].is_none() {
-        return -1;
+        return Err(EBADF);
     }
     let file = fd_table[fd].clone().unwrap();
+    drop(proc_inner);
     let cur_offset = file.get_offset() as isize;
     let file_size = file.get_file_size() as isize;
     let new_offset = match whence {
         SEEK_SET => offset,
         SEEK_CUR => cur_offset + offset,
         SEEK_END => file_size + offset,
-        _ => return -2,
+        _ => return Err(EINVAL),
     };
     if new_offset < 0 {
-        -1
+        Err(EINVAL)
     } else {
         file.set_offset(new_offset as usize);
-        new_offset
+        Ok(new_offset)
     }
 }
 
 const AT_REMOVEDIR: u32 = 1;
 
-pub fn sys_unlink(path: *const u8, flags: u32) -> isize {
-    let proc = get_current_process();
-    let proc_inner = proc.inner.borrow_mut();
-    let path = get_user_string(proc_inner.token(), path);
-    let path = get_full_path(&proc_inner.cwd, &path);
-
+pub fn sys_unlink(path: *const u8, flags: u32) -> SysResult {
+    let path = get_user_path(path)?;
     let (parent_path, target) = path.rsplit_once('/').unwrap();
-    if let Some(inode) = find_inode(&path) {
-        if flags & AT_REMOVEDIR == 0 && !inode.is_dir() {
-            inode.clear();
-            find_inode(parent_path).unwrap().delete(target);
-            0
-        } else if flags & AT_REMOVEDIR == 1 && inode.is_dir() {
-            if inode.get_file_size() as usize == DIRENT_SZ * 2 {
-                inode.clear();
-                find_inode(parent_path).unwrap().delete(target);
-                0
-            } else {
-                // not empty
-                -3
-            }
-        } else {
-            // type not matched
-            -2
+    let inode = find_inode(&path).ok_or(ENOENT)?;
+    if flags & AT_REMOVEDIR == 0 {
+        if inode.is_dir() {
+            return Err(EISDIR);
         }
-    } else {
-        // no such file
-        -1
+    } else if !inode.is_dir() {
+        return Err(ENOTDIR);
+    } else if is_mount_point(&path) {
+        return Err(EBUSY);
+    } else if inode.size() != DIRENT_SZ * 2 {
+        return Err(ENOTEMPTY);
     }
+    find_inode(parent_path).unwrap().unlink(target)?;
+    Ok(0)
 }
 
-pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
+pub fn sys_fstat(fd: usize, stat: *mut u8) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
-    let user_buffer = get_user_buffer(proc_inner.token(), stat, size_of::<Stat>());
+    let mut proc_inner = proc.inner.lock();
+    let user_buffer = get_user_buffer_mut(&mut proc_inner.memory_set, stat, size_of::<Stat>())?;
     let fd_table = &mut proc_inner.fd_table;
 
     if fd >= fd_table.len() || fd_table[fd].is_none() {
-        return -1;
+        return Err(EBADF);
     }
     let file = fd_table[fd].clone().unwrap();
+    drop(proc_inner);
     let tmp_stat = Stat::from(file);
     let stat_buf = slice_from_raw_parts(&tmp_stat as *const _ as *const u8, size_of::<Stat>());
     for (i, byte) in user_buffer.into_iter().enumerate() {
@@ -229,19 +219,96 @@ pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
             *byte = (*stat_buf)[i];
         }
     }
-    0
+    Ok(0)
 }
 
-pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> SysResult {
     let proc = get_current_process();
-    let mut proc_inner = proc.inner.borrow_mut();
+    let mut proc_inner = proc.inner.lock();
     let fd_table = &mut proc_inner.fd_table;
     if old_fd >= fd_table.len() || fd_table[old_fd].is_none() {
-        return -1;
+        return Err(EBADF);
     }
     if new_fd >= fd_table.len() {
         fd_table.resize(new_fd + 1, None);
     }
     fd_table[new_fd] = fd_table[old_fd].clone();
-    0
+    Ok(0)
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: *mut u8) -> SysResult {
+    let proc = get_current_process();
+    let mut proc_inner = proc.inner.lock();
+    let file = match proc_inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(EBADF),
+    };
+    if !file.is_tty() {
+        return Err(ENOTTY);
+    }
+    match cmd {
+        TIOCGPGRP => {
+            let pgid = foreground_pgid().ok_or(ENOTTY)?;
+            put_user_value(&mut proc_inner.memory_set, pgid, arg)?;
+            Ok(0)
+        }
+        TIOCSPGRP => {
+            let mut pgid = 0usize;
+            get_user_value(&mut proc_inner.memory_set, arg, &mut pgid)?;
+            let sid = proc_inner.sid;
+            drop(proc_inner);
+            // 前台进程组必须属于调用者的会话
+            if !find_process_group(pgid)
+                .iter()
+                .any(|member| member.inner.lock().sid == sid)
+            {
+                return Err(EPERM);
+            }
+            set_foreground_pgid(pgid);
+            Ok(0)
+        }
+        _ => Err(EINVAL),
+    }
+}
+
+pub fn sys_mount(
+    source: *const u8,
+    target: *const u8,
+    fs_type: *const u8,
+    _flags: u32,
+    data: *const u8,
+) -> SysResult {
+    let proc = get_current_process();
+    let mut proc_inner = proc.inner.lock();
+    // 目前的文件系统类型都不需要源设备
+    let _source = get_user_string(&mut proc_inner.memory_set, source)?;
+    let fs_type = get_user_string(&mut proc_inner.memory_set, fs_type)?;
+    // 挂载选项为逗号分隔的字符串，可以为空指针
+    let options = if data.is_null() {
+        String::new()
+    } else {
+        get_user_string(&mut proc_inner.memory_set, data)?
+    };
+    drop(proc_inner);
+    let target = get_user_path(target)?;
+    let sb = create_fs(&fs_type, &options)?;
+    vfs::mount(&target, sb)?;
+    Ok(0)
+}
+
+pub fn sys_umount(target: *const u8, _flags: u32) -> SysResult {
+    let target = get_user_path(target)?;
+    if !is_mount_point(&target) {
+        return Err(EINVAL);
+    }
+    // 有进程的工作目录位于其中时不能卸载
+    let prefix = String::from(&target) + "/";
+    if all_processes().iter().any(|proc| {
+        let cwd = &proc.inner.lock().cwd;
+        *cwd == target || cwd.starts_with(&prefix)
+    }) {
+        return Err(EBUSY);
+    }
+    vfs::umount(&target)?;
+    Ok(0)
 }
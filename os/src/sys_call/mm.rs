@@ -0,0 +1,105 @@
+//! 内存相关系统调用子模块
+
+use super::errno::*;
+use crate::config::{MMAP_BASE, MMAP_TOP, PAGE_SIZE};
+use crate::memory::frame::address::{VirtAddr, VirtPageNum};
+use crate::memory::frame::page_table::{R, U, W, X};
+use crate::memory::frame::segment::SegFlags;
+use crate::task::get_current_process;
+use core::ops::Range;
+
+const PROT_READ: usize = 1 << 0;
+const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+
+/// 将mmap权限转换为段标志位，PROT_NONE的段不可访问
+fn prot_to_flags(prot: usize) -> Option<SegFlags> {
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return None;
+    }
+    let mut flags = U;
+    // SV39不支持只写页面
+    if prot & (PROT_READ | PROT_WRITE) != 0 {
+        flags |= R;
+    }
+    if prot & PROT_WRITE != 0 {
+        flags |= W;
+    }
+    if prot & PROT_EXEC != 0 {
+        flags |= X;
+    }
+    Some(flags)
+}
+
+/// 将用户给出的地址区间转换为mmap区域内的虚拟页区间
+fn mmap_area(start: usize, len: usize) -> Option<Range<VirtPageNum>> {
+    if start % PAGE_SIZE != 0 || len == 0 {
+        return None;
+    }
+    let start_vpn = VirtAddr(start).vpn();
+    let end_vpn = VirtPageNum(start_vpn.0 + (len - 1) / PAGE_SIZE + 1);
+    if start_vpn < VirtAddr(MMAP_BASE).vpn() || end_vpn > VirtAddr(MMAP_TOP).vpn() {
+        return None;
+    }
+    Some(start_vpn..end_vpn)
+}
+
+/// 映射匿名内存，start为0时由内核选择地址
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> SysResult {
+    let flags = prot_to_flags(prot).ok_or(EINVAL)?;
+    if len == 0 {
+        return Err(EINVAL);
+    }
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    let vpn_range = if start == 0 {
+        let page_count = (len - 1) / PAGE_SIZE + 1;
+        let area = VirtAddr(MMAP_BASE).vpn()..VirtAddr(MMAP_TOP).vpn();
+        match inner.memory_set.find_free_area(area, page_count) {
+            Some(vpn_range) => vpn_range,
+            // out of address space
+            None => return Err(ENOMEM),
+        }
+    } else {
+        let vpn_range = mmap_area(start, len).ok_or(EINVAL)?;
+        if !inner.memory_set.is_free(&vpn_range) {
+            return Err(EEXIST);
+        }
+        vpn_range
+    };
+    let start = vpn_range.start.addr().0;
+    inner
+        .memory_set
+        .insert_lazy_segment(vpn_range, flags, None)?;
+    Ok(start as isize)
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> SysResult {
+    let vpn_range = mmap_area(start, len).ok_or(EINVAL)?;
+    let proc = get_current_process();
+    proc.inner.lock().memory_set.remove_area(vpn_range);
+    Ok(0)
+}
+
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> SysResult {
+    let vpn_range = mmap_area(start, len).ok_or(EINVAL)?;
+    let flags = prot_to_flags(prot).ok_or(EINVAL)?;
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    if !inner.memory_set.is_covered(&vpn_range) {
+        // area not mapped
+        return Err(ENOMEM);
+    }
+    inner.memory_set.protect_area(vpn_range, flags);
+    Ok(0)
+}
+
+/// 调整堆顶，addr为0时仅查询，返回调整后的堆顶（失败时为原堆顶）
+pub fn sys_brk(addr: usize) -> SysResult {
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    if addr != 0 {
+        inner.memory_set.set_brk(addr);
+    }
+    Ok(inner.memory_set.brk() as isize)
+}
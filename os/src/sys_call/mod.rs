@@ -1,14 +1,25 @@
 //! 系统调用模块
+pub mod errno;
 mod fs;
+mod mm;
 mod proc;
+mod sync;
+mod thread;
 
+use errno::*;
 use fs::*;
+use mm::*;
 use proc::*;
+use sync::*;
+use thread::*;
 
 const SYS_CALL_GETCWD: usize = 17;
 const SYS_CALL_DUP2: usize = 24;
+const SYS_CALL_IOCTL: usize = 29;
 const SYS_CALL_MKDIR: usize = 34;
 const SYS_CALL_UNLINK: usize = 35;
+const SYS_CALL_UMOUNT2: usize = 39;
+const SYS_CALL_MOUNT: usize = 40;
 const SYS_CALL_CHDIR: usize = 49;
 const SYS_CALL_OPEN: usize = 56;
 const SYS_CALL_CLOSE: usize = 57;
@@ -18,20 +29,57 @@ const SYS_CALL_READ: usize = 63;
 const SYS_CALL_WRITE: usize = 64;
 const SYS_CALL_FSTAT: usize = 80;
 const SYS_CALL_EXIT: usize = 93;
+const SYS_CALL_NANOSLEEP: usize = 101;
 const SYS_CALL_YIELD: usize = 124;
 const SYS_CALL_KILL: usize = 129;
+const SYS_CALL_SIGACTION: usize = 134;
+const SYS_CALL_SIGPROCMASK: usize = 135;
+const SYS_CALL_SIGRETURN: usize = 139;
+const SYS_CALL_SETPRIORITY: usize = 140;
+const SYS_CALL_GETPRIORITY: usize = 141;
+const SYS_CALL_TIMES: usize = 153;
+const SYS_CALL_SETPGID: usize = 154;
+const SYS_CALL_GETPGID: usize = 155;
+const SYS_CALL_SETSID: usize = 157;
+const SYS_CALL_GETRUSAGE: usize = 165;
 const SYS_CALL_GETTIME: usize = 169;
 const SYS_CALL_GETPID: usize = 172;
+const SYS_CALL_GETTID: usize = 178;
+const SYS_CALL_BRK: usize = 214;
+const SYS_CALL_MUNMAP: usize = 215;
 const SYS_CALL_FORK: usize = 220;
 const SYS_CALL_EXEC: usize = 221;
+const SYS_CALL_MMAP: usize = 222;
+const SYS_CALL_MPROTECT: usize = 226;
 const SYS_CALL_WAITPID: usize = 260;
+const SYS_CALL_THREAD_CREATE: usize = 1000;
+const SYS_CALL_WAITTID: usize = 1002;
+const SYS_CALL_MUTEX_CREATE: usize = 1010;
+const SYS_CALL_MUTEX_LOCK: usize = 1011;
+const SYS_CALL_MUTEX_UNLOCK: usize = 1012;
+const SYS_CALL_SEMAPHORE_CREATE: usize = 1020;
+const SYS_CALL_SEMAPHORE_UP: usize = 1021;
+const SYS_CALL_SEMAPHORE_DOWN: usize = 1022;
+const SYS_CALL_CONDVAR_CREATE: usize = 1030;
+const SYS_CALL_CONDVAR_SIGNAL: usize = 1031;
+const SYS_CALL_CONDVAR_WAIT: usize = 1032;
 
-pub fn sys_call(which: usize, args: [usize; 3]) -> isize {
-    match which {
-        SYS_CALL_GETCWD => sys_getcwd(args[0] as *const u8, args[1]),
+/// 分发系统调用，失败时返回负的错误码
+pub fn sys_call(which: usize, args: [usize; 5]) -> isize {
+    let ret = match which {
+        SYS_CALL_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
         SYS_CALL_DUP2 => sys_dup2(args[0], args[1]),
+        SYS_CALL_IOCTL => sys_ioctl(args[0], args[1], args[2] as *mut u8),
         SYS_CALL_MKDIR => sys_mkdir(args[0] as *const u8),
         SYS_CALL_UNLINK => sys_unlink(args[0] as *const u8, args[1] as u32),
+        SYS_CALL_UMOUNT2 => sys_umount(args[0] as *const u8, args[1] as u32),
+        SYS_CALL_MOUNT => sys_mount(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3] as u32,
+            args[4] as *const u8,
+        ),
         SYS_CALL_CHDIR => sys_chdir(args[0] as *const u8),
         SYS_CALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
         SYS_CALL_CLOSE => sys_close(args[0]),
@@ -42,12 +90,47 @@ pub fn sys_call(which: usize, args: [usize; 3]) -> isize {
         SYS_CALL_FSTAT => sys_fstat(args[0], args[1] as *mut u8),
         SYS_CALL_EXIT => sys_exit(args[0] as i32),
         SYS_CALL_YIELD => sys_yield(),
-        SYS_CALL_KILL => sys_kill(args[0]),
+        SYS_CALL_KILL => sys_kill(args[0] as isize, args[1]),
+        SYS_CALL_SIGACTION => sys_sigaction(args[0], args[1] as *const u8, args[2] as *mut u8),
+        SYS_CALL_SIGPROCMASK => sys_sigprocmask(args[0], args[1] as u32),
+        SYS_CALL_SIGRETURN => sys_sigreturn(),
+        SYS_CALL_SETPRIORITY => sys_setpriority(args[0], args[1], args[2] as isize),
+        SYS_CALL_GETPRIORITY => sys_getpriority(args[0], args[1]),
+        SYS_CALL_TIMES => sys_times(args[0] as *mut u8),
+        SYS_CALL_SETPGID => sys_setpgid(args[0], args[1]),
+        SYS_CALL_GETPGID => sys_getpgid(args[0]),
+        SYS_CALL_SETSID => sys_setsid(),
+        SYS_CALL_GETRUSAGE => sys_getrusage(args[0] as isize, args[1] as *mut u8),
+        SYS_CALL_NANOSLEEP => sys_nanosleep(args[0] as *const u8, args[1] as *mut u8),
         SYS_CALL_GETTIME => sys_gettime(),
         SYS_CALL_GETPID => sys_getpid(),
+        SYS_CALL_GETTID => sys_gettid(),
+        SYS_CALL_BRK => sys_brk(args[0]),
+        SYS_CALL_MUNMAP => sys_munmap(args[0], args[1]),
         SYS_CALL_FORK => sys_fork(),
-        SYS_CALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const *const u8),
-        SYS_CALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut u8),
-        _ => panic!("sys_call with unknown id: {}", which),
+        SYS_CALL_EXEC => sys_exec(
+            args[0] as *const u8,
+            args[1] as *const *const u8,
+            args[2] as *const *const u8,
+        ),
+        SYS_CALL_MMAP => sys_mmap(args[0], args[1], args[2]),
+        SYS_CALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYS_CALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut u8, args[2]),
+        SYS_CALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
+        SYS_CALL_WAITTID => sys_waittid(args[0], args[1] as *mut u8),
+        SYS_CALL_MUTEX_CREATE => sys_mutex_create(),
+        SYS_CALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
+        SYS_CALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
+        SYS_CALL_SEMAPHORE_CREATE => sys_semaphore_create(args[0]),
+        SYS_CALL_SEMAPHORE_UP => sys_semaphore_up(args[0]),
+        SYS_CALL_SEMAPHORE_DOWN => sys_semaphore_down(args[0]),
+        SYS_CALL_CONDVAR_CREATE => sys_condvar_create(),
+        SYS_CALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
+        SYS_CALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        _ => Err(ENOSYS),
+    };
+    match ret {
+        Ok(ret) => ret,
+        Err(errno) => -(errno as isize),
     }
 }
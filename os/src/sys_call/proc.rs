@@ -1,112 +1,459 @@
 //! 进程相关系统调用子模块
 
-use crate::fs::rfs::{find_inode, get_full_path};
-use crate::interrupt::timer::get_time_ms;
+use super::errno::*;
+use crate::config::ARG_MAX;
+use crate::fs::rfs::get_full_path;
+use crate::fs::vfs::find_inode;
+use crate::interrupt::timer::{
+    add_sleeping_task, get_time_ms, remove_sleeping_task, ticks_to_ms, TimeSpec, TimeVal,
+};
+use crate::memory::frame::memory_set::MemorySet;
 use crate::memory::frame::user_buffer::{get_user_string, get_user_value, put_user_value};
+use crate::task::signal::*;
 use crate::task::{
-    add_new_task, exit_current_and_run_next, get_current_process, suspend_current_and_run_next,
-    TaskStatus, TASK_MANAGER,
+    add_new_task, all_processes, block_current_and_run_next, cond_resched,
+    exit_current_thread_and_run_next, find_process, find_process_group, get_current_process,
+    get_current_task, send_signal, suspend_current_and_run_next, ProcessControlBlock, DAEMON,
 };
+use crate::tools::elf_decoder::ElfFile;
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// exec时每次从文件读取的字节数，两次读取之间是内核抢占点
+const EXEC_READ_CHUNK: usize = 0x4000;
+
+/// waitpid: 没有可回收的子进程时立即返回
+const WNOHANG: usize = 1;
+/// waitpid: 同时报告已暂停的子进程
+const WUNTRACED: usize = 2;
+
+/// getrusage: 本进程
+const RUSAGE_SELF: isize = 0;
+/// getrusage: 已回收的子进程
+const RUSAGE_CHILDREN: isize = -1;
+
+/// times返回的CPU时间，单位为毫秒（与用户库中定义一致）
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Tms {
+    utime: usize,
+    stime: usize,
+    cutime: usize,
+    cstime: usize,
+}
+
+/// getrusage返回的资源使用情况（与用户库中定义一致）
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Rusage {
+    utime: TimeVal,
+    stime: TimeVal,
+}
 
 pub fn sys_exit(exit_code: i32) -> ! {
-    exit_current_and_run_next(exit_code);
+    exit_current_thread_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
-pub fn sys_yield() -> isize {
+pub fn sys_yield() -> SysResult {
     suspend_current_and_run_next();
-    0
+    Ok(0)
+}
+
+pub fn sys_gettime() -> SysResult {
+    Ok(get_time_ms() as isize)
 }
 
-pub fn sys_gettime() -> isize {
-    get_time_ms() as isize
+pub fn sys_nanosleep(req: *const u8, rem: *mut u8) -> SysResult {
+    let task = get_current_task();
+    let process = task.process();
+    let mut time = TimeSpec::default();
+    get_user_value(&mut process.inner.lock().memory_set, req, &mut time)?;
+    // 秒数为负或纳秒数超出范围
+    if time.sec > isize::MAX as usize || time.nsec >= 1_000_000_000 {
+        return Err(EINVAL);
+    }
+    let expire_ms = get_time_ms().saturating_add(time.to_ms());
+    loop {
+        let current_ms = get_time_ms();
+        if current_ms >= expire_ms {
+            return Ok(0);
+        }
+        if process.inner.lock().should_interrupt(&task.inner.lock()) {
+            // interrupted by signal
+            if !rem.is_null() {
+                put_user_value(
+                    &mut process.inner.lock().memory_set,
+                    TimeSpec::from_ms(expire_ms - current_ms),
+                    rem,
+                )?;
+            }
+            return Err(EINTR);
+        }
+        add_sleeping_task(expire_ms, task.clone());
+        block_current_and_run_next();
+        remove_sleeping_task(&task);
+    }
 }
 
-pub fn sys_getpid() -> isize {
+/// 获取本进程及已回收子进程的CPU时间，返回系统启动以来的毫秒数
+pub fn sys_times(buf: *mut u8) -> SysResult {
     let proc = get_current_process();
-    proc.pid.0 as isize
+    let mut inner = proc.inner.lock();
+    let tms = Tms {
+        utime: ticks_to_ms(inner.times.user),
+        stime: ticks_to_ms(inner.times.system),
+        cutime: ticks_to_ms(inner.children_times.user),
+        cstime: ticks_to_ms(inner.children_times.system),
+    };
+    put_user_value(&mut inner.memory_set, tms, buf)?;
+    Ok(get_time_ms() as isize)
 }
 
-pub fn sys_fork() -> isize {
+pub fn sys_getrusage(who: isize, usage: *mut u8) -> SysResult {
     let proc = get_current_process();
-    let new_proc = proc.fork();
-    let trap_cx = new_proc.inner.borrow().trap_cx();
-    trap_cx.x[10] = 0;
-    add_new_task(new_proc.clone());
-    new_proc.pid.0 as isize
+    let mut inner = proc.inner.lock();
+    let times = match who {
+        RUSAGE_SELF => inner.times,
+        RUSAGE_CHILDREN => inner.children_times,
+        _ => return Err(EINVAL),
+    };
+    let rusage = Rusage {
+        utime: TimeVal::from_ticks(times.user),
+        stime: TimeVal::from_ticks(times.system),
+    };
+    put_user_value(&mut inner.memory_set, rusage, usage)?;
+    Ok(0)
 }
 
-pub fn sys_exec(path: *const u8, mut arg_ptrs_ptr: *const *const u8) -> isize {
+pub fn sys_getpid() -> SysResult {
     let proc = get_current_process();
-    let proc_inner = proc.inner.borrow();
-    let path = get_user_string(proc_inner.token(), path);
-    let path = get_full_path(&proc_inner.cwd, &path);
-    let mut args = vec![];
+    Ok(proc.pid.0 as isize)
+}
+
+pub fn sys_fork() -> SysResult {
+    let task = get_current_task();
+    let proc = task.process();
+    let new_proc = proc.fork(&task)?;
+    let tid = task.inner.lock().tid();
+    let new_task = new_proc.inner.lock().threads[tid].clone().unwrap();
+    new_task.inner.lock().trap_cx().x[10] = 0;
+    add_new_task(new_task);
+    Ok(new_proc.pid.0 as isize)
+}
+
+/// 读取用户空间中以空指针结尾的字符串指针数组（指针数组本身为空指针时视为空数组）
+fn get_user_strings(
+    memory_set: &mut MemorySet,
+    mut ptrs: *const *const u8,
+) -> Result<Vec<String>, Errno> {
+    let mut strings = vec![];
+    if ptrs.is_null() {
+        return Ok(strings);
+    }
     loop {
-        let mut arg_ptr = 0usize;
-        get_user_value(proc_inner.token(), arg_ptrs_ptr as *const _, &mut arg_ptr);
-        if arg_ptr == 0 {
+        let mut ptr = 0usize;
+        get_user_value(memory_set, ptrs as *const _, &mut ptr)?;
+        if ptr == 0 {
             break;
         }
-        args.push(get_user_string(proc_inner.token(), arg_ptr as *const u8));
+        strings.push(get_user_string(memory_set, ptr as *const u8)?);
         unsafe {
-            arg_ptrs_ptr = arg_ptrs_ptr.add(1);
+            ptrs = ptrs.add(1);
         }
     }
+    Ok(strings)
+}
+
+pub fn sys_exec(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> SysResult {
+    let proc = get_current_process();
+    let mut proc_inner = proc.inner.lock();
+    let path = get_user_string(&mut proc_inner.memory_set, path)?;
+    let path = get_full_path(&proc_inner.cwd, &path);
+    let args = get_user_strings(&mut proc_inner.memory_set, argv)?;
+    let envs = get_user_strings(&mut proc_inner.memory_set, envp)?;
     drop(proc_inner);
+    // 参数与环境变量的字符串及指针需要放在新的用户栈上
+    let total_len: usize = args
+        .iter()
+        .chain(&envs)
+        .map(|s| s.len() + 1 + size_of::<usize>())
+        .sum();
+    if total_len > ARG_MAX {
+        return Err(E2BIG);
+    }
 
-    if let Some(app_inode) = find_inode(&path) {
-        let size = app_inode.get_file_size() as usize;
-        let mut app_data = vec![0u8; size];
-        app_inode.read_at(0, &mut app_data);
-        proc.exec(&app_data, &args);
-        // return argc because cx.x[10] will be covered with it later
-        args.len() as isize
-    } else {
-        -1
+    let app_inode = find_inode(&path).ok_or(ENOENT)?;
+    if app_inode.is_dir() {
+        return Err(EISDIR);
+    }
+    let mut app_data = vec![0u8; app_inode.size()];
+    // 分块读取，时间片在读取期间用完时可以让出处理器
+    for (i, chunk) in app_data.chunks_mut(EXEC_READ_CHUNK).enumerate() {
+        app_inode.read_at(i * EXEC_READ_CHUNK, chunk);
+        cond_resched();
+    }
+    if ElfFile::new(&app_data).is_none() {
+        return Err(ENOEXEC);
     }
+    proc.exec(&get_current_task(), &app_data, &args, &envs)?;
+    // return argc because cx.x[10] will be covered with it later
+    Ok(args.len() as isize)
 }
 
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut u8) -> isize {
-    let process = get_current_process();
-    let mut inner = process.inner.borrow_mut();
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut u8, options: usize) -> SysResult {
+    let task = get_current_task();
+    let process = task.process();
+    loop {
+        let mut inner = process.inner.lock();
+        // pid为-1时等待任意子进程，为0或小于-1时等待本进程组或-pid进程组中的子进程
+        let pgid = inner.pgid;
+        let is_target = |child: &Arc<ProcessControlBlock>| match pid {
+            -1 => true,
+            0 => child.inner.lock().pgid == pgid,
+            pid if pid < 0 => child.inner.lock().pgid == -pid as usize,
+            pid => child.pid.0 == pid as usize,
+        };
 
-    if !inner
-        .children
-        .iter()
-        .any(|child| pid == -1 || pid as usize == child.pid.0)
-    {
-        // child not found
-        return -1;
+        // 守护进程等待任意子进程时，暂无子进程也阻塞，直到有孤儿进程交给它并退出
+        let is_daemon = process.pid.0 == unsafe { DAEMON.pid.0 };
+        if !inner.children.iter().any(is_target) && !(is_daemon && pid == -1) {
+            return Err(ECHILD);
+        }
+
+        let pair = inner
+            .children
+            .iter()
+            .enumerate()
+            .find(|(_, child)| is_target(child) && child.inner.lock().is_reapable());
+        if let Some((idx, child)) = pair {
+            let status = child.inner.lock().wait_status();
+            if !exit_code_ptr.is_null() {
+                // 地址无效时子进程保留，之后仍可回收
+                put_user_value(&mut inner.memory_set, status, exit_code_ptr)?;
+            }
+            let child = inner.children.remove(idx);
+            // 子进程的CPU时间计入父进程
+            let child_inner = child.inner.lock();
+            inner.children_times += child_inner.times;
+            inner.children_times += child_inner.children_times;
+            return Ok(child.pid.0 as isize);
+        }
+
+        if options & WUNTRACED != 0 {
+            let stopped = inner
+                .children
+                .iter()
+                .filter(|child| is_target(child))
+                .find_map(|child| {
+                    let signum = child.inner.lock().stop_report.take()?;
+                    Some((child.pid.0, signum))
+                });
+            if let Some((child_pid, signum)) = stopped {
+                if !exit_code_ptr.is_null() {
+                    let status = (signum as i32) << 8 | 0x7f;
+                    put_user_value(&mut inner.memory_set, status, exit_code_ptr)?;
+                }
+                return Ok(child_pid as isize);
+            }
+        }
+
+        if options & WNOHANG != 0 {
+            // child running
+            return Ok(0);
+        }
+        if inner.should_interrupt(&task.inner.lock()) {
+            return Err(EINTR);
+        }
+        drop(inner);
+        process.wait_queue.lock().push(task.clone());
+        block_current_and_run_next();
+        process.wait_queue.lock().remove(&task);
     }
+}
 
-    let pair = inner.children.iter().enumerate().find(|(_, child)| {
-        child.inner.borrow().task_status == TaskStatus::Exited
-            && (pid == -1 || pid as usize == child.pid.0)
-    });
+/// pid为0时发送给本进程组，为-1时发送给除守护进程外的全部进程，小于-1时发送给-pid进程组
+pub fn sys_kill(pid: isize, signum: usize) -> SysResult {
+    if signum > MAX_SIG {
+        return Err(EINVAL);
+    }
+    let procs = match pid {
+        0 => find_process_group(get_current_process().inner.lock().pgid),
+        -1 => all_processes()
+            .into_iter()
+            .filter(|proc| proc.pid.0 != unsafe { DAEMON.pid.0 })
+            .collect(),
+        pid if pid < 0 => find_process_group(-pid as usize),
+        pid => vec![find_process(pid as usize).ok_or(ESRCH)?],
+    };
+    if procs.is_empty() {
+        return Err(ESRCH);
+    }
+    // 0号信号仅用于检查进程是否存在
+    if signum != 0 {
+        for proc in procs.iter() {
+            send_signal(proc, signum);
+        }
+    }
+    Ok(0)
+}
 
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        let child_pid = child.pid.0;
-        put_user_value(inner.token(), child.inner.borrow().exit_code, exit_code_ptr);
-        child_pid as isize
+/// pid为0时设置本进程，pgid为0时以目标进程的PID作为进程组号
+pub fn sys_setpgid(pid: usize, pgid: usize) -> SysResult {
+    let proc = get_current_process();
+    let target = if pid == 0 || pid == proc.pid.0 {
+        proc.clone()
     } else {
-        // child running
-        -2
+        // 只能设置本进程或子进程
+        let inner = proc.inner.lock();
+        let child = inner.children.iter().find(|child| child.pid.0 == pid);
+        child.cloned().ok_or(ESRCH)?
+    };
+    let pgid = if pgid == 0 { target.pid.0 } else { pgid };
+    let sid = proc.inner.lock().sid;
+    let target_sid = target.inner.lock().sid;
+    // 会话首进程不能离开自己的进程组，也不能设置其他会话中的进程
+    if target_sid != sid || target_sid == target.pid.0 {
+        return Err(EPERM);
+    }
+    // 只能加入同一会话中已存在的进程组
+    if pgid != target.pid.0
+        && !find_process_group(pgid)
+            .iter()
+            .any(|member| member.inner.lock().sid == sid)
+    {
+        return Err(EPERM);
     }
+    target.inner.lock().pgid = pgid;
+    Ok(0)
 }
 
-pub fn sys_kill(pid: usize) -> isize {
-    if get_current_process().pid.0 == pid {
-        exit_current_and_run_next(0);
-        return 0;
+pub fn sys_getpgid(pid: usize) -> SysResult {
+    let proc = if pid == 0 {
+        get_current_process()
+    } else {
+        find_process(pid).ok_or(ESRCH)?
+    };
+    let pgid = proc.inner.lock().pgid;
+    Ok(pgid as isize)
+}
+
+const PRIO_PROCESS: usize = 0;
+const PRIO_PGRP: usize = 1;
+
+/// setpriority与getpriority作用的进程，who为0时为当前进程或当前进程组
+fn priority_targets(which: usize, who: usize) -> Result<Vec<Arc<ProcessControlBlock>>, Errno> {
+    let proc = get_current_process();
+    let targets = match which {
+        PRIO_PROCESS if who == 0 => vec![proc],
+        PRIO_PROCESS => vec![find_process(who).ok_or(ESRCH)?],
+        PRIO_PGRP => {
+            let pgid = if who == 0 {
+                proc.inner.lock().pgid
+            } else {
+                who
+            };
+            find_process_group(pgid)
+        }
+        _ => return Err(EINVAL),
+    };
+    if targets.is_empty() {
+        return Err(ESRCH);
     }
-    unsafe {
-        if let Some(proc) = TASK_MANAGER.tasks().find(|task| task.pid.0 == pid) {
-            proc.inner.borrow_mut().task_status = TaskStatus::Exited;
-            return 0;
+    Ok(targets)
+}
+
+/// 设置进程中所有线程的nice值（-20~19）
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> SysResult {
+    let nice = nice.clamp(-20, 19);
+    for proc in priority_targets(which, who)? {
+        for thread in proc.inner.lock().threads.iter().flatten() {
+            thread.inner.lock().nice = nice;
         }
     }
-    -1
+    Ok(0)
+}
+
+/// 获取进程中最小的nice值，与Linux一致返回20-nice以避免负值
+pub fn sys_getpriority(which: usize, who: usize) -> SysResult {
+    let nice = priority_targets(which, who)?
+        .iter()
+        .flat_map(|proc| {
+            let inner = proc.inner.lock();
+            inner
+                .threads
+                .iter()
+                .flatten()
+                .map(|thread| thread.inner.lock().nice)
+                .collect::<Vec<_>>()
+        })
+        .min()
+        .unwrap_or(0);
+    Ok(20 - nice)
+}
+
+/// 创建新会话，调用者成为新会话及新进程组的首进程
+pub fn sys_setsid() -> SysResult {
+    let proc = get_current_process();
+    let pid = proc.pid.0;
+    // 进程组首进程不能创建会话，否则组内其他进程将位于不同会话
+    if !find_process_group(pid).is_empty() {
+        return Err(EPERM);
+    }
+    let mut inner = proc.inner.lock();
+    inner.pgid = pid;
+    inner.sid = pid;
+    Ok(pid as isize)
+}
+
+pub fn sys_sigaction(signum: usize, action: *const u8, old_action: *mut u8) -> SysResult {
+    if !is_valid_signal(signum) || sig_bit(signum) & UNMASKABLE != 0 {
+        return Err(EINVAL);
+    }
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    if !old_action.is_null() {
+        let old = inner.signal_actions.table[signum];
+        put_user_value(&mut inner.memory_set, old, old_action)?;
+    }
+    if !action.is_null() {
+        let mut new_action = SignalAction::default();
+        get_user_value(&mut inner.memory_set, action, &mut new_action)?;
+        // 被忽略的信号不再保持待处理状态
+        if new_action.ignores(signum) {
+            inner.signals &= !sig_bit(signum);
+        }
+        inner.signal_actions.table[signum] = new_action;
+    }
+    Ok(0)
+}
+
+pub fn sys_sigprocmask(how: usize, set: SignalFlags) -> SysResult {
+    let task = get_current_task();
+    let mut inner = task.inner.lock();
+    let old_mask = inner.signal_mask;
+    let set = set & !UNMASKABLE;
+    inner.signal_mask = match how {
+        SIG_BLOCK => old_mask | set,
+        SIG_UNBLOCK => old_mask & !set,
+        SIG_SETMASK => set,
+        _ => return Err(EINVAL),
+    };
+    Ok(old_mask as isize)
+}
+
+pub fn sys_sigreturn() -> SysResult {
+    let task = get_current_task();
+    let mut inner = task.inner.lock();
+    if let Some(frame) = inner.signal_frame.take() {
+        inner.signal_mask = frame.mask;
+        *inner.trap_cx() = frame.trap_cx;
+        // 返回值会写入a0，此处返回被中断时的a0以恢复现场
+        Ok(frame.trap_cx.x[10] as isize)
+    } else {
+        Err(EINVAL)
+    }
 }
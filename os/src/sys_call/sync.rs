@@ -0,0 +1,112 @@
+//! 同步相关系统调用子模块
+
+use super::errno::*;
+use crate::sync::{Condvar, Mutex, Semaphore};
+use crate::task::{get_current_process, get_current_task};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 放入表中第一个空位，返回其ID
+fn insert_item<T>(list: &mut Vec<Option<Arc<T>>>, item: T) -> usize {
+    if let Some(id) = list.iter().position(|slot| slot.is_none()) {
+        list[id] = Some(Arc::new(item));
+        id
+    } else {
+        list.push(Some(Arc::new(item)));
+        list.len() - 1
+    }
+}
+
+/// 按ID取出表项
+fn get_item<T>(list: &[Option<Arc<T>>], id: usize) -> Option<Arc<T>> {
+    list.get(id).cloned().flatten()
+}
+
+pub fn sys_mutex_create() -> SysResult {
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    Ok(insert_item(&mut inner.mutex_list, Mutex::new()) as isize)
+}
+
+pub fn sys_mutex_lock(mutex_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let mutex = get_item(&proc.inner.lock().mutex_list, mutex_id);
+    let tid = get_current_task().inner.lock().tid();
+    match mutex {
+        Some(mutex) if mutex.lock(tid) => Ok(0),
+        Some(_) => Err(EINTR),
+        None => Err(EINVAL),
+    }
+}
+
+pub fn sys_mutex_unlock(mutex_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let mutex = get_item(&proc.inner.lock().mutex_list, mutex_id);
+    let tid = get_current_task().inner.lock().tid();
+    match mutex {
+        Some(mutex) if mutex.unlock(tid) => Ok(0),
+        // mutex not locked by the current thread
+        Some(_) => Err(EPERM),
+        None => Err(EINVAL),
+    }
+}
+
+pub fn sys_semaphore_create(count: usize) -> SysResult {
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    Ok(insert_item(&mut inner.semaphore_list, Semaphore::new(count)) as isize)
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let semaphore = get_item(&proc.inner.lock().semaphore_list, sem_id);
+    match semaphore {
+        Some(semaphore) => {
+            semaphore.up();
+            Ok(0)
+        }
+        None => Err(EINVAL),
+    }
+}
+
+pub fn sys_semaphore_down(sem_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let semaphore = get_item(&proc.inner.lock().semaphore_list, sem_id);
+    match semaphore {
+        Some(semaphore) if semaphore.down() => Ok(0),
+        Some(_) => Err(EINTR),
+        None => Err(EINVAL),
+    }
+}
+
+pub fn sys_condvar_create() -> SysResult {
+    let proc = get_current_process();
+    let mut inner = proc.inner.lock();
+    Ok(insert_item(&mut inner.condvar_list, Condvar::new()) as isize)
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let condvar = get_item(&proc.inner.lock().condvar_list, condvar_id);
+    match condvar {
+        Some(condvar) => {
+            condvar.signal();
+            Ok(0)
+        }
+        None => Err(EINVAL),
+    }
+}
+
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult {
+    let proc = get_current_process();
+    let inner = proc.inner.lock();
+    let condvar = get_item(&inner.condvar_list, condvar_id);
+    let mutex = get_item(&inner.mutex_list, mutex_id);
+    drop(inner);
+    match (condvar, mutex) {
+        (Some(condvar), Some(mutex)) if condvar.wait(&mutex) => Ok(0),
+        // interrupted by signal before the mutex was reacquired
+        (Some(_), Some(_)) => Err(EINTR),
+        _ => Err(EINVAL),
+    }
+}
@@ -0,0 +1,77 @@
+//! 线程相关系统调用子模块
+
+use super::errno::*;
+use crate::interrupt::{context::Context, handler::interrupt_handler};
+use crate::memory::frame::memory_set::kernel_token;
+use crate::memory::frame::user_buffer::put_user_value;
+use crate::task::{
+    add_new_task, block_current_and_run_next, get_current_task, ThreadControlBlock, ThreadUserRes,
+};
+use alloc::sync::Arc;
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> SysResult {
+    let task = get_current_task();
+    let process = task.process();
+    let res = ThreadUserRes::alloc(&process)?;
+    let tid = res.tid;
+    let ustack_top = res.ustack_top();
+    let signal_mask = task.inner.lock().signal_mask;
+    let new_task = Arc::new(ThreadControlBlock::new(&process, res, signal_mask)?);
+    new_task.inner.lock().nice = task.inner.lock().nice;
+    let mut trap_cx = Context::app_init_context(
+        entry,
+        ustack_top,
+        kernel_token(),
+        new_task.kernel_stack.get_top(),
+        interrupt_handler as usize,
+    );
+    trap_cx.x[10] = arg;
+    *new_task.inner.lock().trap_cx() = trap_cx;
+    let mut inner = process.inner.lock();
+    if inner.threads.len() <= tid {
+        inner.threads.resize(tid + 1, None);
+    }
+    inner.threads[tid] = Some(new_task.clone());
+    add_new_task(new_task);
+    Ok(tid as isize)
+}
+
+pub fn sys_gettid() -> SysResult {
+    Ok(get_current_task().inner.lock().tid() as isize)
+}
+
+/// 等待线程结束，退出码写入exit_code_ptr，返回线程的TID
+pub fn sys_waittid(tid: usize, exit_code_ptr: *mut u8) -> SysResult {
+    let task = get_current_task();
+    let process = task.process();
+    if task.inner.lock().tid() == tid {
+        // can't wait for itself
+        return Err(EDEADLK);
+    }
+    loop {
+        let mut inner = process.inner.lock();
+        let waited = match inner.threads.get(tid) {
+            Some(Some(waited)) => waited.clone(),
+            _ => return Err(ESRCH),
+        };
+        let exit_code = waited.inner.lock().exit_code;
+        if let Some(exit_code) = exit_code {
+            if !exit_code_ptr.is_null() {
+                // 地址无效时线程保留，之后仍可回收
+                put_user_value(&mut inner.memory_set, exit_code, exit_code_ptr)?;
+            }
+            inner.threads[tid] = None;
+            drop(inner);
+            // 释放线程资源时需要借用进程控制块
+            drop(waited);
+            return Ok(tid as isize);
+        }
+        if inner.should_interrupt(&task.inner.lock()) {
+            return Err(EINTR);
+        }
+        drop(inner);
+        process.wait_queue.lock().push(task.clone());
+        block_current_and_run_next();
+        process.wait_queue.lock().remove(&task);
+    }
+}
@@ -4,10 +4,12 @@ use crate::memory::frame::{
     memory_set::KERNEL_MEMORY_SET,
     page_table::{R, W},
 };
+use crate::sync::SpinLock;
 use crate::tools::uninit_cell::UninitCell;
 use alloc::vec::Vec;
 
 /// 循环分配器
+#[derive(Clone)]
 pub struct RecycleAllocator {
     current: usize,
     recycled: Vec<usize>,
@@ -43,64 +45,74 @@ impl RecycleAllocator {
 }
 
 /// 全局PID分配器
-pub static mut PID_ALLOCATOR: UninitCell<RecycleAllocator> = UninitCell::uninit();
+pub static mut PID_ALLOCATOR: UninitCell<SpinLock<RecycleAllocator>> = UninitCell::uninit();
 
 /// PID句柄
 pub struct PidHandle(pub usize);
 
 /// 分配PID句柄
 pub fn pid_alloc() -> PidHandle {
-    unsafe { PidHandle(PID_ALLOCATOR.alloc()) }
+    unsafe { PidHandle(PID_ALLOCATOR.lock().alloc()) }
 }
 
 /// PID句柄Drop实现
 impl Drop for PidHandle {
     fn drop(&mut self) {
         unsafe {
-            PID_ALLOCATOR.dealloc(self.0);
+            PID_ALLOCATOR.lock().dealloc(self.0);
         }
     }
 }
 
+/// 全局内核栈分配器
+pub static mut KSTACK_ALLOCATOR: UninitCell<SpinLock<RecycleAllocator>> = UninitCell::uninit();
+
 /// 获取目前内核栈位置
-pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+pub fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - kstack_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
-/// app 内核栈
+/// 线程内核栈
 pub struct KernelStack {
-    pid: usize,
+    id: usize,
 }
 
-impl KernelStack {
-    /// 由 PID 分配栈空间
-    pub fn new(pid_handle: &PidHandle) -> Self {
-        let pid = pid_handle.0;
-        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(pid);
-        unsafe {
-            KERNEL_MEMORY_SET.insert_segment(
-                VirtAddr(kernel_stack_bottom).vpn()..VirtAddr(kernel_stack_top).vpn(),
-                R | W,
-                None,
-            );
-        }
-        KernelStack { pid: pid_handle.0 }
+/// 分配内核栈空间，内存不足时返回None
+pub fn kstack_alloc() -> Option<KernelStack> {
+    let id = unsafe { KSTACK_ALLOCATOR.lock().alloc() };
+    let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(id);
+    let result = unsafe {
+        KERNEL_MEMORY_SET.lock().insert_segment(
+            VirtAddr(kernel_stack_bottom).vpn()..VirtAddr(kernel_stack_top).vpn(),
+            R | W,
+            None,
+        )
+    };
+    if result.is_err() {
+        unsafe { KSTACK_ALLOCATOR.lock().dealloc(id) };
+        return None;
     }
+    Some(KernelStack { id })
+}
 
+impl KernelStack {
     /// 获取栈顶
     pub fn get_top(&self) -> usize {
-        let (_, kernel_stack_top) = kernel_stack_position(self.pid);
+        let (_, kernel_stack_top) = kernel_stack_position(self.id);
         kernel_stack_top
     }
 }
 
 impl Drop for KernelStack {
     fn drop(&mut self) {
-        let (kernel_stack_bottom, _) = kernel_stack_position(self.pid);
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.id);
         let kernel_stack_bottom_vpn = VirtAddr(kernel_stack_bottom).vpn();
         unsafe {
-            KERNEL_MEMORY_SET.remove_segment(kernel_stack_bottom_vpn);
+            KERNEL_MEMORY_SET
+                .lock()
+                .remove_segment(kernel_stack_bottom_vpn);
+            KSTACK_ALLOCATOR.lock().dealloc(self.id);
         }
     }
 }
@@ -108,7 +120,8 @@ impl Drop for KernelStack {
 /// 资源分配模块初始化
 pub fn init() {
     unsafe {
-        PID_ALLOCATOR = UninitCell::init(RecycleAllocator::new());
+        PID_ALLOCATOR = UninitCell::init(SpinLock::new(RecycleAllocator::new()));
+        KSTACK_ALLOCATOR = UninitCell::init(SpinLock::new(RecycleAllocator::new()));
     }
 }
 
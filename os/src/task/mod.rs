@@ -1,162 +1,618 @@
 mod context;
 mod id;
 pub mod schd;
+pub mod signal;
 mod switch;
 mod task;
+mod thread;
+mod wait_queue;
 
-use crate::fs::rfs::layout::InodeType;
+use crate::config::MAX_HARTS;
+use crate::drivers::handle_irqs;
+use crate::fs::stdio::poll_console;
+use crate::interrupt::handler::{disable_interrupt, handle_deferred_interrupt, restore_interrupt};
+use crate::smp::{self, hart_id};
+use crate::sync::SpinLock;
 use crate::tools::uninit_cell::UninitCell;
-use crate::{fs::rfs::find_inode, interrupt::timer};
-use alloc::rc::Rc;
-use alloc::{format, vec};
+use crate::{fs::vfs::find_inode, interrupt::timer};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 pub use context::TaskContext;
-use schd::{get_time_slice, SchdMaster};
+use core::sync::atomic::{AtomicBool, Ordering};
+use schd::SchdMaster;
+use signal::{sig_bit, DefaultAction, SignalFrame, SIGCONT, SIGKILL, SIG_DFL, SIG_IGN, UNMASKABLE};
 pub use switch::__switch;
-pub use task::{ProcessControlBlock, TaskPos, TaskStatus};
+pub use task::{CpuTimes, ProcessControlBlock};
+pub use thread::{TaskPos, TaskStatus, ThreadControlBlock, ThreadUserRes};
+pub use wait_queue::WaitQueue;
 
-/// 任务管理器
+/// 任务管理器，每个核一个
 pub struct TaskManager {
-    current_task: Rc<ProcessControlBlock>,
+    /// 正在运行的线程，空闲时为None
+    current_task: Option<Arc<ThreadControlBlock>>,
+    /// 刚被换下的线程，切换完成后才释放：切换期间仍在使用它的内核栈
+    prev_task: Option<Arc<ThreadControlBlock>>,
+    /// 本核的就绪队列
     schd: SchdMaster,
+    /// 上次计时的时刻，此后的时间属于当前进程
+    last_time: usize,
+    /// 当前任务开始运行的时刻
+    run_start: usize,
+    /// 空闲任务的上下文，没有可运行的任务时切换到此处
+    idle_cx: TaskContext,
+    /// 本核是否已启动并参与调度
+    online: bool,
 }
 
 impl TaskManager {
     /// 创建新的任务管理器
-    fn new(daemon: Rc<ProcessControlBlock>) -> Self {
+    fn new() -> Self {
         Self {
-            current_task: daemon,
+            current_task: None,
+            prev_task: None,
             schd: SchdMaster::new(),
+            last_time: timer::get_time(),
+            run_start: timer::get_time(),
+            idle_cx: TaskContext::zero_init(),
+            online: false,
         }
     }
 
-    /// 结束本任务 调度执行下一个任务
-    fn switch_to_next_task(&mut self) {
-        let current_task = self.current_task.clone();
-        let mut current_task_inner = current_task
-            .inner
-            .try_borrow_mut()
-            .expect(&format!("{}", current_task.pid.0));
-        let current_task_cx = &mut current_task_inner.task_cx as *mut TaskContext;
-        let current_task_status = current_task_inner.task_status;
-        if current_task_status != TaskStatus::Exited {
-            current_task_inner.task_status = TaskStatus::Ready;
-            drop(current_task_inner);
-            self.schd.requeue_current(current_task);
-        } else {
-            drop(current_task_inner);
-            drop(current_task);
-        }
-        let next_task = self.schd.get_next().unwrap();
-        let mut next_task_inner = next_task.inner.borrow_mut();
-        let next_task_cx = &mut next_task_inner.task_cx as *mut TaskContext;
-        timer::set_next_timeout(get_time_slice(next_task_inner.task_pos));
-        drop(next_task_inner);
-        self.current_task = next_task;
-        unsafe {
-            if current_task_status != TaskStatus::Exited {
-                __switch(current_task_cx, next_task_cx);
+    /// 将上次计时以来的时间计入当前进程的用户态（user为true）或内核态时间
+    fn account_time(&mut self, user: bool) {
+        let now = timer::get_time();
+        let elapsed = now - self.last_time;
+        self.last_time = now;
+        // 空闲或进程已被回收时无需计时
+        let process = self
+            .current_task
+            .as_ref()
+            .and_then(|task| task.process.upgrade());
+        if let Some(process) = process {
+            let times = &mut process.inner.lock().times;
+            if user {
+                times.user += elapsed;
             } else {
-                let mut _unused = TaskContext::zero_init();
-                __switch(&mut _unused, next_task_cx);
+                times.system += elapsed;
             }
         }
     }
 
-    /// 获取正在运行的任务
-    pub fn get_current_process(&self) -> Rc<ProcessControlBlock> {
-        self.current_task.clone()
+    /// 获取正在运行的线程
+    pub fn get_current_task(&self) -> Arc<ThreadControlBlock> {
+        self.current_task.clone().unwrap()
+    }
+}
+
+/// 各核的任务管理器，以核号为下标
+/// 派发与窃取任务时其他核也会访问，锁不能跨越任务切换持有
+pub static mut TASK_MANAGERS: UninitCell<Vec<SpinLock<TaskManager>>> = UninitCell::uninit();
+
+/// 编译期检查在各核间共享的对象确实可以跨核访问，防止通过static mut绕过检查
+#[allow(dead_code)]
+fn assert_shareable() {
+    fn check<T: Send + Sync>() {}
+    check::<SpinLock<TaskManager>>();
+    check::<ProcessControlBlock>();
+    check::<ThreadControlBlock>();
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_RESCHED: AtomicBool = AtomicBool::new(false);
+
+/// 各核在内核态收到时钟中断后标记需要调度，在可以安全切换的位置处理
+static NEED_RESCHED: [AtomicBool; MAX_HARTS] = [NO_RESCHED; MAX_HARTS];
+
+/// 当前核的任务管理器
+fn local_manager() -> &'static SpinLock<TaskManager> {
+    unsafe { &TASK_MANAGERS[hart_id()] }
+}
+
+/// 结束本任务 调度执行下一个任务
+fn switch_to_next_task() {
+    // 切换期间关闭中断，恢复运行后回到本任务原来的中断状态
+    let interrupt_enabled = disable_interrupt();
+    NEED_RESCHED[hart_id()].store(false, Ordering::Relaxed);
+    let mut manager = local_manager().lock();
+    manager.account_time(false);
+    let current_task = manager.current_task.clone().unwrap();
+    let elapsed = timer::get_time() - manager.run_start;
+    manager.schd.tick(&current_task, elapsed);
+    let mut current_task_inner = current_task.inner.lock();
+    let current_task_cx = &mut current_task_inner.task_cx as *mut TaskContext;
+    let current_task_status = current_task_inner.task_status;
+    drop(current_task_inner);
+    match current_task_status {
+        TaskStatus::Ready => manager.schd.requeue_current(current_task),
+        // 阻塞的任务由等待队列持有，暂停的任务由所属进程持有，被唤醒时重新入队
+        TaskStatus::Blocked | TaskStatus::Stopped => {}
+        TaskStatus::Exited => drop(current_task),
+    }
+    drop(manager);
+    let mut _unused = TaskContext::zero_init();
+    let current_task_cx = if current_task_status != TaskStatus::Exited {
+        current_task_cx
+    } else {
+        &mut _unused as *mut TaskContext
+    };
+    match fetch_next() {
+        Some(next_task) => run_task(current_task_cx, next_task),
+        // 没有可运行的任务，回到空闲任务等待中断
+        None => {
+            let mut manager = local_manager().lock();
+            manager.prev_task = manager.current_task.take();
+            let idle_cx = &manager.idle_cx as *const TaskContext;
+            drop(manager);
+            unsafe { __switch(current_task_cx, idle_cx) }
+            reap_prev_task();
+        }
+    }
+    restore_interrupt(interrupt_enabled);
+}
+
+/// 保存当前上下文到current_task_cx，切换到next_task运行
+fn run_task(current_task_cx: *mut TaskContext, next_task: Arc<ThreadControlBlock>) {
+    let mut manager = local_manager().lock();
+    // 空闲时间不计入任何进程
+    manager.last_time = timer::get_time();
+    manager.run_start = manager.last_time;
+    timer::set_next_timeout(manager.schd.time_slice(&next_task));
+    let next_task_inner = next_task.inner.lock();
+    let next_task_cx = &next_task_inner.task_cx as *const TaskContext;
+    drop(next_task_inner);
+    // 换下的任务在切换完成后才释放：其资源回收时会访问所属进程，且此时仍在其内核栈上
+    manager.prev_task = manager.current_task.replace(next_task);
+    drop(manager);
+    unsafe {
+        __switch(current_task_cx, next_task_cx);
+    }
+    reap_prev_task();
+}
+
+/// 释放本核上一个被换下的线程，切换完成后在新的上下文中调用
+/// 新线程从interrupt_return开始运行，由其负责调用
+pub fn reap_prev_task() {
+    let prev_task = local_manager().lock().prev_task.take();
+    drop(prev_task);
+}
+
+/// 选择任务最少的在线核（正在运行的任务也计入，相同时优先当前核）
+fn least_loaded_hart() -> usize {
+    let managers = unsafe { &TASK_MANAGERS };
+    let current = hart_id();
+    (0..managers.len())
+        .filter_map(|id| {
+            let manager = managers[id].lock();
+            let load = manager.schd.load() + manager.current_task.is_some() as usize;
+            (manager.online || id == current).then(|| (id, (load, id != current)))
+        })
+        .min_by_key(|&(_, key)| key)
+        .unwrap()
+        .0
+}
+
+/// 将就绪的任务放入任务最少的核的队列，该核空闲时将其唤醒
+fn dispatch(task: Arc<ThreadControlBlock>, is_new: bool) {
+    let id = least_loaded_hart();
+    let mut manager = unsafe { TASK_MANAGERS[id].lock() };
+    if is_new {
+        manager.schd.add_new_task(task);
+    } else {
+        manager.schd.wakeup(task);
+    }
+    let idle = manager.current_task.is_none();
+    drop(manager);
+    if id != hart_id() && idle {
+        smp::wake_hart(id);
     }
+}
 
-    /// 获取全部任务迭代器
-    pub fn tasks(&self) -> impl Iterator<Item = &Rc<ProcessControlBlock>> {
-        self.schd.tasks()
+/// 唤醒到期的睡眠任务后取出下一个可运行的任务
+/// 本核队列为空时从任务最多的核取走一个，以平衡各核负载
+fn fetch_next() -> Option<Arc<ThreadControlBlock>> {
+    wakeup_expired();
+    let managers = unsafe { &TASK_MANAGERS };
+    let current = hart_id();
+    loop {
+        let id = if managers[current].lock().schd.load() > 0 {
+            current
+        } else {
+            (0..managers.len())
+                .map(|id| (id, managers[id].lock().schd.load()))
+                .filter(|&(_, load)| load > 0)
+                .max_by_key(|&(_, load)| load)?
+                .0
+        };
+        let task = managers[id].lock().schd.get_next()?;
+        // 所属进程退出时留在队列中的线程直接丢弃
+        if task.inner.lock().task_status != TaskStatus::Exited {
+            return Some(task);
+        }
     }
 }
 
-/// 全局任务管理器
-pub static mut TASK_MANAGER: UninitCell<TaskManager> = UninitCell::uninit();
+/// 唤醒睡眠时间已到的任务
+fn wakeup_expired() {
+    for task in timer::take_expired_tasks() {
+        wakeup_task(task);
+    }
+}
 
 /// 守护进程
-pub static mut DAEMON: UninitCell<Rc<ProcessControlBlock>> = UninitCell::uninit();
+pub static mut DAEMON: UninitCell<Arc<ProcessControlBlock>> = UninitCell::uninit();
 
-/// 向调度队列加入新的进程
-pub fn add_new_task(task: Rc<ProcessControlBlock>) {
-    unsafe {
-        TASK_MANAGER.schd.add_new_task(task);
+/// 将上次计时以来的时间计入当前进程，user表示这段时间处于用户态
+pub fn account_current_time(user: bool) {
+    local_manager().lock().account_time(user);
+}
+
+/// 向调度队列加入新的线程
+pub fn add_new_task(task: Arc<ThreadControlBlock>) {
+    dispatch(task, true);
+}
+
+/// 退出当前线程并运行下一个
+/// 主线程或进程中最后一个线程退出时整个进程随之退出
+pub fn exit_current_thread_and_run_next(exit_code: i32) {
+    let task = get_current_task();
+    let process = task.process();
+    let mut task_inner = task.inner.lock();
+    if task_inner.tid() == 0 || !process.inner.lock().has_other_threads(&task) {
+        drop(task_inner);
+        drop(process);
+        drop(task);
+        exit_current_and_run_next(exit_code);
+        return;
     }
+    task_inner.task_status = TaskStatus::Exited;
+    task_inner.exit_code = Some(exit_code);
+    drop(task_inner);
+    drop(task);
+    process.wait_queue.lock().wake_all();
+    drop(process);
+    suspend_current_and_run_next();
 }
 
-/// 退出目前进程并运行下一个
+/// 退出目前进程（包括其全部线程）并运行下一个
 pub fn exit_current_and_run_next(exit_code: i32) {
-    let proc = get_current_process();
-    let mut inner = proc.inner.borrow_mut();
     if exit_code != 0 {
         println!(
             "[kernel] Process {} exit with code {}",
-            proc.pid.0, exit_code
+            get_current_process().pid.0,
+            exit_code
         );
     }
-    inner.task_status = TaskStatus::Exited;
+    do_exit_current(exit_code, None);
+}
+
+/// 因信号终止目前进程并运行下一个
+pub fn kill_current_and_run_next(signum: usize) {
+    do_exit_current(0, Some(signum));
+}
+
+/// 通知进程中除current外的全部线程退出
+/// 阻塞在内核中的线程被唤醒，离开等待后在返回用户态前退出，期间仍可释放所持有的锁
+fn kill_other_threads(proc: &Arc<ProcessControlBlock>, current: &Arc<ThreadControlBlock>) {
+    let inner = proc.inner.lock();
+    let others = inner
+        .threads
+        .iter()
+        .flatten()
+        .filter(|thread| !Arc::ptr_eq(thread, current))
+        .cloned()
+        .collect::<Vec<_>>();
+    let token = inner.token();
+    drop(inner);
+    for thread in others.iter() {
+        thread.inner.lock().killed = true;
+        timer::remove_sleeping_task(thread);
+    }
+    // 其余核上正在运行的线程先离开用户态，进入内核时退出
+    smp::tlb_shootdown(token);
+    for thread in others {
+        wakeup_task(thread);
+    }
+}
+
+/// 当前线程已被通知退出时结束该线程，并唤醒等待其离开内核的exec调用者与父进程
+pub fn exit_if_killed() {
+    let task = get_current_task();
+    let mut task_inner = task.inner.lock();
+    if !task_inner.killed {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Exited;
+    drop(task_inner);
+    let process = task.process();
+    drop(task);
+    process.wait_queue.lock().wake_all();
+    let parent = process.inner.lock().parent.upgrade();
+    if let Some(parent) = parent {
+        parent.wait_queue.lock().wake_all();
+    }
+    drop(process);
+    suspend_current_and_run_next();
+}
+
+/// 结束当前进程：回收资源，将子进程交给守护进程，并唤醒等待的父进程
+/// 其余线程离开内核之后，父进程才能回收本进程
+fn do_exit_current(exit_code: i32, term_signal: Option<usize>) {
+    let task = get_current_task();
+    let proc = task.process();
+    task.inner.lock().task_status = TaskStatus::Exited;
+    kill_other_threads(&proc, &task);
+    drop(task);
+    let mut inner = proc.inner.lock();
+    inner.is_zombie = true;
     inner.fd_table.clear();
     inner.exit_code = exit_code;
+    inner.term_signal = term_signal;
     unsafe {
-        let mut daemon_inner = DAEMON.inner.borrow_mut();
+        let mut daemon_inner = DAEMON.inner.lock();
+        let mut has_zombie = false;
         for child in inner.children.iter() {
-            child.inner.borrow_mut().parent = Rc::downgrade(&DAEMON);
+            let mut child_inner = child.inner.lock();
+            child_inner.parent = Arc::downgrade(&DAEMON);
+            has_zombie |= child_inner.is_zombie;
             daemon_inner.children.push(child.clone());
         }
+        drop(daemon_inner);
+        if has_zombie {
+            DAEMON.wait_queue.lock().wake_all();
+        }
     }
+    let parent = inner.parent.upgrade();
     drop(inner);
+    if let Some(parent) = parent {
+        parent.wait_queue.lock().wake_all();
+        send_signal(&parent, signal::SIGCHLD);
+    }
     drop(proc);
     suspend_current_and_run_next();
 }
 
+/// 阻塞当前线程并运行下一个，需事先将其加入某个等待队列
+pub fn block_current_and_run_next() {
+    get_current_task().inner.lock().task_status = TaskStatus::Blocked;
+    suspend_current_and_run_next();
+}
+
+/// 唤醒被阻塞的线程，使其重新参与调度，线程未在等待时返回false
+pub fn wakeup_task(task: Arc<ThreadControlBlock>) -> bool {
+    let mut inner = task.inner.lock();
+    if !matches!(inner.task_status, TaskStatus::Blocked | TaskStatus::Stopped) {
+        return false;
+    }
+    inner.task_status = TaskStatus::Ready;
+    drop(inner);
+    dispatch(task, false);
+    true
+}
+
+/// 向进程发送信号，并唤醒可以接收该信号的阻塞中的线程（SIGCONT唤醒暂停的线程）
+pub fn send_signal(proc: &Arc<ProcessControlBlock>, signum: usize) {
+    let mut inner = proc.inner.lock();
+    inner.add_signal(signum);
+    let threads = inner
+        .threads
+        .iter()
+        .flatten()
+        .filter(|thread| {
+            let thread_inner = thread.inner.lock();
+            (signum == SIGCONT && thread_inner.task_status == TaskStatus::Stopped)
+                || inner.next_signal(&thread_inner).is_some()
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    drop(inner);
+    for thread in threads {
+        wakeup_task(thread);
+    }
+}
+
+/// 向进程组中的全部进程发送信号，进程组不存在时返回false
+pub fn send_group_signal(pgid: usize, signum: usize) -> bool {
+    let group = find_process_group(pgid);
+    for proc in group.iter() {
+        send_signal(proc, signum);
+    }
+    !group.is_empty()
+}
+
+/// 当前线程是否有可以递送的信号或已被通知退出，阻塞中的系统调用据此提前返回
+pub fn current_signal_pending() -> bool {
+    let task = get_current_task();
+    let process = task.process();
+    let inner = process.inner.lock();
+    let task_inner = task.inner.lock();
+    inner.should_interrupt(&task_inner)
+}
+
+/// 当前线程是否已被通知退出，不响应信号的内核等待据此提前返回
+pub fn current_killed() -> bool {
+    get_current_task().inner.lock().killed
+}
+
 /// 挂起当前进程并运行下一个
 pub fn suspend_current_and_run_next() {
-    unsafe {
-        TASK_MANAGER.switch_to_next_task();
-    }
+    switch_to_next_task();
 }
 
 /// the callback function used in the supervisor time interrupt
 /// to implement the basic task scheduling
 pub fn schedule_callback() {
-    unsafe {
-        TASK_MANAGER.switch_to_next_task();
+    switch_to_next_task();
+}
+
+/// 获取当前正在执行的线程
+pub fn get_current_task() -> Arc<ThreadControlBlock> {
+    local_manager().lock().get_current_task()
+}
+
+/// 获取当前正在执行的线程，空闲时返回None
+pub fn try_get_current_task() -> Option<Arc<ThreadControlBlock>> {
+    unsafe { TASK_MANAGERS.is_init() }
+        .then(|| local_manager().lock().current_task.clone())
+        .flatten()
+}
+
+/// 标记当前核需要重新调度（内核态时钟中断时调用）
+pub fn request_resched() {
+    NEED_RESCHED[hart_id()].store(true, Ordering::Relaxed);
+}
+
+/// 内核抢占点：时间片在内核态用完时让出处理器，调用时不能持有任务或进程数据的借用
+pub fn cond_resched() {
+    if NEED_RESCHED[hart_id()].load(Ordering::Relaxed) {
+        suspend_current_and_run_next();
     }
 }
 
 /// 获取当前正在执行的进程
-pub fn get_current_process() -> Rc<ProcessControlBlock> {
-    unsafe { TASK_MANAGER.get_current_process() }
+pub fn get_current_process() -> Arc<ProcessControlBlock> {
+    get_current_task().process()
+}
+
+/// 获取全部进程（从守护进程开始遍历进程树，包括阻塞中的进程）
+pub fn all_processes() -> Vec<Arc<ProcessControlBlock>> {
+    let mut procs = vec![];
+    let mut stack = vec![unsafe { DAEMON.clone() }];
+    while let Some(proc) = stack.pop() {
+        stack.extend(proc.inner.lock().children.iter().cloned());
+        procs.push(proc);
+    }
+    procs
+}
+
+/// 根据PID查找进程
+pub fn find_process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    all_processes().into_iter().find(|proc| proc.pid.0 == pid)
+}
+
+/// 获取进程组中尚未退出的进程
+pub fn find_process_group(pgid: usize) -> Vec<Arc<ProcessControlBlock>> {
+    all_processes()
+        .into_iter()
+        .filter(|proc| {
+            let inner = proc.inner.lock();
+            inner.pgid == pgid && !inner.is_zombie
+        })
+        .collect()
+}
+
+/// 处理当前线程的待处理信号，在返回用户态前调用
+/// 需要用户处理的信号通过改写中断上下文跳转到处理函数，已被通知退出的线程在此退出
+pub fn handle_signals() {
+    loop {
+        exit_if_killed();
+        let task = get_current_task();
+        let mut task_inner = task.inner.lock();
+        let proc = task.process();
+        let mut inner = proc.inner.lock();
+        let signum = if inner.stopped {
+            // 暂停期间离开调度队列，直到收到SIGCONT或SIGKILL，其余信号在继续运行后处理
+            if inner.signals & sig_bit(SIGKILL) == 0 {
+                task_inner.task_status = TaskStatus::Stopped;
+                drop(inner);
+                drop(proc);
+                drop(task_inner);
+                drop(task);
+                suspend_current_and_run_next();
+                continue;
+            }
+            SIGKILL
+        } else {
+            match inner.next_signal(&task_inner) {
+                Some(signum) => signum,
+                None => return,
+            }
+        };
+        inner.signals &= !sig_bit(signum);
+        let action = inner.signal_actions.table[signum];
+        if action.handler == SIG_DFL || sig_bit(signum) & UNMASKABLE != 0 {
+            match DefaultAction::of(signum) {
+                DefaultAction::Terminate => {
+                    drop(inner);
+                    drop(proc);
+                    drop(task_inner);
+                    drop(task);
+                    kill_current_and_run_next(signum);
+                    return;
+                }
+                DefaultAction::Stop => {
+                    inner.stopped = true;
+                    inner.stop_report = Some(signum);
+                    if let Some(parent) = inner.parent.upgrade() {
+                        parent.wait_queue.lock().wake_all();
+                        send_signal(&parent, signal::SIGCHLD);
+                    }
+                }
+                DefaultAction::Ignore => {}
+            }
+        } else if action.handler != SIG_IGN {
+            let trap_cx = task_inner.trap_cx();
+            task_inner.signal_frame = Some(SignalFrame {
+                trap_cx: *trap_cx,
+                mask: task_inner.signal_mask,
+            });
+            task_inner.signal_mask |= (action.mask | sig_bit(signum)) & !UNMASKABLE;
+            trap_cx.sepc = action.handler;
+            trap_cx.x[1] = action.restorer;
+            trap_cx.x[10] = signum;
+            return;
+        }
+    }
 }
 
 /// 模块初始化
 pub fn init() {
     unsafe {
         id::init();
-        let root_inode = find_inode("/").expect("[kernel] No root inode??");
-        let proc_inode = root_inode.create("proc", InodeType::Directory).unwrap();
-        proc_inode.set_default_dirent(root_inode.get_inode_id());
         let app_inode = find_inode("/bin/daemon").expect("[kernel] daemon not found!");
-        let size = app_inode.get_file_size() as usize;
-        let mut app_data = vec![0u8; size];
+        let mut app_data = vec![0u8; app_inode.size()];
         app_inode.read_at(0, &mut app_data);
-        DAEMON = UninitCell::init(Rc::new(ProcessControlBlock::new(&app_data)));
-        TASK_MANAGER = UninitCell::init(TaskManager::new(DAEMON.clone()));
+        DAEMON = UninitCell::init(ProcessControlBlock::new(&app_data));
+        let daemon_thread = DAEMON.inner.lock().threads[0].clone().unwrap();
+        TASK_MANAGERS = UninitCell::init(
+            (0..MAX_HARTS)
+                .map(|_| SpinLock::new(TaskManager::new()))
+                .collect(),
+        );
+        add_new_task(daemon_thread);
+        println!("[kernel] scheduler: {}", local_manager().lock().schd.name());
         println!("mod task initialized!");
     }
 }
 
-/// 运行进程调度过程
+/// 运行进程调度过程，启动栈此后作为本核空闲任务的栈
 pub fn run() {
+    local_manager().lock().online = true;
+    loop {
+        // 线程可能在内核态收到外部中断后阻塞，切换到了空闲任务
+        handle_deferred_interrupt();
+        match fetch_next() {
+            Some(task) => {
+                let mut manager = local_manager().lock();
+                let idle_cx = &mut manager.idle_cx as *mut TaskContext;
+                drop(manager);
+                run_task(idle_cx, task);
+            }
+            None => wait_for_interrupt(),
+        }
+    }
+}
+
+/// 没有可运行的任务时释放内核锁并执行wfi，直到时钟中断、外部中断或其他核唤醒本核
+/// 内核态下sstatus.SIE保持关闭，sie中已使能的中断挂起时wfi即返回而不会进入中断处理
+fn wait_for_interrupt() {
+    timer::set_next_timeout(schd::get_default_time_slice());
+    smp::unlock_kernel();
     unsafe {
-        let current_task = TASK_MANAGER.current_task.clone();
-        let mut current_task_inner = current_task.inner.borrow_mut();
-        let current_task_cx = &mut current_task_inner.task_cx as *mut TaskContext;
-        drop(current_task_inner);
-        let mut _unused = TaskContext::zero_init();
-        __switch(&mut _unused as *mut TaskContext, current_task_cx);
-        unreachable!();
+        core::arch::asm!("wfi");
     }
+    smp::lock_kernel();
+    smp::clear_ipi();
+    poll_console();
+    handle_irqs();
 }
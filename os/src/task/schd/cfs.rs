@@ -0,0 +1,78 @@
+//! 完全公平调度
+//!
+//! 按权重折算实际运行时间得到虚拟运行时间，总是选择虚拟运行时间最小的任务
+
+use super::{nice_to_weight, Scheduler, NICE_0_WEIGHT};
+use crate::config::{CLOCK_FREQ, SCHED_LATENCY_MS, SCHED_MIN_GRANULARITY_MS};
+use crate::task::thread::ThreadControlBlock;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+/// 按(虚拟运行时间, 入队序号)排序的就绪队列，同时记录任务入队时的权重
+pub struct Cfs {
+    queue: BTreeMap<(usize, usize), (Arc<ThreadControlBlock>, usize)>,
+    /// 入队序号，虚拟运行时间相同时先入队的先运行
+    seq: usize,
+    /// 单调增长的最小虚拟运行时间，新任务与被唤醒的任务以此为基准
+    min_vruntime: usize,
+    /// 队列中任务入队时的权重之和，排队期间修改优先级不影响出队时扣除的值
+    load: usize,
+}
+
+impl Cfs {
+    pub fn new() -> Self {
+        Cfs {
+            queue: BTreeMap::new(),
+            seq: 0,
+            min_vruntime: 0,
+            load: 0,
+        }
+    }
+
+    fn insert(&mut self, task: Arc<ThreadControlBlock>) {
+        let inner = task.inner.lock();
+        let key = (inner.vruntime, self.seq + 1);
+        let weight = nice_to_weight(inner.nice);
+        drop(inner);
+        self.load += weight;
+        self.seq += 1;
+        self.queue.insert(key, (task, weight));
+    }
+}
+
+impl Scheduler for Cfs {
+    fn name(&self) -> &'static str {
+        "cfs"
+    }
+    fn enqueue(&mut self, task: Arc<ThreadControlBlock>) {
+        task.inner.lock().vruntime = self.min_vruntime;
+        self.insert(task);
+    }
+    fn requeue(&mut self, task: Arc<ThreadControlBlock>) {
+        self.insert(task);
+    }
+    /// 睡眠较久的任务至多领先半个调度周期，避免长期独占处理器
+    fn wakeup(&mut self, task: Arc<ThreadControlBlock>) {
+        let credit = SCHED_LATENCY_MS * CLOCK_FREQ / 1000 / 2;
+        let mut inner = task.inner.lock();
+        inner.vruntime = inner.vruntime.max(self.min_vruntime.saturating_sub(credit));
+        drop(inner);
+        self.insert(task);
+    }
+    fn pick_next(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        let key = *self.queue.keys().next()?;
+        self.min_vruntime = self.min_vruntime.max(key.0);
+        let (task, weight) = self.queue.remove(&key)?;
+        self.load -= weight;
+        Some(task)
+    }
+    fn tick(&mut self, task: &Arc<ThreadControlBlock>, elapsed: usize) {
+        let mut inner = task.inner.lock();
+        inner.vruntime += elapsed * NICE_0_WEIGHT / nice_to_weight(inner.nice);
+    }
+    /// 调度周期按权重分给当前任务与队列中的任务，但不短于最小粒度
+    fn time_slice(&self, task: &Arc<ThreadControlBlock>) -> usize {
+        let weight = nice_to_weight(task.inner.lock().nice);
+        (SCHED_LATENCY_MS * weight / (self.load + weight)).max(SCHED_MIN_GRANULARITY_MS)
+    }
+}
@@ -0,0 +1,129 @@
+//! 多级反馈队列调度
+//!
+//! 任务用完本级的时间配额后降级，提前让出处理器的任务保持原有级别，并定期将所有任务提升到最高级
+
+use super::Scheduler;
+use crate::config::{
+    MLFQ_BOOST_INTERVAL_MS, TASK_QUEUE_FCFS1_ALLOTMENT_MS, TASK_QUEUE_FCFS1_SLICE_MS,
+    TASK_QUEUE_FCFS2_ALLOTMENT_MS, TASK_QUEUE_FCFS2_SLICE_MS, TASK_QUEUE_RR_SLICE_MS,
+};
+use crate::interrupt::timer::{get_time, ticks_to_ms};
+use crate::task::thread::{TaskPos, ThreadControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 各级的时间配额（ms），最低级没有限制
+fn allotment(pos: TaskPos) -> usize {
+    match pos {
+        TaskPos::Fcfs1 => TASK_QUEUE_FCFS1_ALLOTMENT_MS,
+        TaskPos::Fcfs2 => TASK_QUEUE_FCFS2_ALLOTMENT_MS,
+        TaskPos::Rr => usize::MAX,
+    }
+}
+
+/// 多级反馈队列
+pub struct MultilevelFeedbackQueue {
+    fcfs1_queue: VecDeque<Arc<ThreadControlBlock>>,
+    fcfs2_queue: VecDeque<Arc<ThreadControlBlock>>,
+    rr_queue: VecDeque<Arc<ThreadControlBlock>>,
+    /// 上次提升优先级的时刻（时钟周期），各核的时钟相同，迁移的任务也可比较
+    last_boost: usize,
+}
+
+impl MultilevelFeedbackQueue {
+    /// 创建多级反馈队列
+    pub fn new() -> Self {
+        MultilevelFeedbackQueue {
+            fcfs1_queue: VecDeque::new(),
+            fcfs2_queue: VecDeque::new(),
+            rr_queue: VecDeque::new(),
+            last_boost: get_time(),
+        }
+    }
+
+    /// 任务进入其级别对应的队列
+    fn push(&mut self, task: Arc<ThreadControlBlock>) {
+        let task_pos = task.inner.lock().task_pos;
+        match task_pos {
+            TaskPos::Fcfs1 => self.fcfs1_queue.push_back(task),
+            TaskPos::Fcfs2 => self.fcfs2_queue.push_back(task),
+            TaskPos::Rr => self.rr_queue.push_back(task),
+        }
+    }
+
+    /// 将排队中的任务全部提升到最高级并重新计算配额，防止低级队列中的任务饿死
+    pub fn boost(&mut self) {
+        // 保证每次提升的时刻不同
+        self.last_boost = get_time().max(self.last_boost + 1);
+        let lower = self.fcfs2_queue.drain(..).chain(self.rr_queue.drain(..));
+        self.fcfs1_queue.extend(lower);
+        for task in self.fcfs1_queue.iter() {
+            let mut inner = task.inner.lock();
+            inner.task_pos = TaskPos::Fcfs1;
+            inner.level_used = 0;
+            inner.boost_epoch = self.last_boost;
+        }
+    }
+
+    /// 运行或阻塞中的任务不在队列里，错过的提升在其重新入队时补上
+    fn catch_up_boost(&self, task: &Arc<ThreadControlBlock>) {
+        let mut inner = task.inner.lock();
+        if inner.boost_epoch < self.last_boost {
+            inner.task_pos = TaskPos::Fcfs1;
+            inner.level_used = 0;
+            inner.boost_epoch = self.last_boost;
+        }
+    }
+}
+
+impl Scheduler for MultilevelFeedbackQueue {
+    fn name(&self) -> &'static str {
+        "mlfq"
+    }
+    /// 新任务进入最高级队列
+    fn enqueue(&mut self, task: Arc<ThreadControlBlock>) {
+        task.inner.lock().boost_epoch = self.last_boost;
+        self.fcfs1_queue.push_back(task)
+    }
+    /// 用完本级配额的任务降一级后再次入队，否则保持原有级别
+    fn requeue(&mut self, task: Arc<ThreadControlBlock>) {
+        self.catch_up_boost(&task);
+        let mut inner = task.inner.lock();
+        if ticks_to_ms(inner.level_used) >= allotment(inner.task_pos) {
+            inner.task_pos = match inner.task_pos {
+                TaskPos::Fcfs1 => TaskPos::Fcfs2,
+                TaskPos::Fcfs2 | TaskPos::Rr => TaskPos::Rr,
+            };
+            inner.level_used = 0;
+        }
+        drop(inner);
+        self.push(task);
+    }
+    /// 被唤醒的任务回到其所在的队列，不降级；阻塞期间发生过提升时回到最高级
+    fn wakeup(&mut self, task: Arc<ThreadControlBlock>) {
+        self.catch_up_boost(&task);
+        self.push(task);
+    }
+    /// 依次从高到低取各级队列的队首
+    fn pick_next(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        if ticks_to_ms(get_time().saturating_sub(self.last_boost)) >= MLFQ_BOOST_INTERVAL_MS {
+            self.boost();
+        }
+        self.fcfs1_queue
+            .pop_front()
+            .or_else(|| self.fcfs2_queue.pop_front())
+            .or_else(|| self.rr_queue.pop_front())
+    }
+    /// 实际使用的CPU时间计入本级配额
+    fn tick(&mut self, task: &Arc<ThreadControlBlock>, elapsed: usize) {
+        task.inner.lock().level_used += elapsed;
+    }
+    /// 各级队列的时间片依次增长
+    fn time_slice(&self, task: &Arc<ThreadControlBlock>) -> usize {
+        match task.inner.lock().task_pos {
+            TaskPos::Fcfs1 => TASK_QUEUE_FCFS1_SLICE_MS,
+            TaskPos::Fcfs2 => TASK_QUEUE_FCFS2_SLICE_MS,
+            TaskPos::Rr => TASK_QUEUE_RR_SLICE_MS,
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! 调度子模块
+//!
+//! 调度策略实现Scheduler trait，编译时通过cargo feature选择，默认为多级反馈队列
+mod cfs;
+mod mlfq;
+mod rr;
+mod stride;
+
+use super::thread::*;
+use crate::config::TASK_QUEUE_FCFS1_SLICE_MS;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+pub use cfs::Cfs;
+pub use mlfq::MultilevelFeedbackQueue;
+pub use rr::RoundRobin;
+pub use stride::Stride;
+
+/// 调度策略
+pub trait Scheduler: Send {
+    /// 策略名
+    fn name(&self) -> &'static str;
+    /// 新任务入队
+    fn enqueue(&mut self, task: Arc<ThreadControlBlock>);
+    /// 用完时间片或主动让出的任务再次入队
+    fn requeue(&mut self, task: Arc<ThreadControlBlock>);
+    /// 被唤醒的任务重新入队
+    fn wakeup(&mut self, task: Arc<ThreadControlBlock>);
+    /// 按调度算法取出下一个任务
+    fn pick_next(&mut self) -> Option<Arc<ThreadControlBlock>>;
+    /// 任务被换下前调用，elapsed为其本次运行的时钟周期数
+    fn tick(&mut self, task: &Arc<ThreadControlBlock>, elapsed: usize);
+    /// 任务本次运行的时间片（ms）
+    fn time_slice(&self, task: &Arc<ThreadControlBlock>) -> usize;
+}
+
+/// nice为0时的权重
+pub const NICE_0_WEIGHT: usize = 1024;
+
+/// nice值（-20~19）对应的权重，相邻nice值的权重约相差1.25倍
+pub fn nice_to_weight(nice: isize) -> usize {
+    const WEIGHTS: [usize; 40] = [
+        88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100,
+        4904, 3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172,
+        137, 110, 87, 70, 56, 45, 36, 29, 23, 18, 15,
+    ];
+    WEIGHTS[(nice.clamp(-20, 19) + 20) as usize]
+}
+
+/// 根据cargo feature选择调度策略
+fn default_scheduler() -> Box<dyn Scheduler> {
+    if cfg!(feature = "sched-cfs") {
+        Box::new(Cfs::new())
+    } else if cfg!(feature = "sched-stride") {
+        Box::new(Stride::new())
+    } else if cfg!(feature = "sched-rr") {
+        Box::new(RoundRobin::new())
+    } else {
+        Box::new(MultilevelFeedbackQueue::new())
+    }
+}
+
+/// 调度器
+pub struct SchdMaster {
+    scheduler: Box<dyn Scheduler>,
+    /// 队列中的任务数
+    load: usize,
+}
+
+impl SchdMaster {
+    /// 创建使用默认调度策略的调度器实例
+    pub fn new() -> Self {
+        SchdMaster {
+            scheduler: default_scheduler(),
+            load: 0,
+        }
+    }
+
+    /// 调度策略名
+    pub fn name(&self) -> &'static str {
+        self.scheduler.name()
+    }
+
+    /// 当前任务再次入队
+    pub fn requeue_current(&mut self, current_task_cb: Arc<ThreadControlBlock>) {
+        self.load += 1;
+        self.scheduler.requeue(current_task_cb);
+    }
+
+    /// 按调度算法取出下一个任务
+    pub fn get_next(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        let task = self.scheduler.pick_next()?;
+        self.load -= 1;
+        Some(task)
+    }
+
+    /// 新任务入队
+    pub fn add_new_task(&mut self, tcb: Arc<ThreadControlBlock>) {
+        self.load += 1;
+        self.scheduler.enqueue(tcb);
+    }
+
+    /// 被唤醒的任务重新入队
+    pub fn wakeup(&mut self, tcb: Arc<ThreadControlBlock>) {
+        self.load += 1;
+        self.scheduler.wakeup(tcb);
+    }
+
+    /// 队列中的任务数
+    pub fn load(&self) -> usize {
+        self.load
+    }
+
+    /// 记录任务本次运行的时间
+    pub fn tick(&mut self, tcb: &Arc<ThreadControlBlock>, elapsed: usize) {
+        self.scheduler.tick(tcb, elapsed);
+    }
+
+    /// 获取任务本次运行的时间片
+    pub fn time_slice(&self, tcb: &Arc<ThreadControlBlock>) -> usize {
+        self.scheduler.time_slice(tcb)
+    }
+}
+
+/// 获取默认时间片
+#[inline(always)]
+pub fn get_default_time_slice() -> usize {
+    TASK_QUEUE_FCFS1_SLICE_MS
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{CLOCK_FREQ, TASK_QUEUE_FCFS2_ALLOTMENT_MS};
+    use crate::fs::rfs::find_inode;
+    use crate::task::ProcessControlBlock;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// 创建新进程并返回其主线程（进程保存在procs中以保持存活）
+    fn new_task(
+        app_data: &[u8],
+        procs: &mut Vec<Arc<ProcessControlBlock>>,
+    ) -> Arc<ThreadControlBlock> {
+        let process = ProcessControlBlock::new(app_data);
+        let task = process.inner.lock().threads[0].clone().unwrap();
+        procs.push(process);
+        task
+    }
+
+    test!(test_mlfq, {
+        let app_inode = find_inode("/bin/daemon").expect("[kernel] daemon not found!");
+        let size = app_inode.get_file_size() as usize;
+        let mut app_data = vec![0u8; size];
+        app_inode.read_at(0, &mut app_data);
+        let mut procs = vec![];
+
+        let pcb = new_task(&app_data, &mut procs);
+
+        let mut mlfq = MultilevelFeedbackQueue::new();
+        // 用完本级的时间配额
+        let use_up = |mlfq: &mut MultilevelFeedbackQueue, task: &Arc<ThreadControlBlock>| {
+            mlfq.tick(task, TASK_QUEUE_FCFS2_ALLOTMENT_MS * CLOCK_FREQ / 1000)
+        };
+        mlfq.enqueue(pcb);
+        let pcb = mlfq.pick_next();
+        test_assert!(pcb.is_some());
+        let pcb = pcb.unwrap();
+        test_assert!(pcb.as_ref().inner.lock().task_pos == TaskPos::Fcfs1);
+        test_assert!(mlfq.pick_next().is_none());
+
+        // 提前让出处理器的任务不降级
+        mlfq.requeue(pcb);
+        let pcb = mlfq.pick_next().unwrap();
+        test_assert!(pcb.as_ref().inner.lock().task_pos == TaskPos::Fcfs1);
+
+        use_up(&mut mlfq, &pcb);
+        mlfq.requeue(pcb);
+        let pcb = mlfq.pick_next();
+        test_assert!(pcb.is_some());
+        let pcb = pcb.unwrap();
+        test_assert!(pcb.as_ref().inner.lock().task_pos == TaskPos::Fcfs2);
+        test_assert!(mlfq.pick_next().is_none());
+
+        let pcb1 = new_task(&app_data, &mut procs);
+        let pcb2 = new_task(&app_data, &mut procs);
+        let pcb3 = new_task(&app_data, &mut procs);
+        let pcb4 = new_task(&app_data, &mut procs);
+
+        let pid1 = pcb1.process().pid.0;
+        let pid2 = pcb2.process().pid.0;
+        let pid3 = pcb3.process().pid.0;
+        let pid4 = pcb4.process().pid.0;
+
+        mlfq.enqueue(pcb1);
+        mlfq.enqueue(pcb2);
+        mlfq.enqueue(pcb3);
+
+        let pcb1 = mlfq.pick_next().unwrap();
+        let pcb2 = mlfq.pick_next().unwrap();
+        let pcb3 = mlfq.pick_next().unwrap();
+
+        for pcb in [&pcb1, &pcb2, &pcb3] {
+            use_up(&mut mlfq, pcb);
+        }
+        mlfq.requeue(pcb1);
+        mlfq.requeue(pcb2);
+        mlfq.requeue(pcb3);
+
+        let pcb1 = mlfq.pick_next().unwrap();
+        use_up(&mut mlfq, &pcb1);
+        mlfq.requeue(pcb1);
+
+        mlfq.enqueue(pcb4);
+
+        let pcb4 = mlfq.pick_next().unwrap();
+        let pcb2 = mlfq.pick_next().unwrap();
+        let pcb3 = mlfq.pick_next().unwrap();
+        let pcb1 = mlfq.pick_next().unwrap();
+
+        test_assert!(pid1 == pcb1.process().pid.0);
+        test_assert!(pid2 == pcb2.process().pid.0);
+        test_assert!(pid3 == pcb3.process().pid.0);
+        test_assert!(pid4 == pcb4.process().pid.0);
+
+        // 被唤醒的任务保持原有级别
+        mlfq.enqueue(pcb4);
+        mlfq.wakeup(pcb1);
+        let pcb4 = mlfq.pick_next().unwrap();
+        let pcb1 = mlfq.pick_next().unwrap();
+        test_assert!(pid4 == pcb4.process().pid.0);
+        test_assert!(pid1 == pcb1.process().pid.0);
+        test_assert!(pcb1.as_ref().inner.lock().task_pos == TaskPos::Rr);
+
+        // 提升后所有排队的任务回到最高级
+        mlfq.requeue(pcb1);
+        mlfq.requeue(pcb4);
+        mlfq.boost();
+        let pcb4 = mlfq.pick_next().unwrap();
+        let pcb1 = mlfq.pick_next().unwrap();
+        test_assert!(pid4 == pcb4.process().pid.0);
+        test_assert!(pcb1.as_ref().inner.lock().task_pos == TaskPos::Fcfs1);
+        test_assert!(pcb1.as_ref().inner.lock().level_used == 0);
+
+        // 阻塞期间错过提升的任务被唤醒时回到最高级
+        use_up(&mut mlfq, &pcb1);
+        mlfq.requeue(pcb1);
+        let pcb1 = mlfq.pick_next().unwrap();
+        test_assert!(pcb1.as_ref().inner.lock().task_pos == TaskPos::Fcfs2);
+        mlfq.boost();
+        mlfq.wakeup(pcb1);
+        let pcb1 = mlfq.pick_next().unwrap();
+        test_assert!(pcb1.as_ref().inner.lock().task_pos == TaskPos::Fcfs1);
+        test_assert!(pcb1.as_ref().inner.lock().level_used == 0);
+
+        Ok("passed")
+    });
+
+    test!(test_schd, {
+        let app_inode = find_inode("/bin/daemon").expect("[kernel] daemon not found!");
+        let size = app_inode.get_file_size() as usize;
+        let mut app_data = vec![0u8; size];
+        app_inode.read_at(0, &mut app_data);
+        let mut procs = vec![];
+
+        let pcb1 = new_task(&app_data, &mut procs);
+        let pcb2 = new_task(&app_data, &mut procs);
+        let pcb3 = new_task(&app_data, &mut procs);
+        let pcb4 = new_task(&app_data, &mut procs);
+
+        let pid1 = pcb1.process().pid.0;
+        let pid2 = pcb2.process().pid.0;
+        let pid3 = pcb3.process().pid.0;
+        let pid4 = pcb4.process().pid.0;
+
+        let mut master = SchdMaster::new();
+
+        master.add_new_task(pcb1);
+        master.add_new_task(pcb2);
+        master.add_new_task(pcb3);
+        master.add_new_task(pcb4);
+
+        let pcb1 = master.get_next().unwrap();
+        let pcb2 = master.get_next().unwrap();
+        let pcb3 = master.get_next().unwrap();
+
+        test_assert!(pid1 == pcb1.process().pid.0);
+        test_assert!(pid2 == pcb2.process().pid.0);
+        test_assert!(pid3 == pcb3.process().pid.0);
+
+        master.requeue_current(pcb1);
+        let pcb4 = master.get_next().unwrap();
+        test_assert!(pid4 == pcb4.process().pid.0);
+
+        master.requeue_current(pcb2);
+        master.requeue_current(pcb4);
+        master.requeue_current(pcb3);
+
+        let pcb1 = master.get_next().unwrap();
+        let pcb2 = master.get_next().unwrap();
+        let pcb4 = master.get_next().unwrap();
+        let pcb3 = master.get_next().unwrap();
+
+        test_assert!(pid1 == pcb1.process().pid.0);
+        test_assert!(pid2 == pcb2.process().pid.0);
+        test_assert!(pid3 == pcb3.process().pid.0);
+        test_assert!(pid4 == pcb4.process().pid.0);
+
+        Ok("passed")
+    });
+
+    /// 读取守护进程的程序用于创建测试任务
+    fn daemon_data() -> Vec<u8> {
+        let app_inode = find_inode("/bin/daemon").expect("[kernel] daemon not found!");
+        let mut app_data = vec![0u8; app_inode.get_file_size() as usize];
+        app_inode.read_at(0, &mut app_data);
+        app_data
+    }
+
+    /// 反复调度rounds次，每次运行elapsed个周期，返回两个任务各自被选中的次数
+    fn run_rounds(
+        scheduler: &mut dyn Scheduler,
+        first: &Arc<ThreadControlBlock>,
+        rounds: usize,
+        elapsed: usize,
+    ) -> (usize, usize) {
+        let mut counts = (0, 0);
+        for _ in 0..rounds {
+            let task = scheduler.pick_next().unwrap();
+            if Arc::ptr_eq(&task, first) {
+                counts.0 += 1;
+            } else {
+                counts.1 += 1;
+            }
+            scheduler.tick(&task, elapsed);
+            scheduler.requeue(task);
+        }
+        counts
+    }
+
+    test!(test_rr, {
+        let app_data = daemon_data();
+        let mut procs = vec![];
+        let task1 = new_task(&app_data, &mut procs);
+        let task2 = new_task(&app_data, &mut procs);
+        let mut rr = RoundRobin::new();
+        rr.enqueue(task1.clone());
+        rr.enqueue(task2.clone());
+        test_assert!(run_rounds(&mut rr, &task1, 10, 1) == (5, 5));
+        test_assert!(rr.time_slice(&task1) == rr.time_slice(&task2));
+        Ok("passed")
+    });
+
+    test!(test_stride, {
+        let app_data = daemon_data();
+        let mut procs = vec![];
+        let task1 = new_task(&app_data, &mut procs);
+        let task2 = new_task(&app_data, &mut procs);
+        // 权重约为3:1
+        task2.inner.lock().nice = 5;
+        let mut stride = Stride::new();
+        stride.enqueue(task1.clone());
+        stride.enqueue(task2.clone());
+        let (count1, count2) = run_rounds(&mut stride, &task1, 40, 1);
+        test_assert!(count1 >= 29 && count2 >= 9);
+        Ok("passed")
+    });
+
+    test!(test_cfs, {
+        let app_data = daemon_data();
+        let mut procs = vec![];
+        let task1 = new_task(&app_data, &mut procs);
+        let task2 = new_task(&app_data, &mut procs);
+        task2.inner.lock().nice = 5;
+        let mut cfs = Cfs::new();
+        cfs.enqueue(task1.clone());
+        cfs.enqueue(task2.clone());
+        // 权重较大的任务得到较长的时间片
+        test_assert!(cfs.time_slice(&task1) > cfs.time_slice(&task2));
+        let (count1, count2) = run_rounds(&mut cfs, &task1, 40, 100_000);
+        test_assert!(count1 >= 29 && count2 >= 9);
+        // 睡眠很久的任务被唤醒后不会积累过多的运行机会
+        let task3 = new_task(&app_data, &mut procs);
+        cfs.wakeup(task3.clone());
+        let (count3, _) = run_rounds(&mut cfs, &task3, 40, 100_000);
+        test_assert!(count3 < 30);
+        // 排队期间提高优先级，出队时仍扣除入队时的权重
+        let mut cfs = Cfs::new();
+        cfs.enqueue(task1.clone());
+        task1.inner.lock().nice = -10;
+        test_assert!(cfs.pick_next().is_some());
+        test_assert!(cfs.time_slice(&task1) == Cfs::new().time_slice(&task1));
+        Ok("passed")
+    });
+}
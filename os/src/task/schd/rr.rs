@@ -0,0 +1,42 @@
+//! 时间片轮转调度
+
+use super::Scheduler;
+use crate::config::SCHED_SLICE_MS;
+use crate::task::thread::ThreadControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 单个先进先出队列，所有任务的时间片相同
+pub struct RoundRobin {
+    queue: VecDeque<Arc<ThreadControlBlock>>,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        RoundRobin {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobin {
+    fn name(&self) -> &'static str {
+        "rr"
+    }
+    fn enqueue(&mut self, task: Arc<ThreadControlBlock>) {
+        self.queue.push_back(task);
+    }
+    fn requeue(&mut self, task: Arc<ThreadControlBlock>) {
+        self.queue.push_back(task);
+    }
+    fn wakeup(&mut self, task: Arc<ThreadControlBlock>) {
+        self.queue.push_back(task);
+    }
+    fn pick_next(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        self.queue.pop_front()
+    }
+    fn tick(&mut self, _task: &Arc<ThreadControlBlock>, _elapsed: usize) {}
+    fn time_slice(&self, _task: &Arc<ThreadControlBlock>) -> usize {
+        SCHED_SLICE_MS
+    }
+}
@@ -0,0 +1,69 @@
+//! 步长调度
+//!
+//! 每次运行后pass增加与权重成反比的步长，总是选择pass最小的任务
+
+use super::{nice_to_weight, Scheduler};
+use crate::config::SCHED_SLICE_MS;
+use crate::task::thread::ThreadControlBlock;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+/// 权重为1时的步长
+const BIG_STRIDE: usize = 1 << 32;
+
+/// 按(pass, 入队序号)排序的就绪队列
+pub struct Stride {
+    queue: BTreeMap<(usize, usize), Arc<ThreadControlBlock>>,
+    /// 入队序号，pass相同时先入队的先运行
+    seq: usize,
+    /// 最近被选中的任务的pass，新任务与被唤醒的任务不低于此值
+    min_pass: usize,
+}
+
+impl Stride {
+    pub fn new() -> Self {
+        Stride {
+            queue: BTreeMap::new(),
+            seq: 0,
+            min_pass: 0,
+        }
+    }
+
+    fn insert(&mut self, task: Arc<ThreadControlBlock>) {
+        let pass = task.inner.lock().vruntime;
+        self.seq += 1;
+        self.queue.insert((pass, self.seq), task);
+    }
+}
+
+impl Scheduler for Stride {
+    fn name(&self) -> &'static str {
+        "stride"
+    }
+    fn enqueue(&mut self, task: Arc<ThreadControlBlock>) {
+        task.inner.lock().vruntime = self.min_pass;
+        self.insert(task);
+    }
+    fn requeue(&mut self, task: Arc<ThreadControlBlock>) {
+        let mut inner = task.inner.lock();
+        inner.vruntime += BIG_STRIDE / nice_to_weight(inner.nice);
+        drop(inner);
+        self.insert(task);
+    }
+    /// 睡眠期间不积累运行机会
+    fn wakeup(&mut self, task: Arc<ThreadControlBlock>) {
+        let mut inner = task.inner.lock();
+        inner.vruntime = inner.vruntime.max(self.min_pass);
+        drop(inner);
+        self.insert(task);
+    }
+    fn pick_next(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        let key = *self.queue.keys().next()?;
+        self.min_pass = self.min_pass.max(key.0);
+        self.queue.remove(&key)
+    }
+    fn tick(&mut self, _task: &Arc<ThreadControlBlock>, _elapsed: usize) {}
+    fn time_slice(&self, _task: &Arc<ThreadControlBlock>) -> usize {
+        SCHED_SLICE_MS
+    }
+}
@@ -0,0 +1,149 @@
+//! 信号子模块
+
+use crate::interrupt::context::Context;
+
+/// 信号集合（第n位对应n号信号）
+pub type SignalFlags = u32;
+
+/// 最大信号编号
+pub const MAX_SIG: usize = 31;
+
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGBUS: usize = 7;
+pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGWINCH: usize = 28;
+
+/// 默认处理方式
+pub const SIG_DFL: usize = 0;
+/// 忽略该信号
+pub const SIG_IGN: usize = 1;
+
+/// sigprocmask: 将set加入屏蔽集合
+pub const SIG_BLOCK: usize = 0;
+/// sigprocmask: 将set移出屏蔽集合
+pub const SIG_UNBLOCK: usize = 1;
+/// sigprocmask: 将屏蔽集合设为set
+pub const SIG_SETMASK: usize = 2;
+
+/// 不可被捕获、忽略或屏蔽的信号
+pub const UNMASKABLE: SignalFlags = 1 << SIGKILL | 1 << SIGSTOP;
+
+/// 默认行为为暂停的信号
+pub const STOP_SIGNALS: SignalFlags = 1 << SIGSTOP | 1 << SIGTSTP | 1 << SIGTTIN | 1 << SIGTTOU;
+
+/// 获取信号对应的标志位
+pub fn sig_bit(signum: usize) -> SignalFlags {
+    1 << signum
+}
+
+/// 信号编号是否合法
+pub fn is_valid_signal(signum: usize) -> bool {
+    signum > 0 && signum <= MAX_SIG
+}
+
+/// 信号的默认行为
+#[derive(Copy, Clone, PartialEq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+}
+
+impl DefaultAction {
+    /// 获取信号的默认行为
+    pub fn of(signum: usize) -> Self {
+        match signum {
+            // SIGCONT的继续运行在发送时已处理
+            SIGCHLD | SIGCONT | SIGURG | SIGWINCH => DefaultAction::Ignore,
+            _ if sig_bit(signum) & STOP_SIGNALS != 0 => DefaultAction::Stop,
+            _ => DefaultAction::Terminate,
+        }
+    }
+}
+
+/// 用户注册的信号处理方式（与用户库中的定义保持一致）
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalAction {
+    /// 处理函数地址，或SIG_DFL/SIG_IGN
+    pub handler: usize,
+    /// 处理函数返回时跳转的地址（负责调用sigreturn）
+    pub restorer: usize,
+    /// 处理期间额外屏蔽的信号
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    /// 默认处理方式
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+impl SignalAction {
+    /// 该处理方式下信号是否会被直接丢弃
+    pub fn ignores(&self, signum: usize) -> bool {
+        self.handler == SIG_IGN
+            || (self.handler == SIG_DFL && DefaultAction::of(signum) == DefaultAction::Ignore)
+    }
+}
+
+/// 进程的信号处理表
+#[derive(Copy, Clone)]
+pub struct SignalActions {
+    pub table: [SignalAction; MAX_SIG + 1],
+}
+
+impl SignalActions {
+    /// 全部使用默认处理方式
+    pub fn new() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+
+    /// exec后重置：已注册的处理函数恢复默认，被忽略的信号保持忽略
+    pub fn reset_handlers(&mut self) {
+        for action in self.table.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+    }
+}
+
+/// 进入用户信号处理函数前保存的现场，由sigreturn恢复
+#[derive(Copy, Clone)]
+pub struct SignalFrame {
+    pub trap_cx: Context,
+    pub mask: SignalFlags,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    test!(test_signal_default_action, {
+        test_assert!(DefaultAction::of(SIGKILL) == DefaultAction::Terminate);
+        test_assert!(DefaultAction::of(SIGCHLD) == DefaultAction::Ignore);
+        test_assert!(DefaultAction::of(SIGTSTP) == DefaultAction::Stop);
+        let mut action = SignalAction::default();
+        test_assert!(action.ignores(SIGCHLD) && !action.ignores(SIGKILL));
+        action.handler = SIG_IGN;
+        test_assert!(action.ignores(SIGKILL));
+        Ok("passed")
+    });
+}
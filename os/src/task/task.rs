@@ -1,202 +1,352 @@
-use super::context::TaskContext;
-use super::id::{pid_alloc, KernelStack, PidHandle};
-use crate::config::TRAP_CONTEXT;
-use crate::fs::rfs::find_inode;
-use crate::fs::rfs::layout::InodeType;
+use super::id::{pid_alloc, PidHandle, RecycleAllocator};
+use super::signal::*;
+use super::thread::{map_user_res, trap_cx_bottom_from_tid, ustack_top_from_tid, TaskStatus};
+use super::thread::{ThreadControlBlock, ThreadControlBlockInner, ThreadUserRes};
+use super::wait_queue::WaitQueue;
+use super::{block_current_and_run_next, kill_other_threads};
+use crate::config::{PAGE_SIZE, USER_STACK_SIZE};
 use crate::fs::stdio::{Stdin, Stdout};
 use crate::fs::File;
 use crate::interrupt::{context::Context, handler::interrupt_handler};
 use crate::memory::frame::address::*;
+use crate::memory::frame::memory_set::{kernel_token, MemorySet};
 use crate::memory::frame::user_buffer::put_user_value;
-use crate::memory::frame::{memory_set::MemorySet, memory_set::KERNEL_MEMORY_SET};
-use alloc::rc::{Rc, Weak};
-use alloc::string::{String, ToString};
+use crate::sync::SpinLock;
+use crate::sync::{Condvar, Mutex, Semaphore};
+use crate::sys_call::errno::{Errno, EINTR};
+use crate::tools::elf_decoder::ElfFile;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::RefCell;
-use core::mem::size_of;
-
-/// 任务状态枚举
-#[derive(Copy, Clone, PartialEq)]
-pub enum TaskStatus {
-    Ready,
-    // Running,
-    Exited,
+use core::mem::{size_of, take};
+use core::ops::AddAssign;
+
+/// 辅助向量结束标志
+const AT_NULL: usize = 0;
+/// 程序头表地址
+const AT_PHDR: usize = 3;
+/// 程序头表项大小
+const AT_PHENT: usize = 4;
+/// 程序头表项数量
+const AT_PHNUM: usize = 5;
+/// 页面大小
+const AT_PAGESZ: usize = 6;
+/// 程序入口
+const AT_ENTRY: usize = 9;
+
+/// 生成传递给用户程序的辅助向量（以AT_NULL结尾）
+fn auxv(elf_data: &[u8], entry_point: usize) -> Vec<(usize, usize)> {
+    let elf = ElfFile::new(elf_data).unwrap();
+    let mut auxv = vec![];
+    if let Some(ph_vaddr) = elf.ph_vaddr() {
+        auxv.push((AT_PHDR, ph_vaddr));
+    }
+    auxv.push((AT_PHENT, elf.header.ph_entry_size()));
+    auxv.push((AT_PHNUM, elf.header.ph_count()));
+    auxv.push((AT_PAGESZ, PAGE_SIZE));
+    auxv.push((AT_ENTRY, entry_point));
+    auxv.push((AT_NULL, 0));
+    auxv
 }
 
-/// 任务队列状态枚举
-#[derive(Copy, Clone, PartialEq)]
-pub enum TaskPos {
-    Fcfs1,
-    Fcfs2,
-    Rr,
+/// 进程的CPU时间（时钟周期数）
+#[derive(Copy, Clone, Default)]
+pub struct CpuTimes {
+    /// 用户态运行时间
+    pub user: usize,
+    /// 内核态运行时间
+    pub system: usize,
+}
+
+impl AddAssign for CpuTimes {
+    fn add_assign(&mut self, other: Self) {
+        self.user += other.user;
+        self.system += other.system;
+    }
 }
 
 /// 进程控制块
 pub struct ProcessControlBlock {
     pub pid: PidHandle,
-    pub kernel_stack: KernelStack,
-    pub inner: RefCell<ProcessControlBlockInner>,
+    pub inner: SpinLock<ProcessControlBlockInner>,
+    /// 等待子进程或线程状态变化的线程
+    pub wait_queue: SpinLock<WaitQueue>,
 }
 
 pub struct ProcessControlBlockInner {
     /// 进程控制块内部可变结构
-    pub task_status: TaskStatus,
-    pub task_cx: TaskContext,
-    pub task_pos: TaskPos,
+    pub is_zombie: bool,
     pub memory_set: MemorySet,
-    pub trap_cx_ppn: PhysPageNum,
     pub cwd: String,
-    pub fd_table: Vec<Option<Rc<dyn File>>>,
+    /// exec时的参数
+    pub cmdline: Vec<String>,
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
     pub parent: Weak<ProcessControlBlock>,
-    pub children: Vec<Rc<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    /// 所属进程组
+    pub pgid: usize,
+    /// 所属会话
+    pub sid: usize,
     pub exit_code: i32,
+    /// 本进程全部线程的CPU时间
+    pub times: CpuTimes,
+    /// 已回收的子进程（包括其回收的子进程）的CPU时间
+    pub children_times: CpuTimes,
+    /// 导致进程终止的信号
+    pub term_signal: Option<usize>,
+    /// 尚未被父进程waitpid获取的暂停信号
+    pub stop_report: Option<usize>,
+    /// 待处理信号
+    pub signals: SignalFlags,
+    pub signal_actions: SignalActions,
+    /// 是否因信号而暂停
+    pub stopped: bool,
+    /// 进程内的线程（下标为TID）
+    pub threads: Vec<Option<Arc<ThreadControlBlock>>>,
+    pub tid_allocator: RecycleAllocator,
+    /// 进程内的同步原语（下标为对应的ID）
+    pub mutex_list: Vec<Option<Arc<Mutex>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
 }
 
 impl ProcessControlBlock {
     /// 通过 elf 数据创建新进程
-    pub fn new(elf_data: &[u8]) -> Self {
+    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+        let (memory_set, entry) =
+            MemorySet::from_elf(elf_data).expect("[kernel] Invalid elf file!");
         let pid = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid);
-        let kernel_stack_top = kernel_stack.get_top();
-        let (memory_set, user_sp, entry) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr(TRAP_CONTEXT).vpn())
-            .expect("[kernel] Trap context not mapped!");
-        let trap_cx = trap_cx_ppn.addr().get_mut();
-        *trap_cx = Context::app_init_context(
-            entry,
-            user_sp,
-            unsafe { KERNEL_MEMORY_SET.satp_token() },
-            kernel_stack_top,
-            interrupt_handler as usize,
-        );
-        Self {
+        // 新进程自成一个会话与进程组
+        let (pgid, sid) = (pid.0, pid.0);
+        let process = Arc::new(Self {
             pid,
-            kernel_stack,
-            inner: RefCell::new(ProcessControlBlockInner {
-                task_status: TaskStatus::Ready,
-                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                task_pos: TaskPos::Fcfs1,
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
                 memory_set,
-                trap_cx_ppn,
                 cwd: String::from("/"),
+                cmdline: vec![],
                 fd_table: vec![
                     // 0 -> stdin
-                    Some(Rc::new(Stdin)),
+                    Some(Arc::new(Stdin)),
                     // 1 -> stdout
-                    Some(Rc::new(Stdout)),
+                    Some(Arc::new(Stdout)),
                     // 2 -> stderr
-                    Some(Rc::new(Stdout)),
+                    Some(Arc::new(Stdout)),
                 ],
                 parent: Weak::new(),
                 children: vec![],
+                pgid,
+                sid,
                 exit_code: 0,
+                times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                term_signal: None,
+                stop_report: None,
+                signals: 0,
+                signal_actions: SignalActions::new(),
+                stopped: false,
+                threads: vec![],
+                tid_allocator: RecycleAllocator::new(),
+                mutex_list: vec![],
+                semaphore_list: vec![],
+                condvar_list: vec![],
             }),
-        }
+            wait_queue: SpinLock::new(WaitQueue::new()),
+        });
+        let res = ThreadUserRes::alloc(&process).expect("[kernel] Out of memory!");
+        let ustack_top = res.ustack_top();
+        let thread =
+            Arc::new(ThreadControlBlock::new(&process, res, 0).expect("[kernel] Out of memory!"));
+        *thread.inner.lock().trap_cx() = Context::app_init_context(
+            entry,
+            ustack_top,
+            kernel_token(),
+            thread.kernel_stack.get_top(),
+            interrupt_handler as usize,
+        );
+        process.inner.lock().threads.push(Some(thread));
+        process
     }
 
-    /// 使用本进程用相应参数执行指定 elf 数据
-    pub fn exec(&self, elf_data: &[u8], args: &[String]) {
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let mem_inode =
-            find_inode(&(String::from("/proc/") + &self.pid.0.to_string() + "/mem")).unwrap();
-        mem_inode.clear();
-        mem_inode.write_at(0, memory_set.get_size().to_string().as_bytes());
-        let cmd_inode =
-            find_inode(&(String::from("/proc/") + &self.pid.0.to_string() + "/cmd")).unwrap();
-        cmd_inode.clear();
-        cmd_inode.write_at(0, args[0].as_bytes());
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr(TRAP_CONTEXT).vpn())
-            .expect("[kernel] Trap context not mapped!");
-
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv = vec![];
-        for arg in args {
-            user_sp -= (arg.len() + 1) * size_of::<usize>();
-            argv.push(user_sp);
-            let mut p = user_sp;
-            for &c in arg.as_bytes() {
-                put_user_value(memory_set.satp_token(), c, p as *mut u8);
-                p += 1;
+    /// 使用本进程用相应参数执行指定 elf 数据，调用线程成为新的主线程
+    /// 新地址空间在替换旧地址空间之前准备完毕，内存不足时返回ENOMEM且本进程不受影响
+    pub fn exec(
+        self: &Arc<Self>,
+        thread: &Arc<ThreadControlBlock>,
+        elf_data: &[u8],
+        args: &[String],
+        envs: &[String],
+    ) -> Result<(), Errno> {
+        let (mut memory_set, entry_point) = MemorySet::from_elf(elf_data)?;
+        // 新的主线程编号为0
+        map_user_res(&mut memory_set, 0)?;
+        let mut user_sp = ustack_top_from_tid(0);
+
+        // 在用户栈上放置参数与环境变量字符串，其下依次为argc、argv、envp与辅助向量
+        let mut push_str = |s: &String| -> Result<usize, Errno> {
+            user_sp -= s.len() + 1;
+            for (i, &c) in s.as_bytes().iter().chain(&[0u8]).enumerate() {
+                put_user_value(&mut memory_set, c, (user_sp + i) as *mut u8)?;
             }
-            put_user_value(memory_set.satp_token(), 0, p as *mut u8);
+            Ok(user_sp)
+        };
+        let argv = args
+            .iter()
+            .map(&mut push_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        let envp = envs
+            .iter()
+            .map(&mut push_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut words = vec![args.len()];
+        words.extend(argv);
+        words.push(0);
+        words.extend(envp);
+        words.push(0);
+        for (key, value) in auxv(elf_data, entry_point) {
+            words.push(key);
+            words.push(value);
         }
-        argv.push(0);
-        for (i, &arg_ptr) in argv.iter().enumerate() {
+        user_sp = (user_sp - words.len() * size_of::<usize>()) & !0xf;
+        for (i, &word) in words.iter().enumerate() {
             put_user_value(
-                memory_set.satp_token(),
-                arg_ptr,
-                (argv_base + i * size_of::<usize>()) as *mut u8,
-            );
+                &mut memory_set,
+                word,
+                (user_sp + i * size_of::<usize>()) as *mut u8,
+            )?;
         }
+        let argv_base = user_sp + size_of::<usize>();
+        let envp_base = argv_base + (args.len() + 1) * size_of::<usize>();
 
-        let mut inner = self.inner.borrow_mut();
+        // 通知其余线程退出，等它们离开内核后才能释放其资源并替换地址空间
+        kill_other_threads(self, thread);
+        loop {
+            // 其他线程同时退出或执行exec时本线程也被通知退出
+            if thread.inner.lock().killed {
+                return Err(EINTR);
+            }
+            if !self.inner.lock().has_other_threads(thread) {
+                break;
+            }
+            self.wait_queue.lock().push(thread.clone());
+            block_current_and_run_next();
+            self.wait_queue.lock().remove(thread);
+        }
+        // 在旧地址空间中释放全部线程资源
+        let threads = take(&mut self.inner.lock().threads);
+        let mut old_res = vec![thread.inner.lock().res.take()];
+        for other in threads.iter().flatten() {
+            if !Arc::ptr_eq(other, thread) {
+                old_res.push(other.inner.lock().res.take());
+            }
+        }
+        drop(old_res);
+        drop(threads);
+
+        let mut inner = self.inner.lock();
         inner.memory_set = memory_set;
-        inner.trap_cx_ppn = trap_cx_ppn;
+        inner.tid_allocator = RecycleAllocator::new();
+        let tid = inner.tid_allocator.alloc();
+        inner.signal_actions.reset_handlers();
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        inner.threads.push(Some(thread.clone()));
+        inner.cmdline = args.to_vec();
+        drop(inner);
+        let res = ThreadUserRes::inherit(self, tid);
+        let mut thread_inner = thread.inner.lock();
+        thread_inner.trap_cx_ppn = res.trap_cx_ppn();
+        thread_inner.res = Some(res);
+        thread_inner.signal_frame = None;
 
         let mut trap_cx = Context::app_init_context(
             entry_point,
             user_sp,
-            unsafe { KERNEL_MEMORY_SET.satp_token() },
-            self.kernel_stack.get_top(),
+            kernel_token(),
+            thread.kernel_stack.get_top(),
             interrupt_handler as usize,
         );
         trap_cx.x[10] = args.len();
         trap_cx.x[11] = argv_base;
-        *trap_cx_ppn.addr().get_mut() = trap_cx;
+        trap_cx.x[12] = envp_base;
+        *thread_inner.trap_cx() = trap_cx;
+        Ok(())
     }
 
-    /// fork 创建子进程
-    pub fn fork(self: Rc<Self>) -> Rc<Self> {
-        let mut inner = self.inner.borrow_mut();
-        let memory_set = inner.memory_set.clone();
+    /// fork 创建子进程，子进程中只有调用fork的线程；内存不足时返回ENOMEM
+    pub fn fork(self: &Arc<Self>, thread: &Arc<ThreadControlBlock>) -> Result<Arc<Self>, Errno> {
+        let mut inner = self.inner.lock();
+        let memory_set = inner.memory_set.fork()?;
         let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr(TRAP_CONTEXT).vpn())
-            .expect("[kernel] Trap context not mapped!");
-        // write proc info
-        let procs_inode = find_inode("/proc").unwrap();
-        let proc_inode = procs_inode
-            .create(&pid_handle.0.to_string(), InodeType::Directory)
-            .unwrap();
-        proc_inode.set_default_dirent(procs_inode.get_inode_id());
-        let mem_inode = proc_inode.create("mem", InodeType::File).unwrap();
-        mem_inode.write_at(0, memory_set.get_size().to_string().as_bytes());
-        let cmd_inode = proc_inode.create("cmd", InodeType::File).unwrap();
-        if let Some(parent_cmd_inode) =
-            find_inode(&(String::from("/proc/") + &self.pid.0.to_string() + "/cmd"))
-        {
-            let mut cmd = vec![0u8; parent_cmd_inode.get_file_size() as usize];
-            parent_cmd_inode.read_at(0, &mut cmd);
-            cmd_inode.write_at(0, &cmd);
-        }
-
-        let new_pcb = Rc::new(ProcessControlBlock {
+        let new_pcb = Arc::new(ProcessControlBlock {
             pid: pid_handle,
-            kernel_stack,
-            inner: RefCell::new(ProcessControlBlockInner {
-                task_status: inner.task_status,
-                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                task_pos: inner.task_pos,
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
                 memory_set,
-                trap_cx_ppn,
                 cwd: inner.cwd.clone(),
+                cmdline: inner.cmdline.clone(),
                 fd_table: inner.fd_table.clone(),
-                parent: Rc::downgrade(&self),
+                parent: Arc::downgrade(self),
                 children: vec![],
+                pgid: inner.pgid,
+                sid: inner.sid,
                 exit_code: 0,
+                times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                term_signal: None,
+                stop_report: None,
+                signals: 0,
+                signal_actions: inner.signal_actions,
+                stopped: false,
+                threads: vec![],
+                tid_allocator: inner.tid_allocator.clone(),
+                mutex_list: vec![],
+                semaphore_list: vec![],
+                condvar_list: vec![],
             }),
+            wait_queue: SpinLock::new(WaitQueue::new()),
         });
-        inner.children.push(new_pcb.clone());
-        let trap_cx = new_pcb.inner.borrow_mut().trap_cx();
-        trap_cx.kernel_sp = kernel_stack_top;
-        new_pcb
+
+        // 子进程中不存在的其他线程的资源需要释放（保留0号，主线程编号不再复用）
+        let thread_inner = thread.inner.lock();
+        let tid = thread_inner.tid();
+        let mut new_inner = new_pcb.inner.lock();
+        for other in inner.threads.iter().flatten() {
+            // 调用线程的控制块已被锁住，不能再次加锁
+            if Arc::ptr_eq(other, thread) {
+                continue;
+            }
+            let other_tid = other.inner.lock().res.as_ref().map(|res| res.tid);
+            if let Some(other_tid) = other_tid {
+                if other_tid != 0 {
+                    new_inner.dealloc_user_res(other_tid);
+                }
+            }
+        }
+        drop(new_inner);
+        drop(inner);
+
+        let new_thread = Arc::new(ThreadControlBlock::new(
+            &new_pcb,
+            ThreadUserRes::inherit(&new_pcb, tid),
+            thread_inner.signal_mask,
+        )?);
+        let mut new_thread_inner = new_thread.inner.lock();
+        new_thread_inner.task_pos = thread_inner.task_pos;
+        new_thread_inner.vruntime = thread_inner.vruntime;
+        new_thread_inner.nice = thread_inner.nice;
+        new_thread_inner.signal_frame = thread_inner.signal_frame;
+        new_thread_inner.trap_cx().kernel_sp = new_thread.kernel_stack.get_top();
+        drop(new_thread_inner);
+        let mut new_inner = new_pcb.inner.lock();
+        new_inner.threads.resize(tid + 1, None);
+        new_inner.threads[tid] = Some(new_thread);
+        drop(new_inner);
+        // 子进程创建成功后才加入父进程
+        self.inner.lock().children.push(new_pcb.clone());
+        Ok(new_pcb)
     }
 }
 
@@ -206,10 +356,6 @@ impl ProcessControlBlockInner {
         self.memory_set.satp_token()
     }
 
-    pub fn trap_cx(&self) -> &'static mut Context {
-        self.trap_cx_ppn.addr().get_mut()
-    }
-
     pub fn alloc_fd(&mut self) -> usize {
         if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
             fd
@@ -218,15 +364,72 @@ impl ProcessControlBlockInner {
             self.fd_table.len() - 1
         }
     }
-}
 
-/// 进程控制块Drop实现
-impl Drop for ProcessControlBlock {
-    fn drop(&mut self) {
-        let procs_inode = find_inode("/proc").unwrap();
-        if let Some(proc_inode) = procs_inode.find(&self.pid.0.to_string()) {
-            proc_inode.delete("mem");
-            procs_inode.delete(&self.pid.0.to_string());
+    /// 释放线程的用户栈、中断上下文页与TID
+    pub fn dealloc_user_res(&mut self, tid: usize) {
+        let ustack_top = ustack_top_from_tid(tid);
+        self.memory_set
+            .remove_segment(VirtAddr(ustack_top - USER_STACK_SIZE).vpn());
+        self.memory_set
+            .remove_segment(VirtAddr(trap_cx_bottom_from_tid(tid)).vpn());
+        self.tid_allocator.dealloc(tid);
+    }
+
+    /// 进程已退出且全部线程都已离开内核，可以被父进程回收
+    pub fn is_reapable(&self) -> bool {
+        self.is_zombie
+            && self
+                .threads
+                .iter()
+                .flatten()
+                .all(|thread| thread.inner.lock().task_status == TaskStatus::Exited)
+    }
+
+    /// 阻塞中的系统调用是否需要提前返回：有可以递送给该线程的信号，或线程已被通知退出
+    pub fn should_interrupt(&self, thread: &ThreadControlBlockInner) -> bool {
+        thread.killed || self.next_signal(thread).is_some()
+    }
+
+    /// 除指定线程外是否还有未退出的线程
+    pub fn has_other_threads(&self, thread: &Arc<ThreadControlBlock>) -> bool {
+        self.threads.iter().flatten().any(|other| {
+            !Arc::ptr_eq(other, thread) && other.inner.lock().task_status != TaskStatus::Exited
+        })
+    }
+
+    /// 向本进程发送信号
+    pub fn add_signal(&mut self, signum: usize) {
+        if signum == SIGCONT {
+            // SIGCONT无论是否被捕获都会使进程继续运行
+            self.stopped = false;
+            self.stop_report = None;
+            self.signals &= !STOP_SIGNALS;
+        } else if sig_bit(signum) & STOP_SIGNALS != 0 {
+            self.signals &= !sig_bit(SIGCONT);
+        }
+        if sig_bit(signum) & UNMASKABLE == 0 && self.signal_actions.table[signum].ignores(signum) {
+            return;
         }
+        self.signals |= sig_bit(signum);
+    }
+
+    /// 编码后的退出状态（与Linux的wait status格式一致）
+    pub fn wait_status(&self) -> i32 {
+        match self.term_signal {
+            Some(signum) => signum as i32,
+            None => (self.exit_code & 0xff) << 8,
+        }
+    }
+
+    /// 取出下一个可以递送给指定线程的信号
+    /// 线程正在执行用户处理函数时，不再递送需要用户处理的信号
+    pub fn next_signal(&self, thread: &ThreadControlBlockInner) -> Option<usize> {
+        let pending = self.signals & !(thread.signal_mask & !UNMASKABLE);
+        (1..=MAX_SIG).find(|&signum| {
+            pending & sig_bit(signum) != 0
+                && (thread.signal_frame.is_none()
+                    || sig_bit(signum) & UNMASKABLE != 0
+                    || self.signal_actions.table[signum].handler == SIG_DFL)
+        })
     }
 }
@@ -0,0 +1,210 @@
+//! 线程子模块
+
+use super::context::TaskContext;
+use super::id::{kstack_alloc, KernelStack};
+use super::signal::{SignalFlags, SignalFrame};
+use super::ProcessControlBlock;
+use crate::config::{MAX_THREAD_NUM, PAGE_SIZE, TRAP_CONTEXT, USER_STACK_SIZE, USER_STACK_TOP};
+use crate::interrupt::context::Context;
+use crate::memory::frame::address::{PhysPageNum, VirtAddr};
+use crate::memory::frame::memory_set::MemorySet;
+use crate::memory::frame::page_table::{R, U, W};
+use crate::sync::SpinLock;
+use crate::sys_call::errno::{Errno, EAGAIN, ENOMEM};
+use alloc::sync::{Arc, Weak};
+
+/// 任务状态枚举
+#[derive(Copy, Clone, PartialEq)]
+pub enum TaskStatus {
+    Ready,
+    // Running,
+    /// 等待某一事件，不在调度队列中
+    Blocked,
+    /// 所属进程被信号暂停，不在调度队列中
+    Stopped,
+    Exited,
+}
+
+/// 任务队列状态枚举
+#[derive(Copy, Clone, PartialEq)]
+pub enum TaskPos {
+    Fcfs1,
+    Fcfs2,
+    Rr,
+}
+
+/// 线程中断上下文页的用户地址
+pub fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+/// 线程用户栈栈顶
+pub fn ustack_top_from_tid(tid: usize) -> usize {
+    USER_STACK_TOP - tid * (USER_STACK_SIZE + PAGE_SIZE)
+}
+
+/// 在地址空间中映射线程的用户栈与中断上下文页，内存不足时返回ENOMEM
+pub fn map_user_res(memory_set: &mut MemorySet, tid: usize) -> Result<(), Errno> {
+    let ustack_top = ustack_top_from_tid(tid);
+    let ustack_bottom = VirtAddr(ustack_top - USER_STACK_SIZE).vpn();
+    memory_set.insert_lazy_segment(ustack_bottom..VirtAddr(ustack_top).vpn(), U | R | W, None)?;
+    let trap_cx_bottom = trap_cx_bottom_from_tid(tid);
+    if let Err(err) = memory_set.insert_segment(
+        VirtAddr(trap_cx_bottom).vpn()..VirtAddr(trap_cx_bottom + PAGE_SIZE).vpn(),
+        R | W,
+        None,
+    ) {
+        memory_set.remove_segment(ustack_bottom);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// 线程在所属进程中占用的资源：TID、用户栈与中断上下文页
+pub struct ThreadUserRes {
+    pub tid: usize,
+    pub process: Weak<ProcessControlBlock>,
+}
+
+impl ThreadUserRes {
+    /// 分配TID并在进程地址空间中映射用户栈与中断上下文页
+    /// 线程过多时返回EAGAIN，内存不足时返回ENOMEM
+    pub fn alloc(process: &Arc<ProcessControlBlock>) -> Result<Self, Errno> {
+        let mut inner = process.inner.lock();
+        let tid = inner.tid_allocator.alloc();
+        if tid >= MAX_THREAD_NUM {
+            inner.tid_allocator.dealloc(tid);
+            return Err(EAGAIN);
+        }
+        if let Err(err) = map_user_res(&mut inner.memory_set, tid) {
+            inner.tid_allocator.dealloc(tid);
+            return Err(err);
+        }
+        Ok(Self {
+            tid,
+            process: Arc::downgrade(process),
+        })
+    }
+
+    /// 沿用地址空间中已有的资源（fork时复制自父线程）
+    pub fn inherit(process: &Arc<ProcessControlBlock>, tid: usize) -> Self {
+        Self {
+            tid,
+            process: Arc::downgrade(process),
+        }
+    }
+
+    /// 中断上下文页的用户地址
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_bottom_from_tid(self.tid)
+    }
+
+    /// 用户栈栈顶
+    pub fn ustack_top(&self) -> usize {
+        ustack_top_from_tid(self.tid)
+    }
+
+    /// 中断上下文所在物理页
+    pub fn trap_cx_ppn(&self) -> PhysPageNum {
+        let process = self.process.upgrade().unwrap();
+        let inner = process.inner.lock();
+        inner
+            .memory_set
+            .translate(VirtAddr(self.trap_cx_user_va()).vpn())
+            .expect("[kernel] Trap context not mapped!")
+    }
+}
+
+impl Drop for ThreadUserRes {
+    fn drop(&mut self) {
+        // 进程已被回收时其地址空间随之释放，无需单独处理
+        if let Some(process) = self.process.upgrade() {
+            process.inner.lock().dealloc_user_res(self.tid);
+        }
+    }
+}
+
+/// 线程控制块
+pub struct ThreadControlBlock {
+    pub process: Weak<ProcessControlBlock>,
+    pub kernel_stack: KernelStack,
+    pub inner: SpinLock<ThreadControlBlockInner>,
+}
+
+/// 线程控制块内部可变结构
+pub struct ThreadControlBlockInner {
+    pub res: Option<ThreadUserRes>,
+    pub trap_cx_ppn: PhysPageNum,
+    pub task_cx: TaskContext,
+    pub task_status: TaskStatus,
+    /// 所属进程已退出或执行了exec，线程离开内核等待后在返回用户态前退出
+    pub killed: bool,
+    pub task_pos: TaskPos,
+    /// 在多级反馈队列当前级别已使用的CPU时间（时钟周期）
+    pub level_used: usize,
+    /// 当前级别对应的多级反馈队列提升时刻，早于队列上次提升时说明在队列之外错过了提升
+    pub boost_epoch: usize,
+    /// 步长调度的pass或CFS的虚拟运行时间
+    pub vruntime: usize,
+    /// nice值，越小权重越大
+    pub nice: isize,
+    /// 线程退出码，由waittid取得
+    pub exit_code: Option<i32>,
+    /// 被屏蔽的信号
+    pub signal_mask: SignalFlags,
+    /// 正在执行用户信号处理函数时保存的现场
+    pub signal_frame: Option<SignalFrame>,
+}
+
+impl ThreadControlBlock {
+    /// 使用已分配的资源创建线程，内存不足时返回ENOMEM（资源随之释放）
+    pub fn new(
+        process: &Arc<ProcessControlBlock>,
+        res: ThreadUserRes,
+        signal_mask: SignalFlags,
+    ) -> Result<Self, Errno> {
+        let kernel_stack = kstack_alloc().ok_or(ENOMEM)?;
+        let kernel_stack_top = kernel_stack.get_top();
+        let trap_cx_ppn = res.trap_cx_ppn();
+        Ok(Self {
+            process: Arc::downgrade(process),
+            kernel_stack,
+            inner: SpinLock::new(ThreadControlBlockInner {
+                res: Some(res),
+                trap_cx_ppn,
+                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                task_status: TaskStatus::Ready,
+                killed: false,
+                task_pos: TaskPos::Fcfs1,
+                level_used: 0,
+                boost_epoch: 0,
+                vruntime: 0,
+                nice: 0,
+                exit_code: None,
+                signal_mask,
+                signal_frame: None,
+            }),
+        })
+    }
+
+    /// 获取所属进程
+    pub fn process(&self) -> Arc<ProcessControlBlock> {
+        self.process.upgrade().unwrap()
+    }
+}
+
+impl ThreadControlBlockInner {
+    pub fn trap_cx(&self) -> &'static mut Context {
+        self.trap_cx_ppn.addr().get_mut()
+    }
+
+    /// 线程在进程内的编号
+    pub fn tid(&self) -> usize {
+        self.res.as_ref().unwrap().tid
+    }
+
+    /// 中断上下文页的用户地址
+    pub fn trap_cx_user_va(&self) -> usize {
+        self.res.as_ref().unwrap().trap_cx_user_va()
+    }
+}
@@ -0,0 +1,47 @@
+//! 等待队列子模块
+
+use super::{wakeup_task, ThreadControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 等待某一事件而阻塞的线程队列
+pub struct WaitQueue {
+    queue: VecDeque<Arc<ThreadControlBlock>>,
+}
+
+impl WaitQueue {
+    /// 创建空的等待队列
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// 将线程加入等待队列
+    pub fn push(&mut self, task: Arc<ThreadControlBlock>) {
+        self.queue.push_back(task);
+    }
+
+    /// 将线程移出等待队列（被其他原因唤醒时使用）
+    pub fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.queue.retain(|waiter| !Arc::ptr_eq(waiter, task));
+    }
+
+    /// 唤醒队首仍在等待的线程，返回是否有线程被唤醒
+    /// 已被其他原因唤醒或已退出的线程直接移出，不占用这次唤醒
+    pub fn wake_one(&mut self) -> bool {
+        while let Some(task) = self.queue.pop_front() {
+            if wakeup_task(task) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 唤醒队列中的全部线程
+    pub fn wake_all(&mut self) {
+        while let Some(task) = self.queue.pop_front() {
+            wakeup_task(task);
+        }
+    }
+}
@@ -3,6 +3,7 @@
 use alloc::vec;
 use alloc::vec::Vec;
 use core::marker::Copy;
+use core::mem::size_of;
 
 /// 无符号地址
 #[derive(Clone, Copy, Debug)]
@@ -75,6 +76,21 @@ impl ElfHeader {
     pub fn entry(&self) -> usize {
         self.e_entry.0 as usize
     }
+
+    /// 程序头表在文件中的位置
+    pub fn ph_offset(&self) -> usize {
+        self.e_phoff.0 as usize
+    }
+
+    /// 程序头表项的大小
+    pub fn ph_entry_size(&self) -> usize {
+        self.e_phentsize.0 as usize
+    }
+
+    /// 程序头表项的数量
+    pub fn ph_count(&self) -> usize {
+        self.e_phnum.0 as usize
+    }
 }
 
 /// 程序头表
@@ -148,6 +164,9 @@ pub struct ElfFile {
 impl ElfFile {
     /// 解析elf数据
     pub fn new(elf_data: &[u8]) -> Option<Self> {
+        if elf_data.len() < size_of::<ElfHeader>() {
+            return None;
+        }
         let elf_ptr = elf_data.as_ptr();
         let header = unsafe { *(elf_ptr as *const ElfHeader) };
         if !header.is_valid() {
@@ -165,4 +184,15 @@ impl ElfFile {
             program_headers,
         })
     }
+
+    /// 程序头表被载入内存后的虚拟地址（不在任何加载段中时为None）
+    pub fn ph_vaddr(&self) -> Option<usize> {
+        let ph_offset = self.header.ph_offset();
+        self.program_headers
+            .iter()
+            .find(|ph| {
+                ph.is_load() && ph.offset() <= ph_offset && ph_offset < ph.offset() + ph.file_size()
+            })
+            .map(|ph| ph.vaddr() + ph_offset - ph.offset())
+    }
 }
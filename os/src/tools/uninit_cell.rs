@@ -10,6 +10,10 @@ impl<T> UninitCell<T> {
     pub fn init(inner: T) -> Self {
         Self(Some(inner))
     }
+
+    pub fn is_init(&self) -> bool {
+        self.0.is_some()
+    }
 }
 
 impl<T> Deref for UninitCell<T> {
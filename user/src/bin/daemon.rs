@@ -8,16 +8,19 @@ use user_lib::*;
 
 #[no_mangle]
 fn main() -> i32 {
-    if fork() == 0 {
-        exec("/bin/rush", &["rush"]);
+    // 显式打开控制台作为标准输入、输出与错误输出
+    for fd in 0..3 {
+        close(fd).unwrap();
+        assert_eq!(open("/dev/console", RDWR), Ok(fd));
+    }
+    if fork().unwrap() == 0 {
+        let errno = exec("/bin/rush", &["rush"]);
+        println!("rush: {}", errno);
     } else {
+        // 阻塞等待回收子进程，暂无子进程时内核也会阻塞等待孤儿进程
         loop {
             let mut exit_code: i32 = 0;
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
-                r#yield();
-                continue;
-            }
+            wait(&mut exit_code).ok();
         }
     }
     0
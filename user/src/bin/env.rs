@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use user_lib::*;
+
+/// env [NAME=VALUE]... [COMMAND [ARG]...]
+#[no_mangle]
+fn main(args: &[&str]) -> i32 {
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].split_once('=') {
+            Some((name, value)) => {
+                if let Err(errno) = setenv(name, value, true) {
+                    println!("env: '{}': {}", args[i], errno);
+                    return 1;
+                }
+            }
+            None => break,
+        }
+        i += 1;
+    }
+    if i == args.len() {
+        for env in environ() {
+            println!("{}", env);
+        }
+        return 0;
+    }
+    let errno = execvp(args[i], &args[i..]);
+    println!("env: '{}': {}", args[i], errno);
+    127
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, waitpid, wexitstatus, wifexited, wifsignaled, wtermsig, SIGSEGV};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+static mut BUF: [u8; BUF_SIZE] = [0; BUF_SIZE];
+
+/// 递归使用栈空间
+fn recurse(depth: usize) -> usize {
+    let frame = [depth as u8; 256];
+    if depth == 0 {
+        frame[0] as usize
+    } else {
+        recurse(depth - 1) + frame[255] as usize
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // bss与栈在首次访问时才分配物理页
+    unsafe {
+        assert!(BUF.iter().all(|&byte| byte == 0));
+        for (i, byte) in BUF.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        assert!(BUF.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+    }
+    assert_eq!(recurse(16), (1..=16).sum());
+    println!("lazy allocation ok");
+
+    let pid = fork().unwrap();
+    if pid == 0 {
+        // 写入写时复制的页面
+        unsafe {
+            BUF[0] = 42;
+            assert_eq!(BUF[0], 42);
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid, &mut status, 0).unwrap();
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    assert_eq!(unsafe { BUF[0] }, 0);
+    println!("copy on write ok");
+
+    let pid = fork().unwrap();
+    if pid == 0 {
+        unsafe {
+            core::ptr::write_volatile(0x10 as *mut usize, 0);
+        }
+        exit(0);
+    }
+    waitpid(pid, &mut status, 0).unwrap();
+    assert!(wifsignaled(status) && wtermsig(status) == SIGSEGV);
+    println!("segment fault ok");
+    println!("fault_test passed!");
+    0
+}
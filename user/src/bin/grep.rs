@@ -33,27 +33,25 @@ fn main(args: &[&str]) -> i32 {
     }
     for target in &args[2..] {
         // TODO: check file type
-        let fd = open(target, RDONLY);
-        if fd == -1 {
-            println!("{}: No such file or directory", target);
-            continue;
-        }
-        let mut stat = Stat::new();
-        match fstat(fd as usize, &mut stat) {
-            0 => {}
-            -1 => {
-                println!("{}: Bad file descriptor", fd);
+        let fd = match open(target, RDONLY) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                println!("{}: {}", target, errno);
                 continue;
             }
-            _ => panic!(),
+        };
+        let mut stat = Stat::new();
+        if let Err(errno) = fstat(fd, &mut stat) {
+            println!("{}: {}", fd, errno);
+            continue;
         }
         let mut buf = vec![0u8; stat.size as usize];
-        read(fd as usize, &mut buf);
-        let res = find(str::from_utf8(&buf).unwrap(), pattern);
+        let len = read(fd, &mut buf).unwrap_or(0);
+        let res = find(str::from_utf8(&buf[..len]).unwrap(), pattern);
         for line in res {
             println!("{}:{}", target, line);
         }
-        close(fd as usize);
+        close(fd).unwrap();
     }
     0
 }
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk, ENOMEM};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 远大于原先4K的固定堆
+    let mut big = vec![0usize; 128 * 1024];
+    for (i, value) in big.iter_mut().enumerate() {
+        *value = i;
+    }
+    assert!(big.iter().enumerate().all(|(i, &value)| value == i));
+    let mut small = Vec::new();
+    for i in 0..1000 {
+        small.push(vec![i as u8; 64]);
+    }
+    assert!(small.iter().enumerate().all(|(i, v)| v[63] == i as u8));
+    drop(big);
+    drop(small);
+    println!("heap growth ok");
+
+    let old_brk = sbrk(4096).unwrap();
+    let page = unsafe { core::slice::from_raw_parts_mut(old_brk as *mut u8, 4096) };
+    page.fill(0xff);
+    assert_eq!(sbrk(-4096), Ok(old_brk + 4096));
+    assert_eq!(brk(0), old_brk as isize);
+    // 堆不能越过mmap区域
+    assert_eq!(sbrk(isize::MAX / 2), Err(ENOMEM));
+    println!("sbrk ok");
+    println!("heap_test passed!");
+    0
+}
@@ -7,12 +7,55 @@ extern crate user_lib;
 use core::str;
 use user_lib::*;
 
+const SIGNAL_NAMES: &[(&str, usize)] = &[
+    ("HUP", SIGHUP),
+    ("INT", SIGINT),
+    ("QUIT", SIGQUIT),
+    ("KILL", SIGKILL),
+    ("USR1", SIGUSR1),
+    ("USR2", SIGUSR2),
+    ("ALRM", SIGALRM),
+    ("TERM", SIGTERM),
+    ("CHLD", SIGCHLD),
+    ("CONT", SIGCONT),
+    ("STOP", SIGSTOP),
+    ("TSTP", SIGTSTP),
+];
+
+/// 解析信号编号或名称（如9、KILL、SIGKILL）
+fn parse_signal(s: &str) -> Option<usize> {
+    if let Ok(signum) = s.parse::<usize>() {
+        return Some(signum);
+    }
+    let name = s.strip_prefix("SIG").unwrap_or(s);
+    SIGNAL_NAMES
+        .iter()
+        .find(|(signame, _)| *signame == name)
+        .map(|(_, signum)| *signum)
+}
+
 #[no_mangle]
 fn main(args: &[&str]) -> i32 {
-    for arg in &args[1..] {
+    let mut pids = &args[1..];
+    let mut signum = SIGTERM;
+    if let Some(sig) = pids.first().and_then(|arg| arg.strip_prefix('-')) {
+        match parse_signal(sig) {
+            Some(sig) => signum = sig,
+            None => {
+                println!("{}: invalid signal specification", sig);
+                return 1;
+            }
+        }
+        pids = &pids[1..];
+    }
+    if pids.is_empty() {
+        println!("Usage: kill [-SIGNAL] PID...");
+        return 1;
+    }
+    for arg in pids {
         if let Ok(pid) = arg.parse::<isize>() {
-            if kill(pid as usize) == -1 {
-                println!("({}) - No such process", pid);
+            if let Err(errno) = kill(pid as usize, signum) {
+                println!("({}) - {}", pid, errno);
             }
         }
     }
@@ -18,19 +18,17 @@ fn main(args: &[&str]) -> i32 {
         targets.push(target);
     }
     for target in targets {
-        let fd = open(target, RDONLY);
-        let mut stat = Stat::new();
-        if fd == -1 {
-            println!("cannot access '{}': No such file or directory", target);
-            continue;
-        }
-        match fstat(fd as usize, &mut stat) {
-            0 => {}
-            -1 => {
-                println!("{}: Bad file descriptor", fd);
+        let fd = match open(target, RDONLY) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                println!("cannot access '{}': {}", target, errno);
                 continue;
             }
-            _ => panic!(),
+        };
+        let mut stat = Stat::new();
+        if let Err(errno) = fstat(fd, &mut stat) {
+            println!("{}: {}", fd, errno);
+            continue;
         }
         match stat.mode as usize {
             REG => {
@@ -38,7 +36,8 @@ fn main(args: &[&str]) -> i32 {
             }
             DIR => {
                 let mut buf = vec![0u8; stat.size as usize];
-                read(fd as usize, &mut buf);
+                let len = read(fd, &mut buf).unwrap_or(0);
+                buf.truncate(len);
                 for i in 2..buf.len() / DIRENT_SZ {
                     let offset = i * DIRENT_SZ;
                     let dirent = unsafe { &*(buf.as_ptr().add(offset) as *const Dirent) };
@@ -56,7 +55,7 @@ fn main(args: &[&str]) -> i32 {
             }
             _ => panic!("Unknown mode: {}", stat.mode),
         };
-        close(fd as usize);
+        close(fd).unwrap();
     }
     0
 }
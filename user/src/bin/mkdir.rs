@@ -14,14 +14,8 @@ fn main(args: &[&str]) -> i32 {
         return 1;
     }
     for target in &args[1..] {
-        match mkdir(target) {
-            0 => {}
-            -1 => println!(
-                "cannot create directory '{}': No such file or directory",
-                target
-            ),
-            -2 => println!("cannot create directory '{}': File exists", target),
-            _ => panic!(),
+        if let Err(errno) = mkdir(target) {
+            println!("cannot create directory '{}': {}", target, errno);
         }
     }
     0
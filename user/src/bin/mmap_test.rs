@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, mmap, mprotect, munmap, waitpid, wifsignaled, wtermsig, EEXIST, EINVAL, ENOMEM,
+    PROT_NONE, PROT_READ, PROT_WRITE, SIGSEGV,
+};
+
+const PAGE_SIZE: usize = 4096;
+const LEN: usize = 4 * PAGE_SIZE;
+
+fn fill(start: usize, len: usize) {
+    let buf = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, len) };
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    assert!(buf.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = mmap(0, LEN, PROT_READ | PROT_WRITE).unwrap();
+    fill(start, LEN);
+    // 与已有映射重叠
+    assert_eq!(mmap(start + PAGE_SIZE, PAGE_SIZE, PROT_READ), Err(EEXIST));
+    // 未对齐
+    assert_eq!(mmap(start + LEN + 1, PAGE_SIZE, PROT_READ), Err(EINVAL));
+    let next = mmap(start + LEN, PAGE_SIZE, PROT_READ | PROT_WRITE);
+    assert_eq!(next, Ok(start + LEN));
+    println!("mmap ok");
+
+    // 释放中间的一页后可以重新映射
+    assert_eq!(munmap(start + PAGE_SIZE, PAGE_SIZE), Ok(()));
+    assert_eq!(
+        mmap(start + PAGE_SIZE, PAGE_SIZE, PROT_READ | PROT_WRITE),
+        Ok(start + PAGE_SIZE)
+    );
+    fill(start + PAGE_SIZE, PAGE_SIZE);
+    assert_eq!(munmap(start, LEN + PAGE_SIZE), Ok(()));
+    println!("munmap ok");
+
+    let start = mmap(0, PAGE_SIZE, PROT_READ | PROT_WRITE).unwrap();
+    fill(start, PAGE_SIZE);
+    assert_eq!(mprotect(start, PAGE_SIZE, PROT_READ), Ok(()));
+    assert_eq!(mprotect(start, 2 * PAGE_SIZE, PROT_READ), Err(ENOMEM));
+    let pid = fork().unwrap();
+    if pid == 0 {
+        unsafe {
+            core::ptr::write_volatile((start + 1) as *mut u8, 0);
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid, &mut status, 0).unwrap();
+    assert!(wifsignaled(status) && wtermsig(status) == SIGSEGV);
+    assert_eq!(unsafe { *((start + 1) as *const u8) }, 1);
+    // 不可访问的页面，恢复权限后内容不变
+    assert_eq!(mprotect(start, PAGE_SIZE, PROT_NONE), Ok(()));
+    let pid = fork().unwrap();
+    if pid == 0 {
+        unsafe {
+            core::ptr::read_volatile(start as *const u8);
+        }
+        exit(0);
+    }
+    waitpid(pid, &mut status, 0).unwrap();
+    assert!(wifsignaled(status) && wtermsig(status) == SIGSEGV);
+    assert_eq!(mprotect(start, PAGE_SIZE, PROT_READ), Ok(()));
+    assert_eq!(unsafe { *((start + 1) as *const u8) }, 1);
+    let none = mmap(0, PAGE_SIZE, PROT_NONE).unwrap();
+    assert_eq!(mprotect(none, PAGE_SIZE, PROT_READ | PROT_WRITE), Ok(()));
+    fill(none, PAGE_SIZE);
+    println!("mprotect ok");
+    println!("mmap_test passed!");
+    0
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::*;
+
+#[no_mangle]
+fn main(args: &[&str]) -> i32 {
+    match args.len() {
+        // 无参数时列出挂载表
+        1 => {
+            let fd = match open("/proc/mounts", RDONLY) {
+                Ok(fd) => fd,
+                Err(errno) => {
+                    println!("mount: /proc/mounts: {}", errno);
+                    return 1;
+                }
+            };
+            let mut buf = vec![0u8; 256];
+            while let Ok(len @ 1..) = read(fd, &mut buf) {
+                print!("{}", core::str::from_utf8(&buf[..len]).unwrap());
+            }
+            close(fd).unwrap();
+            0
+        }
+        5 | 7 if args[1] == "-t" => {
+            let (fs_type, source, target) = (args[2], args[3], args[4]);
+            let options = match args[5..] {
+                [] => "",
+                ["-o", options] => options,
+                _ => {
+                    println!("usage: mount [-t <type> <source> <target> [-o <options>]]");
+                    return 1;
+                }
+            };
+            if let Err(errno) = mount(source, target, fs_type, options) {
+                println!("mount: {}: {}", target, errno);
+                return 1;
+            }
+            0
+        }
+        _ => {
+            println!("usage: mount [-t <type> <source> <target> [-o <options>]]");
+            1
+        }
+    }
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use user_lib::*;
+
+#[no_mangle]
+fn main(args: &[&str]) -> i32 {
+    // 无参数时输出当前的nice值
+    if args.len() == 1 {
+        println!("{}", getpriority(PRIO_PROCESS, 0).unwrap());
+        return 0;
+    }
+    let (adjustment, command) = match args[1] {
+        "-n" if args.len() > 3 => match args[2].parse::<isize>() {
+            Ok(adjustment) => (adjustment, &args[3..]),
+            Err(_) => {
+                println!("nice: invalid adjustment '{}'", args[2]);
+                return 1;
+            }
+        },
+        _ => (10, &args[1..]),
+    };
+    let nice = getpriority(PRIO_PROCESS, 0).unwrap() + adjustment;
+    if let Err(errno) = setpriority(PRIO_PROCESS, 0, nice) {
+        println!("nice: {}", errno);
+        return 1;
+    }
+    let errno = execvp(command[0], command);
+    println!("nice: {}: {}", command[0], errno);
+    127
+}
@@ -12,33 +12,33 @@ static STR: &str = "Hello, world!";
 pub fn main() -> i32 {
     // create pipe
     let mut pipe_fd = [0usize; 2];
-    pipe(&mut pipe_fd);
+    pipe(&mut pipe_fd).unwrap();
     // read end
     assert_eq!(pipe_fd[0], 3);
     // write end
     assert_eq!(pipe_fd[1], 4);
     println!("begin to fork");
 
-    if fork() == 0 {
+    if fork().unwrap() == 0 {
         // child process, read from parent
         // close write_end
-        close(pipe_fd[1]);
+        close(pipe_fd[1]).unwrap();
         let mut buffer = [0u8; 32];
-        let len_read = read(3, &mut buffer) as usize;
+        let len_read = read(3, &mut buffer).unwrap();
         // close read_end
-        close(3);
+        close(3).unwrap();
         assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
         println!("Read OK, child process exited!");
         0
     } else {
         // parent process, write to child
         // close read end
-        close(pipe_fd[0]);
-        assert_eq!(write(4, STR.as_bytes()), STR.len() as isize);
+        close(pipe_fd[0]).unwrap();
+        assert_eq!(write(4, STR.as_bytes()), Ok(STR.len()));
         // close write end
-        close(pipe_fd[1]);
+        close(pipe_fd[1]).unwrap();
         let mut child_exit_code: i32 = 0;
-        wait(&mut child_exit_code);
+        wait(&mut child_exit_code).unwrap();
         assert_eq!(child_exit_code, 0);
         println!("pipetest passed!");
         0
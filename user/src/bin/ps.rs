@@ -4,54 +4,86 @@
 extern crate alloc;
 extern crate user_lib;
 
-use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
-use alloc::{string::String, vec};
-use core::iter::Iterator;
 use core::str;
 use user_lib::*;
 
 #[no_mangle]
 fn main() -> i32 {
-    let procs_fd = open("/proc", RDONLY);
-    let mut stat = Stat::new();
-    fstat(procs_fd as usize, &mut stat);
-    let mut buf = vec![0u8; stat.size as usize];
-    read(procs_fd as usize, &mut buf);
-    close(procs_fd as usize);
-    let procs_info = (2..buf.len() / DIRENT_SZ)
-        .into_iter()
+    let buf = match read_file("/proc") {
+        Ok(buf) => buf,
+        Err(errno) => {
+            println!("ps: /proc: {}", errno);
+            return 1;
+        }
+    };
+    println!("PID\tPPID\tPGID\tSTAT\tTIME\tMEM\tCMD");
+    for pid in dir_entries(&buf) {
+        // 进程可能在读取期间退出
+        let status = match read_file(&format!("/proc/{}/status", pid)) {
+            Ok(buf) => String::from_utf8(buf).unwrap(),
+            Err(_) => continue,
+        };
+        let field = |key: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+                .map_or("", str::trim)
+        };
+        let ms = |key: &str| {
+            field(key)
+                .trim_end_matches(" ms")
+                .parse::<usize>()
+                .unwrap_or(0)
+        };
+        let time = ms("Utime") + ms("Stime");
+        let cmdline = read_file(&format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+        let cmd = str::from_utf8(&cmdline)
+            .unwrap()
+            .split_terminator('\0')
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "{}\t{}\t{}\t{}\t{}.{:02}\t{}\t{}",
+            pid,
+            field("PPid"),
+            field("Pgid"),
+            field("State"),
+            time / 1000,
+            time % 1000 / 10,
+            field("VmSize"),
+            cmd
+        );
+    }
+    0
+}
+
+/// 目录中除"."与".."外的全部文件名
+fn dir_entries(buf: &[u8]) -> Vec<&str> {
+    (2..buf.len() / DIRENT_SZ)
         .map(|i| {
-            let offset = i * DIRENT_SZ;
-            let dirent = unsafe { &*(buf.as_ptr().add(offset) as *const Dirent) };
+            let dirent = unsafe { &*(buf.as_ptr().add(i * DIRENT_SZ) as *const Dirent) };
             let len = dirent
                 .name
                 .iter()
                 .position(|&v| v == 0)
                 .unwrap_or(dirent.name.len());
-            let pid = str::from_utf8(&dirent.name[0..len]).unwrap().to_owned();
-            let mem_fd = open(&(String::from("/proc/") + &pid + "/mem"), RDONLY);
-            let mut stat = Stat::new();
-            fstat(mem_fd as usize, &mut stat);
-            let mut buf = vec![0u8; stat.size as usize];
-            read(mem_fd as usize, &mut buf);
-            close(mem_fd as usize);
-            let mem = str::from_utf8(&buf).unwrap().to_owned();
-
-            let cmd_fd = open(&(String::from("/proc/") + &pid + "/cmd"), RDONLY);
-            let mut stat = Stat::new();
-            fstat(cmd_fd as usize, &mut stat);
-            let mut buf = vec![0u8; stat.size as usize];
-            read(cmd_fd as usize, &mut buf);
-            close(cmd_fd as usize);
-            let cmd = str::from_utf8(&buf).unwrap().to_owned();
-
-            (pid, mem, cmd)
+            str::from_utf8(&dirent.name[..len]).unwrap()
         })
-        .collect::<Vec<_>>();
-    println!("PID\tMEM\t\tCMD");
-    procs_info.into_iter().for_each(|(pid, mem, cmd)| {
-        println!("{}\t{}\t\t{}", pid, mem, cmd);
-    });
-    0
+        .collect()
+}
+
+/// 读取文件的全部内容
+fn read_file(path: &str) -> SysResult<Vec<u8>> {
+    let fd = open(path, RDONLY)?;
+    let mut stat = Stat::new();
+    fstat(fd, &mut stat)?;
+    let mut buf = vec![0u8; stat.size as usize];
+    let len = read(fd, &mut buf)?;
+    buf.truncate(len);
+    close(fd)?;
+    Ok(buf)
 }
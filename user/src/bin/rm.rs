@@ -14,11 +14,8 @@ fn main(args: &[&str]) -> i32 {
         return 1;
     }
     for target in &args[1..] {
-        match unlink(target, 0) {
-            0 => {}
-            -1 => println!("cannot remove '{}': No such file or directory", target),
-            -2 => println!("cannot remove '{}': Is a directory", target),
-            _ => panic!(),
+        if let Err(errno) = unlink(target, 0) {
+            println!("cannot remove '{}': {}", target, errno);
         }
     }
     0
@@ -14,12 +14,8 @@ fn main(args: &[&str]) -> i32 {
         return 1;
     }
     for target in &args[1..] {
-        match unlink(target, AT_REMOVEDIR) {
-            0 => {}
-            -1 => println!("failed to remove '{}': No such file or directory", target),
-            -2 => println!("failed to remove '{}': Not a directory", target),
-            -3 => println!("failed to remove '{}': Directory not empty", target),
-            _ => panic!(),
+        if let Err(errno) = unlink(target, AT_REMOVEDIR) {
+            println!("failed to remove '{}': {}", target, errno);
         }
     }
     0
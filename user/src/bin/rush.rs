@@ -10,26 +10,75 @@ use core::str;
 use user_lib::console::get_line;
 use user_lib::*;
 
+/// 由控制台产生、shell自身需要忽略的信号
+const JOB_CONTROL_SIGNALS: [usize; 3] = [SIGINT, SIGQUIT, SIGTSTP];
+
+/// 作业（以进程组为单位）
+struct Job {
+    id: usize,
+    pgid: usize,
+    cmd: String,
+    stopped: bool,
+}
+
+impl Job {
+    fn status(&self) -> &str {
+        if self.stopped {
+            "Stopped"
+        } else {
+            "Running"
+        }
+    }
+}
+
 #[no_mangle]
 fn main() -> i32 {
-    let mut cwd = String::new();
-    let mut ret_code = 0;
-    let mut backgroud_pids = Vec::new();
-    getcwd(&mut cwd);
+    let mut cwd = getcwd().unwrap();
+    let mut jobs = Vec::new();
+    setenv("PATH", "/bin", false).unwrap();
+    // 自成一个会话并占用控制台
+    setsid().ok();
+    tcsetpgrp(0, getpid() as usize).unwrap();
+    for &signum in JOB_CONTROL_SIGNALS.iter() {
+        sigaction(signum, Some(&SignalAction::ignore()), None).unwrap();
+    }
     loop {
-        for i in (0..backgroud_pids.len()).rev() {
-            let ret = waitpid(backgroud_pids[i], &mut ret_code, true);
-            if ret > 0 {
-                println!("[{}] Done", backgroud_pids.len());
-                backgroud_pids.remove(i);
-            }
-        }
+        update_jobs(&mut jobs);
         print!("root@rusted_os:{}# ", cwd);
         let input = get_line();
         let mut args = input.split_ascii_whitespace().collect::<Vec<_>>();
         if !args.is_empty() {
             match args[0] {
                 "cd" => cd(&mut cwd, &args),
+                "export" => export(&args),
+                "unset" => {
+                    for name in &args[1..] {
+                        if let Err(errno) = unsetenv(name) {
+                            println!("unset: '{}': {}", name, errno);
+                        }
+                    }
+                }
+                "jobs" => {
+                    for job in jobs.iter() {
+                        println!("[{}]  {}\t{}", job.id, job.status(), job.cmd);
+                    }
+                }
+                "fg" => {
+                    if let Some(idx) = find_job(&jobs, &args) {
+                        let job = jobs.remove(idx);
+                        println!("{}", job.cmd);
+                        killpg(job.pgid, SIGCONT).ok();
+                        wait_foreground(&mut jobs, job);
+                    }
+                }
+                "bg" => {
+                    if let Some(idx) = find_job(&jobs, &args) {
+                        let job = &mut jobs[idx];
+                        job.stopped = false;
+                        println!("[{}] {} &", job.id, job.cmd);
+                        killpg(job.pgid, SIGCONT).ok();
+                    }
+                }
                 "exit" => break,
                 _ => {
                     // 判断是否后台运行
@@ -39,16 +88,36 @@ fn main() -> i32 {
                     } else {
                         false
                     };
-                    let pid = fork();
+                    if args.is_empty() {
+                        continue;
+                    }
+                    let cmd = args.join(" ");
+                    let pid = fork().unwrap();
                     if pid == 0 {
+                        // 作业自成一个进程组，前台作业在exec前就占用控制台
+                        setpgid(0, 0).unwrap();
+                        if !background {
+                            tcsetpgrp(0, getpid() as usize).unwrap();
+                        }
+                        for &signum in JOB_CONTROL_SIGNALS.iter() {
+                            sigaction(signum, Some(&SignalAction::default()), None).unwrap();
+                        }
                         execute_cmd(args);
+                        exit(1);
+                    }
+                    // 父子进程都设置进程组，避免依赖调度顺序
+                    setpgid(pid, pid).ok();
+                    let job = Job {
+                        id: next_job_id(&jobs),
+                        pgid: pid,
+                        cmd,
+                        stopped: false,
+                    };
+                    if background {
+                        println!("[{}] {}", job.id, pid);
+                        jobs.push(job);
                     } else {
-                        if background {
-                            backgroud_pids.push(pid as usize);
-                            println!("[{}] {}", backgroud_pids.len(), pid);
-                        } else {
-                            waitpid(pid as usize, &mut ret_code, false);
-                        }
+                        wait_foreground(&mut jobs, job);
                     }
                 }
             }
@@ -57,6 +126,66 @@ fn main() -> i32 {
     0
 }
 
+/// 最小的未被占用的作业号
+fn next_job_id(jobs: &[Job]) -> usize {
+    (1..)
+        .find(|id| jobs.iter().all(|job| job.id != *id))
+        .unwrap()
+}
+
+/// 根据fg/bg的参数（%n或n，缺省为最近的作业）查找作业
+fn find_job(jobs: &[Job], args: &[&str]) -> Option<usize> {
+    let idx = match args.get(1) {
+        None => jobs.len().checked_sub(1),
+        Some(arg) => {
+            let id = arg.strip_prefix('%').unwrap_or(arg).parse::<usize>();
+            id.ok()
+                .and_then(|id| jobs.iter().position(|job| job.id == id))
+        }
+    };
+    if idx.is_none() {
+        println!("{}: no such job", args[0]);
+    }
+    idx
+}
+
+/// 前台运行作业直到其结束或暂停，期间由作业占用控制台
+fn wait_foreground(jobs: &mut Vec<Job>, mut job: Job) {
+    tcsetpgrp(0, job.pgid).ok();
+    let mut status = 0;
+    let ret = waitpid(job.pgid, &mut status, WUNTRACED);
+    tcsetpgrp(0, getpid() as usize).unwrap();
+    if ret.is_ok() && wifstopped(status) {
+        job.stopped = true;
+        println!("\n[{}]+  Stopped\t{}", job.id, job.cmd);
+        jobs.push(job);
+    } else if ret.is_ok() && wifsignaled(status) && wtermsig(status) == SIGINT {
+        println!("");
+    }
+}
+
+/// 回收已结束的后台作业，并记录被暂停的作业
+fn update_jobs(jobs: &mut Vec<Job>) {
+    let mut status = 0;
+    for i in (0..jobs.len()).rev() {
+        let job = &mut jobs[i];
+        match waitpid(job.pgid, &mut status, WNOHANG | WUNTRACED) {
+            Ok(pid) if pid > 0 && wifstopped(status) => {
+                job.stopped = true;
+                println!("[{}]+  Stopped\t{}", job.id, job.cmd);
+            }
+            Ok(pid) if pid > 0 => {
+                println!("[{}]+  Done\t{}", job.id, job.cmd);
+                jobs.remove(i);
+            }
+            Ok(_) => {}
+            Err(_) => {
+                jobs.remove(i);
+            }
+        }
+    }
+}
+
 fn cd(cwd: &mut String, args: &Vec<&str>) {
     let path = match args.len() {
         1 => String::from("/"),
@@ -67,13 +196,28 @@ fn cd(cwd: &mut String, args: &Vec<&str>) {
         }
     };
 
-    match chdir(&path) {
-        0 => {}
-        -1 => println!("{}: No such file or directory", args[1]),
-        -2 => println!("{}: Not a directory", args[1]),
-        _ => panic!(),
+    if let Err(errno) = chdir(&path) {
+        println!("cd: {}: {}", path, errno);
+    }
+    *cwd = getcwd().unwrap();
+}
+
+/// 设置环境变量，子进程在exec时继承
+fn export(args: &Vec<&str>) {
+    if args.len() == 1 {
+        for env in environ() {
+            println!("export {}", env);
+        }
+        return;
+    }
+    for arg in &args[1..] {
+        // 仅给出变量名时没有可导出的值
+        if let Some((name, value)) = arg.split_once('=') {
+            if setenv(name, value, true).is_err() {
+                println!("export: '{}': not a valid identifier", arg);
+            }
+        }
     }
-    getcwd(cwd);
 }
 
 fn execute_cmd(mut args: Vec<&str>) {
@@ -84,16 +228,16 @@ fn execute_cmd(mut args: Vec<&str>) {
     if splited.len() == 2 {
         args = splited[0].clone();
         let mut pipe_fds = [0; 2];
-        pipe(&mut pipe_fds);
-        if fork() == 0 {
-            dup2(pipe_fds[1], 1);
-            close(pipe_fds[0]);
-            close(pipe_fds[1]);
+        pipe(&mut pipe_fds).unwrap();
+        if fork().unwrap() == 0 {
+            dup2(pipe_fds[1], 1).unwrap();
+            close(pipe_fds[0]).unwrap();
+            close(pipe_fds[1]).unwrap();
             execute_cmd(splited[1].clone());
         } else {
-            dup2(pipe_fds[0], 0);
-            close(pipe_fds[0]);
-            close(pipe_fds[1]);
+            dup2(pipe_fds[0], 0).unwrap();
+            close(pipe_fds[0]).unwrap();
+            close(pipe_fds[1]).unwrap();
             // wait(&mut ret_code);
         }
     }
@@ -103,12 +247,14 @@ fn execute_cmd(mut args: Vec<&str>) {
             println!("syntax error");
             return;
         }
-        let fd = open(args[input_pos + 1], RDONLY);
-        if fd == -1 {
-            println!("'{}': No such file or directory", args[input_pos + 1]);
-            return;
-        }
-        dup2(fd as usize, 0);
+        let fd = match open(args[input_pos + 1], RDONLY) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                println!("'{}': {}", args[input_pos + 1], errno);
+                return;
+            }
+        };
+        dup2(fd, 0).unwrap();
         args.drain(input_pos..=input_pos + 1);
     }
     // 输出重定向
@@ -117,14 +263,18 @@ fn execute_cmd(mut args: Vec<&str>) {
             println!("syntax error");
             return;
         }
-        let fd = open(args[output_pos + 1], WRONLY | CREATE);
-        if fd == -1 {
-            println!("'{}': No such file or directory", args[output_pos + 1]);
-            return;
-        }
-        dup2(fd as usize, 1);
+        let fd = match open(args[output_pos + 1], WRONLY | CREATE) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                println!("'{}': {}", args[output_pos + 1], errno);
+                return;
+            }
+        };
+        dup2(fd, 1).unwrap();
         args.drain(output_pos..=output_pos + 1);
     }
-    exec(&(String::from("/bin/") + args[0]), &args);
-    println!("{}: command not found", args[0]);
+    match execvp(args[0], &args) {
+        ENOENT => println!("{}: command not found", args[0]),
+        errno => println!("{}: {}", args[0], errno),
+    }
 }
@@ -14,21 +14,19 @@ fn main(args: &[&str]) -> i32 {
         return 1;
     }
     for target in &args[1..] {
-        let fd = open(target, RDONLY);
-        let mut stat = Stat::new();
-        if fd == -1 {
-            println!("cannot stat '{}': No such file or directory", target);
-            continue;
-        }
-        match fstat(fd as usize, &mut stat) {
-            0 => {}
-            -1 => {
-                println!("{}: Bad file descriptor", fd);
+        let fd = match open(target, RDONLY) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                println!("cannot stat '{}': {}", target, errno);
                 continue;
             }
-            _ => panic!(),
+        };
+        let mut stat = Stat::new();
+        if let Err(errno) = fstat(fd, &mut stat) {
+            println!("{}: {}", fd, errno);
+            continue;
         }
-        close(fd as usize);
+        close(fd).unwrap();
         let file_type = match stat.mode as usize {
             CHR => "character special file",
             REG => "regular file",
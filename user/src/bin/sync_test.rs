@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, exit, mutex_create, mutex_lock, mutex_unlock,
+    r#yield, semaphore_create, semaphore_down, semaphore_up, thread_create, waittid, EPERM,
+};
+
+const THREAD_NUM: usize = 4;
+const PER_THREAD: usize = 1000;
+
+static mut COUNTER: usize = 0;
+static mut READY: bool = false;
+static mut MUTEX_ID: usize = 0;
+static mut SEM_ID: usize = 0;
+static mut CONDVAR_ID: usize = 0;
+
+fn adder(_arg: usize) -> ! {
+    for _ in 0..PER_THREAD {
+        unsafe {
+            mutex_lock(MUTEX_ID).unwrap();
+            let value = COUNTER;
+            // 给其他线程在临界区内被调度的机会
+            r#yield();
+            COUNTER = value + 1;
+            mutex_unlock(MUTEX_ID).unwrap();
+        }
+    }
+    exit(0)
+}
+
+/// 解锁其他线程持有的锁
+fn intruder(_arg: usize) -> ! {
+    unsafe {
+        assert_eq!(mutex_unlock(MUTEX_ID), Err(EPERM));
+    }
+    exit(0)
+}
+
+fn producer(_arg: usize) -> ! {
+    unsafe {
+        mutex_lock(MUTEX_ID).unwrap();
+        READY = true;
+        condvar_signal(CONDVAR_ID).unwrap();
+        mutex_unlock(MUTEX_ID).unwrap();
+        semaphore_up(SEM_ID).unwrap();
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        MUTEX_ID = mutex_create().unwrap();
+        SEM_ID = semaphore_create(0).unwrap();
+        CONDVAR_ID = condvar_create().unwrap();
+    }
+    let tids = (0..THREAD_NUM)
+        .map(|_| thread_create(adder as usize, 0).unwrap())
+        .collect::<Vec<_>>();
+    for tid in tids {
+        waittid(tid).unwrap();
+    }
+    assert_eq!(unsafe { COUNTER }, THREAD_NUM * PER_THREAD);
+    unsafe {
+        mutex_lock(MUTEX_ID).unwrap();
+        waittid(thread_create(intruder as usize, 0).unwrap()).unwrap();
+        mutex_unlock(MUTEX_ID).unwrap();
+        assert_eq!(mutex_unlock(MUTEX_ID), Err(EPERM));
+    }
+    println!("mutex ok");
+
+    let tid = thread_create(producer as usize, 0).unwrap();
+    unsafe {
+        mutex_lock(MUTEX_ID).unwrap();
+        while !READY {
+            condvar_wait(CONDVAR_ID, MUTEX_ID).unwrap();
+        }
+        mutex_unlock(MUTEX_ID).unwrap();
+        semaphore_down(SEM_ID).unwrap();
+    }
+    waittid(tid).unwrap();
+    println!("condvar and semaphore ok");
+    println!("sync_test passed!");
+    0
+}
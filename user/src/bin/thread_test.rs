@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{exit, gettid, thread_create, waittid};
+
+static mut COUNTER: [usize; 3] = [0; 3];
+
+fn worker(idx: usize) -> ! {
+    for _ in 0..1000 {
+        unsafe {
+            COUNTER[idx] += 1;
+        }
+    }
+    println!("thread {} (tid {}) done", idx, gettid());
+    exit(idx as i32)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let tids = (0..3)
+        .map(|idx| thread_create(worker as usize, idx).unwrap())
+        .collect::<Vec<_>>();
+    for (idx, &tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(tid), Ok(idx as i32));
+        assert_eq!(unsafe { COUNTER[idx] }, 1000);
+    }
+    println!("thread_test passed!");
+    0
+}
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use user_lib::*;
+
+/// 以秒为单位输出时间，保留三位小数
+fn print_time(name: &str, time: TimeVal) {
+    println!("{}\t{}.{:03}s", name, time.sec, time.usec / 1000);
+}
+
+#[no_mangle]
+fn main(args: &[&str]) -> i32 {
+    if args.len() < 2 {
+        println!("Usage: time COMMAND [ARG]...");
+        return 1;
+    }
+    let start = gettime();
+    let pid = fork().unwrap();
+    if pid == 0 {
+        let errno = execvp(args[1], &args[1..]);
+        println!("time: {}: {}", args[1], errno);
+        exit(127);
+    }
+    let mut status = 0;
+    waitpid(pid, &mut status, 0).unwrap();
+    let real = gettime() - start;
+    let mut usage = Rusage::default();
+    getrusage(RUSAGE_CHILDREN, &mut usage).unwrap();
+    println!("");
+    print_time(
+        "real",
+        TimeVal {
+            sec: real / 1000,
+            usec: real % 1000 * 1000,
+        },
+    );
+    print_time("user", usage.utime);
+    print_time("sys", usage.stime);
+    if wifexited(status) {
+        wexitstatus(status)
+    } else {
+        128 + wtermsig(status) as i32
+    }
+}
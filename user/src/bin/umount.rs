@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use user_lib::*;
+
+#[no_mangle]
+fn main(args: &[&str]) -> i32 {
+    if args.len() == 1 {
+        println!("missing operand");
+        return 1;
+    }
+    for target in &args[1..] {
+        if let Err(errno) = umount(target) {
+            println!("umount: {}: {}", target, errno);
+        }
+    }
+    0
+}
@@ -24,6 +24,7 @@ fn main(args: &[&str]) -> i32 {
         text.push_str(&s);
     }
     exec_args.append(&mut text.split_ascii_whitespace().collect::<Vec<_>>());
-    exec(&(String::from("/bin/") + exec_args[0]), &exec_args);
-    return 0;
+    let errno = execvp(exec_args[0], &exec_args);
+    println!("xargs: {}: {}", exec_args[0], errno);
+    return 127;
 }
@@ -9,7 +9,7 @@ const STDOUT: usize = 1;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes());
+        write(STDOUT, s.as_bytes()).map_err(|_| fmt::Error)?;
         Ok(())
     }
 }
@@ -35,11 +35,10 @@ macro_rules! println {
 pub fn get_char() -> char {
     let mut c = [0u8; 1];
     loop {
-        let len = read(STDIN, &mut c);
-        match len {
-            0 => return EOT,
-            -1 => continue,
-            _ => break,
+        match read(STDIN, &mut c) {
+            Ok(0) => return EOT,
+            Ok(_) => break,
+            Err(_) => continue,
         }
     }
     c[0] as char
@@ -1,6 +1,7 @@
 //! BuddySystem堆内存分配器
 
 use super::linked_list::LinkedList;
+use crate::sys_call::sys_brk;
 use crate::uninit_cell::UninitCell;
 use alloc::alloc::{GlobalAlloc, Layout};
 use core::cell::RefCell;
@@ -111,12 +112,35 @@ fn get_size(layout: Layout) -> usize {
     )
 }
 
+/// 每次扩展堆的最小字节数
+const HEAP_GROW_SIZE: usize = 4096 * 4;
+
 /// 由于GlobalAlloc的限制导致无法使用mut方法，使用RefCell包装
 pub struct HeapAllocator(pub RefCell<BuddySystemAllocator<32>>);
 
+impl HeapAllocator {
+    /// 通过brk扩展堆空间，使其能够分配layout大小的内存块
+    fn grow(&self, layout: Layout) -> bool {
+        let size = max(get_size(layout), HEAP_GROW_SIZE);
+        let old_brk = sys_brk(0) as usize;
+        // 新增空间中需包含一个按size对齐的完整内存块
+        let new_brk = ((old_brk + size - 1) & !(size - 1)) + size;
+        if sys_brk(new_brk) as usize != new_brk {
+            return false;
+        }
+        self.0.borrow_mut().add(old_brk, new_brk);
+        true
+    }
+}
+
 unsafe impl GlobalAlloc for UninitCell<HeapAllocator> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.0.borrow_mut().alloc(layout)
+        let ptr = self.0.borrow_mut().alloc(layout);
+        if ptr.is_null() && self.grow(layout) {
+            self.0.borrow_mut().alloc(layout)
+        } else {
+            ptr
+        }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
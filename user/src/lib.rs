@@ -7,11 +7,13 @@ extern crate alloc;
 
 #[macro_use]
 pub mod console;
+pub mod errno;
 mod heap;
 mod panic;
 mod sys_call;
 mod uninit_cell;
 
+pub use crate::errno::*;
 use crate::heap::heap_allocator::*;
 use crate::uninit_cell::UninitCell;
 use alloc::alloc::Layout;
@@ -24,11 +26,7 @@ use core::mem::size_of;
 use core::str;
 use sys_call::*;
 
-const USER_HEAP_SIZE: usize = 4096;
-
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
-/// 全局堆内存分配器
-
+/// 全局堆内存分配器（堆空间通过brk按需扩展）
 #[global_allocator]
 pub static mut HEAP_ALLOCATOR: UninitCell<HeapAllocator> = UninitCell::uninit();
 
@@ -38,29 +36,41 @@ fn alloc_error_handler(layout: Layout) -> ! {
     panic!("Allocation error: {:?}", layout);
 }
 
+/// 当前进程的环境变量（NAME=VALUE形式）
+static mut ENVIRON: Vec<String> = Vec::new();
+
+/// 读取以空指针结尾的字符串指针数组
+unsafe fn read_str_array(ptrs: usize) -> Vec<&'static str> {
+    let mut v = Vec::new();
+    if ptrs == 0 {
+        return v;
+    }
+    for i in 0.. {
+        let str_start = ((ptrs + i * size_of::<usize>()) as *const usize).read_volatile();
+        if str_start == 0 {
+            break;
+        }
+        let len = (0usize..)
+            .find(|i| ((str_start + *i) as *const u8).read_volatile() == 0)
+            .unwrap();
+        v.push(str::from_utf8(core::slice::from_raw_parts(str_start as *const u8, len)).unwrap());
+    }
+    v
+}
+
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+pub extern "C" fn _start(_argc: usize, argv: usize, envp: usize) -> ! {
     unsafe {
         HEAP_ALLOCATOR = UninitCell::init(HeapAllocator(RefCell::new(
-            BuddySystemAllocator::<32>::new(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE),
+            BuddySystemAllocator::<32>::new(sys_brk(0) as usize, 0),
         )));
     }
-    let mut v: Vec<&'static str> = Vec::new();
-    for i in 0..argc {
-        let str_start =
-            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
-        let len = (0usize..)
-            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
-            .unwrap();
-        v.push(
-            core::str::from_utf8(unsafe {
-                core::slice::from_raw_parts(str_start as *const u8, len)
-            })
-            .unwrap(),
-        );
+    let args = unsafe { read_str_array(argv) };
+    unsafe {
+        ENVIRON = read_str_array(envp).into_iter().map(String::from).collect();
     }
-    exit(main(&v))
+    exit(main(&args))
 }
 
 #[linkage = "weak"]
@@ -75,12 +85,12 @@ pub const RDWR: u32 = 1 << 1;
 pub const CREATE: u32 = 1 << 9;
 pub const TRUNC: u32 = 1 << 10;
 
-pub fn read(fd: usize, buf: &mut [u8]) -> isize {
-    sys_read(fd, buf)
+pub fn read(fd: usize, buf: &mut [u8]) -> SysResult<usize> {
+    check(sys_read(fd, buf))
 }
 
-pub fn write(fd: usize, buf: &[u8]) -> isize {
-    sys_write(fd, buf)
+pub fn write(fd: usize, buf: &[u8]) -> SysResult<usize> {
+    check(sys_write(fd, buf))
 }
 
 pub fn exit(exit_code: i32) -> ! {
@@ -96,51 +106,91 @@ pub fn gettime() -> usize {
     sys_gettime() as usize
 }
 
-pub fn getcwd(s: &mut String) -> isize {
+/// 与内核中定义一致的时间结构
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// 睡眠，被信号打断时返回EINTR并在rem中写入剩余时间
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> SysResult<()> {
+    let rem = rem.map_or(core::ptr::null_mut(), |rem| rem as *mut _ as *mut _);
+    check(sys_nanosleep(req as *const _ as *const _, rem))?;
+    Ok(())
+}
+
+pub fn getcwd() -> SysResult<String> {
     let mut buffer = vec![0u8; 128];
-    let len = sys_getcwd(&mut buffer);
-    *s = str::from_utf8(&buffer[0..len as usize])
-        .unwrap()
-        .to_string();
-    len
+    let len = check(sys_getcwd(&mut buffer))?;
+    Ok(str::from_utf8(&buffer[0..len]).unwrap().to_string())
 }
 
-pub fn chdir(path: &str) -> isize {
+pub fn chdir(path: &str) -> SysResult<()> {
     let path = String::from(path) + "\0";
-    sys_chdir(path.as_ptr())
+    check(sys_chdir(path.as_ptr()))?;
+    Ok(())
 }
 
-pub fn mkdir(path: &str) -> isize {
+pub fn mkdir(path: &str) -> SysResult<()> {
     let path = String::from(path) + "\0";
-    sys_mkdir(path.as_ptr())
+    check(sys_mkdir(path.as_ptr()))?;
+    Ok(())
 }
 
-pub fn open(path: &str, flags: u32) -> isize {
+pub fn open(path: &str, flags: u32) -> SysResult<usize> {
     let path = String::from(path) + "\0";
-    sys_open(path.as_ptr(), flags)
+    check(sys_open(path.as_ptr(), flags))
 }
 
-pub fn close(fd: usize) -> isize {
-    sys_close(fd)
+pub fn close(fd: usize) -> SysResult<()> {
+    check(sys_close(fd))?;
+    Ok(())
 }
 
-pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+pub fn pipe(pipe_fd: &mut [usize]) -> SysResult<()> {
+    check(sys_pipe(pipe_fd))?;
+    Ok(())
 }
 
 pub const SEEK_SET: u32 = 0;
 pub const SEEK_CUR: u32 = 1;
 pub const SEEK_END: u32 = 2;
 
-pub fn lseek(fd: usize, offset: isize, whence: u32) -> isize {
-    sys_lseek(fd, offset, whence)
+pub fn lseek(fd: usize, offset: isize, whence: u32) -> SysResult<usize> {
+    check(sys_lseek(fd, offset, whence))
 }
 
 pub const AT_REMOVEDIR: u32 = 1;
 
-pub fn unlink(path: &str, flags: u32) -> isize {
+pub fn unlink(path: &str, flags: u32) -> SysResult<()> {
     let path = String::from(path) + "\0";
-    sys_unlink(path.as_ptr(), flags)
+    check(sys_unlink(path.as_ptr(), flags))?;
+    Ok(())
+}
+
+/// 将fs_type类型的文件系统挂载到target目录，options为逗号分隔的挂载选项
+pub fn mount(source: &str, target: &str, fs_type: &str, options: &str) -> SysResult<()> {
+    let source = String::from(source) + "\0";
+    let target = String::from(target) + "\0";
+    let fs_type = String::from(fs_type) + "\0";
+    let options = String::from(options) + "\0";
+    check(sys_mount(
+        source.as_ptr(),
+        target.as_ptr(),
+        fs_type.as_ptr(),
+        0,
+        options.as_ptr(),
+    ))?;
+    Ok(())
+}
+
+/// 卸载target目录上的文件系统
+pub fn umount(target: &str) -> SysResult<()> {
+    let target = String::from(target) + "\0";
+    check(sys_umount(target.as_ptr(), 0))?;
+    Ok(())
 }
 
 pub const CHR: usize = 0;
@@ -165,8 +215,9 @@ impl Stat {
     }
 }
 
-pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
-    sys_fstat(fd, stat as *mut _ as *mut _)
+pub fn fstat(fd: usize, stat: &mut Stat) -> SysResult<()> {
+    check(sys_fstat(fd, stat as *mut _ as *mut _))?;
+    Ok(())
 }
 
 pub const NAME_LENGTH_LIMIT: usize = 27;
@@ -178,67 +229,495 @@ pub struct Dirent {
 
 pub const DIRENT_SZ: usize = size_of::<Dirent>();
 
-pub fn fork() -> isize {
-    sys_fork()
+/// 创建子进程，在子进程中返回0
+pub fn fork() -> SysResult<usize> {
+    check(sys_fork())
 }
 
-pub fn exec(path: &str, args: &[&str]) -> isize {
+/// 以当前进程的环境变量执行程序，仅在失败时返回
+pub fn exec(path: &str, args: &[&str]) -> Errno {
+    let envs = environ();
+    let envs = envs.iter().map(|env| env.as_str()).collect::<Vec<_>>();
+    execve(path, args, &envs)
+}
+
+/// 以指定的环境变量（NAME=VALUE形式）执行程序，仅在失败时返回
+pub fn execve(path: &str, args: &[&str], envs: &[&str]) -> Errno {
     let path = String::from(path) + "\0";
-    let args = args
-        .iter()
-        .map(|&arg| String::from(arg) + "\0")
-        .collect::<Vec<_>>();
-    let mut arg_ptrs = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
-    arg_ptrs.push(0 as *const _);
-    sys_exec(path.as_ptr(), arg_ptrs.as_ptr())
+    let to_c_strs = |strs: &[&str]| {
+        strs.iter()
+            .map(|&s| String::from(s) + "\0")
+            .collect::<Vec<_>>()
+    };
+    let to_ptrs = |strs: &Vec<String>| {
+        let mut ptrs = strs.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+        ptrs.push(0 as *const _);
+        ptrs
+    };
+    let args = to_c_strs(args);
+    let envs = to_c_strs(envs);
+    let ret = sys_exec(
+        path.as_ptr(),
+        to_ptrs(&args).as_ptr(),
+        to_ptrs(&envs).as_ptr(),
+    );
+    Errno(-ret)
 }
 
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _ as *mut _) {
-            -2 => {
-                sys_yield();
-            }
-            pid => return pid,
+/// 在PATH中查找并执行程序（程序名包含'/'时直接执行），仅在失败时返回
+pub fn execvp(file: &str, args: &[&str]) -> Errno {
+    if file.contains('/') {
+        return exec(file, args);
+    }
+    let path = getenv("PATH").unwrap_or_else(|| String::from("/bin"));
+    let mut errno = ENOENT;
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        // 找到但无法执行的程序优先报告
+        match exec(&(String::from(dir) + "/" + file), args) {
+            ENOENT => {}
+            err => errno = err,
         }
     }
+    errno
 }
 
-pub fn waitpid(pid: usize, exit_code: &mut i32, whnohang: bool) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _ as *mut _) {
-            -2 => {
-                if whnohang {
-                    return -2;
-                } else {
-                    sys_yield();
-                }
-            }
-            pid => return pid,
+/// 当前进程的全部环境变量（NAME=VALUE形式）
+pub fn environ() -> Vec<String> {
+    unsafe { ENVIRON.clone() }
+}
+
+/// 环境变量name在ENVIRON中的下标
+fn env_index(name: &str) -> Option<usize> {
+    unsafe {
+        ENVIRON
+            .iter()
+            .position(|env| env.split_once('=').map_or(false, |(key, _)| key == name))
+    }
+}
+
+/// 获取环境变量的值
+pub fn getenv(name: &str) -> Option<String> {
+    let index = env_index(name)?;
+    unsafe {
+        ENVIRON[index]
+            .split_once('=')
+            .map(|(_, value)| String::from(value))
+    }
+}
+
+/// 设置环境变量，overwrite为false时不覆盖已有的值
+pub fn setenv(name: &str, value: &str, overwrite: bool) -> SysResult<()> {
+    if name.is_empty() || name.contains('=') {
+        return Err(EINVAL);
+    }
+    let env = String::from(name) + "=" + value;
+    unsafe {
+        match env_index(name) {
+            Some(index) if overwrite => ENVIRON[index] = env,
+            Some(_) => {}
+            None => ENVIRON.push(env),
+        }
+    }
+    Ok(())
+}
+
+/// 删除环境变量
+pub fn unsetenv(name: &str) -> SysResult<()> {
+    if name.is_empty() || name.contains('=') {
+        return Err(EINVAL);
+    }
+    if let Some(index) = env_index(name) {
+        unsafe {
+            ENVIRON.remove(index);
         }
     }
+    Ok(())
+}
+
+/// 调整堆顶，返回调整后的堆顶（失败时为原堆顶）
+pub fn brk(addr: usize) -> isize {
+    sys_brk(addr)
+}
+
+/// 将堆顶移动increment字节，返回原堆顶
+pub fn sbrk(increment: isize) -> SysResult<usize> {
+    let old_brk = sys_brk(0);
+    let new_brk = old_brk + increment;
+    if sys_brk(new_brk as usize) == new_brk {
+        Ok(old_brk as usize)
+    } else {
+        Err(ENOMEM)
+    }
+}
+
+pub const PROT_NONE: usize = 0;
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+/// 映射匿名内存，start为0时由内核选择地址，返回映射的起始地址
+pub fn mmap(start: usize, len: usize, prot: usize) -> SysResult<usize> {
+    check(sys_mmap(start, len, prot))
+}
+
+pub fn munmap(start: usize, len: usize) -> SysResult<()> {
+    check(sys_munmap(start, len))?;
+    Ok(())
+}
+
+pub fn mprotect(start: usize, len: usize, prot: usize) -> SysResult<()> {
+    check(sys_mprotect(start, len, prot))?;
+    Ok(())
+}
+
+/// waitpid: 没有已结束的子进程时立即返回0
+pub const WNOHANG: usize = 1;
+/// waitpid: 同时报告已暂停的子进程
+pub const WUNTRACED: usize = 2;
+
+pub fn wait(exit_code: &mut i32) -> SysResult<usize> {
+    check(sys_waitpid(-1, exit_code as *mut _ as *mut _, 0))
+}
+
+/// 等待子进程，设置WNOHANG且子进程未结束时返回0
+pub fn waitpid(pid: usize, exit_code: &mut i32, options: usize) -> SysResult<usize> {
+    check(sys_waitpid(
+        pid as isize,
+        exit_code as *mut _ as *mut _,
+        options,
+    ))
+}
+
+/// 子进程是否正常退出
+pub fn wifexited(status: i32) -> bool {
+    status & 0x7f == 0
+}
+
+/// 正常退出时的退出码
+pub fn wexitstatus(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+
+/// 子进程是否因信号终止
+pub fn wifsignaled(status: i32) -> bool {
+    status & 0x7f != 0 && status & 0x7f != 0x7f
+}
+
+/// 导致子进程终止的信号
+pub fn wtermsig(status: i32) -> usize {
+    (status & 0x7f) as usize
+}
+
+/// 子进程是否处于暂停状态
+pub fn wifstopped(status: i32) -> bool {
+    status & 0xff == 0x7f
+}
+
+/// 导致子进程暂停的信号
+pub fn wstopsig(status: i32) -> usize {
+    ((status >> 8) & 0xff) as usize
 }
 
 pub fn getpid() -> isize {
     sys_getpid()
 }
 
-pub fn kill(pid: usize) -> isize {
-    sys_kill(pid)
+/// times中时间的单位（每秒的时钟数）
+pub const CLK_TCK: usize = 1000;
+
+/// 进程的CPU时间（与内核中定义一致）
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    /// 已回收的子进程的用户态时间
+    pub cutime: usize,
+    /// 已回收的子进程的内核态时间
+    pub cstime: usize,
 }
 
-pub fn sleep(ms: usize) {
-    let start_time = gettime();
+pub const PRIO_PROCESS: usize = 0;
+pub const PRIO_PGRP: usize = 1;
+
+/// 设置进程或进程组的nice值（-20~19），who为0时为当前进程或当前进程组
+pub fn setpriority(which: usize, who: usize, nice: isize) -> SysResult<()> {
+    check(sys_setpriority(which, who, nice))?;
+    Ok(())
+}
+
+/// 获取进程或进程组的nice值
+pub fn getpriority(which: usize, who: usize) -> SysResult<isize> {
+    Ok(20 - check(sys_getpriority(which, who))? as isize)
+}
+
+/// 获取本进程及已回收子进程的CPU时间，返回系统启动以来的时钟数
+pub fn times(tms: &mut Tms) -> SysResult<usize> {
+    check(sys_times(tms as *mut _ as *mut _))
+}
+
+/// 与内核中定义一致的时间结构（微秒精度）
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// 资源使用情况（与内核中定义一致）
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Rusage {
+    /// 用户态运行时间
+    pub utime: TimeVal,
+    /// 内核态运行时间
+    pub stime: TimeVal,
+}
+
+/// getrusage: 本进程
+pub const RUSAGE_SELF: isize = 0;
+/// getrusage: 已回收的子进程
+pub const RUSAGE_CHILDREN: isize = -1;
+
+pub fn getrusage(who: isize, usage: &mut Rusage) -> SysResult<()> {
+    check(sys_getrusage(who, usage as *mut _ as *mut _))?;
+    Ok(())
+}
+
+/// 设置进程所属的进程组，pid为0时为当前进程，pgid为0时以pid作为进程组号
+pub fn setpgid(pid: usize, pgid: usize) -> SysResult<()> {
+    check(sys_setpgid(pid, pgid))?;
+    Ok(())
+}
+
+/// 获取进程所属的进程组，pid为0时为当前进程
+pub fn getpgid(pid: usize) -> SysResult<usize> {
+    check(sys_getpgid(pid))
+}
+
+/// 创建新会话并成为其首进程，返回会话号
+pub fn setsid() -> SysResult<usize> {
+    check(sys_setsid())
+}
+
+/// ioctl: 获取终端的前台进程组
+const TIOCGPGRP: usize = 0x540f;
+/// ioctl: 设置终端的前台进程组
+const TIOCSPGRP: usize = 0x5410;
+
+/// 获取终端的前台进程组
+pub fn tcgetpgrp(fd: usize) -> SysResult<usize> {
+    let mut pgid = 0usize;
+    check(sys_ioctl(fd, TIOCGPGRP, &mut pgid as *mut _ as *mut _))?;
+    Ok(pgid)
+}
+
+/// 设置终端的前台进程组，只有前台进程组能读取终端并收到^C、^Z产生的信号
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> SysResult<()> {
+    let mut pgid = pgid;
+    check(sys_ioctl(fd, TIOCSPGRP, &mut pgid as *mut _ as *mut _))?;
+    Ok(())
+}
+
+/// 创建线程，从entry(arg)开始执行，线程需调用exit结束
+pub fn thread_create(entry: usize, arg: usize) -> SysResult<usize> {
+    check(sys_thread_create(entry, arg))
+}
+
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+
+/// 等待线程结束，返回其退出码
+pub fn waittid(tid: usize) -> SysResult<i32> {
+    let mut exit_code = 0i32;
+    check(sys_waittid(tid, &mut exit_code as *mut _ as *mut _))?;
+    Ok(exit_code)
+}
+
+pub fn mutex_create() -> SysResult<usize> {
+    check(sys_mutex_create())
+}
+
+/// 加锁，被信号打断时在信号处理后重试
+pub fn mutex_lock(mutex_id: usize) -> SysResult<()> {
     loop {
-        let curr_time = gettime();
-        if start_time + ms >= curr_time {
-            r#yield();
-        } else {
-            break;
+        match check(sys_mutex_lock(mutex_id)) {
+            Err(EINTR) => continue,
+            ret => return ret.map(|_| ()),
+        }
+    }
+}
+
+pub fn mutex_unlock(mutex_id: usize) -> SysResult<()> {
+    check(sys_mutex_unlock(mutex_id))?;
+    Ok(())
+}
+
+pub fn semaphore_create(count: usize) -> SysResult<usize> {
+    check(sys_semaphore_create(count))
+}
+
+pub fn semaphore_up(sem_id: usize) -> SysResult<()> {
+    check(sys_semaphore_up(sem_id))?;
+    Ok(())
+}
+
+/// P操作，被信号打断时在信号处理后重试
+pub fn semaphore_down(sem_id: usize) -> SysResult<()> {
+    loop {
+        match check(sys_semaphore_down(sem_id)) {
+            Err(EINTR) => continue,
+            ret => return ret.map(|_| ()),
         }
     }
 }
 
-pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
-    sys_dup2(old_fd, new_fd)
+pub fn condvar_create() -> SysResult<usize> {
+    check(sys_condvar_create())
+}
+
+pub fn condvar_signal(condvar_id: usize) -> SysResult<()> {
+    check(sys_condvar_signal(condvar_id))?;
+    Ok(())
+}
+
+/// 等待条件变量，返回时总是持有mutex_id对应的锁（可能是虚假唤醒）
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult<()> {
+    match check(sys_condvar_wait(condvar_id, mutex_id)) {
+        Err(EINTR) => mutex_lock(mutex_id),
+        ret => ret.map(|_| ()),
+    }
+}
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGSTKFLT: usize = 16;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
+pub const SIGVTALRM: usize = 26;
+pub const SIGPROF: usize = 27;
+pub const SIGWINCH: usize = 28;
+pub const SIGIO: usize = 29;
+pub const SIGPWR: usize = 30;
+pub const SIGSYS: usize = 31;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// 信号处理方式（与内核中的定义保持一致）
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: u32,
+}
+
+impl SignalAction {
+    /// 使用handler处理信号，处理期间额外屏蔽mask中的信号
+    pub fn new(handler: fn(usize), mask: u32) -> Self {
+        Self {
+            handler: handler as usize,
+            restorer: sig_restorer as usize,
+            mask,
+        }
+    }
+
+    /// 忽略信号
+    pub fn ignore() -> Self {
+        Self {
+            handler: SIG_IGN,
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+impl Default for SignalAction {
+    /// 默认处理方式
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+/// 信号处理函数返回后跳转至此，恢复被中断的现场
+fn sig_restorer() -> ! {
+    sys_sigreturn();
+    unreachable!();
+}
+
+pub fn kill(pid: usize, signum: usize) -> SysResult<()> {
+    check(sys_kill(pid, signum))?;
+    Ok(())
+}
+
+/// 向进程组中的全部进程发送信号
+pub fn killpg(pgid: usize, signum: usize) -> SysResult<()> {
+    check(sys_kill(-(pgid as isize) as usize, signum))?;
+    Ok(())
+}
+
+pub fn sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> SysResult<()> {
+    check(sys_sigaction(
+        signum,
+        action.map_or(core::ptr::null(), |action| action as *const _ as *const _),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut _ as *mut _),
+    ))?;
+    Ok(())
+}
+
+/// 修改信号屏蔽集合，返回原屏蔽集合
+pub fn sigprocmask(how: usize, set: u32) -> SysResult<u32> {
+    check(sys_sigprocmask(how, set)).map(|mask| mask as u32)
+}
+
+pub fn sigreturn() -> isize {
+    sys_sigreturn()
+}
+
+/// 睡眠ms毫秒，被信号打断时提前返回
+pub fn sleep(ms: usize) {
+    let req = TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1_000_000,
+    };
+    nanosleep(&req, None).ok();
+}
+
+pub fn dup2(old_fd: usize, new_fd: usize) -> SysResult<()> {
+    check(sys_dup2(old_fd, new_fd))?;
+    Ok(())
 }
@@ -2,8 +2,11 @@ use core::arch::asm;
 
 const SYS_CALL_GETCWD: usize = 17;
 const SYS_CALL_DUP2: usize = 24;
+const SYS_CALL_IOCTL: usize = 29;
 const SYS_CALL_MKDIR: usize = 34;
 const SYS_CALL_UNLINK: usize = 35;
+const SYS_CALL_UMOUNT2: usize = 39;
+const SYS_CALL_MOUNT: usize = 40;
 const SYS_CALL_CHDIR: usize = 49;
 const SYS_CALL_OPEN: usize = 56;
 const SYS_CALL_CLOSE: usize = 57;
@@ -13,15 +16,47 @@ const SYS_CALL_READ: usize = 63;
 const SYS_CALL_WRITE: usize = 64;
 const SYS_CALL_FSTAT: usize = 80;
 const SYS_CALL_EXIT: usize = 93;
+const SYS_CALL_NANOSLEEP: usize = 101;
 const SYS_CALL_YIELD: usize = 124;
 const SYS_CALL_KILL: usize = 129;
+const SYS_CALL_SIGACTION: usize = 134;
+const SYS_CALL_SIGPROCMASK: usize = 135;
+const SYS_CALL_SIGRETURN: usize = 139;
+const SYS_CALL_SETPRIORITY: usize = 140;
+const SYS_CALL_GETPRIORITY: usize = 141;
+const SYS_CALL_TIMES: usize = 153;
+const SYS_CALL_SETPGID: usize = 154;
+const SYS_CALL_GETPGID: usize = 155;
+const SYS_CALL_SETSID: usize = 157;
+const SYS_CALL_GETRUSAGE: usize = 165;
 const SYS_CALL_GETTIME: usize = 169;
 const SYS_CALL_GETPID: usize = 172;
+const SYS_CALL_GETTID: usize = 178;
+const SYS_CALL_BRK: usize = 214;
+const SYS_CALL_MUNMAP: usize = 215;
 const SYS_CALL_FORK: usize = 220;
 const SYS_CALL_EXEC: usize = 221;
+const SYS_CALL_MMAP: usize = 222;
+const SYS_CALL_MPROTECT: usize = 226;
 const SYS_CALL_WAITPID: usize = 260;
+const SYS_CALL_THREAD_CREATE: usize = 1000;
+const SYS_CALL_WAITTID: usize = 1002;
+const SYS_CALL_MUTEX_CREATE: usize = 1010;
+const SYS_CALL_MUTEX_LOCK: usize = 1011;
+const SYS_CALL_MUTEX_UNLOCK: usize = 1012;
+const SYS_CALL_SEMAPHORE_CREATE: usize = 1020;
+const SYS_CALL_SEMAPHORE_UP: usize = 1021;
+const SYS_CALL_SEMAPHORE_DOWN: usize = 1022;
+const SYS_CALL_CONDVAR_CREATE: usize = 1030;
+const SYS_CALL_CONDVAR_SIGNAL: usize = 1031;
+const SYS_CALL_CONDVAR_WAIT: usize = 1032;
 
 fn sys_call(id: usize, args: [usize; 3]) -> isize {
+    sys_call5(id, [args[0], args[1], args[2], 0, 0])
+}
+
+/// 需要五个参数的系统调用
+fn sys_call5(id: usize, args: [usize; 5]) -> isize {
     let mut ret: isize;
     unsafe {
         asm!(
@@ -29,6 +64,8 @@ fn sys_call(id: usize, args: [usize; 3]) -> isize {
             inlateout("x10") args[0] => ret,
             in("x11") args[1],
             in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
             in("x17") id
         );
     }
@@ -58,6 +95,10 @@ pub fn sys_gettime() -> isize {
     sys_call(SYS_CALL_GETTIME, [0, 0, 0])
 }
 
+pub fn sys_nanosleep(req: *const u8, rem: *mut u8) -> isize {
+    sys_call(SYS_CALL_NANOSLEEP, [req as usize, rem as usize, 0])
+}
+
 pub fn sys_getcwd(buf: &mut [u8]) -> isize {
     sys_call(SYS_CALL_GETCWD, [buf.as_ptr() as usize, buf.len(), 0])
 }
@@ -91,6 +132,29 @@ pub fn sys_unlink(path: *const u8, flags: u32) -> isize {
     sys_call(SYS_CALL_UNLINK, [path as usize, flags as usize, 0])
 }
 
+pub fn sys_umount(target: *const u8, flags: u32) -> isize {
+    sys_call(SYS_CALL_UMOUNT2, [target as usize, flags as usize, 0])
+}
+
+pub fn sys_mount(
+    source: *const u8,
+    target: *const u8,
+    fs_type: *const u8,
+    flags: u32,
+    data: *const u8,
+) -> isize {
+    sys_call5(
+        SYS_CALL_MOUNT,
+        [
+            source as usize,
+            target as usize,
+            fs_type as usize,
+            flags as usize,
+            data as usize,
+        ],
+    )
+}
+
 pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
     sys_call(SYS_CALL_FSTAT, [fd, stat as usize, 0])
 }
@@ -99,22 +163,136 @@ pub fn sys_fork() -> isize {
     sys_call(SYS_CALL_FORK, [0, 0, 0])
 }
 
-pub fn sys_exec(path: *const u8, arg_ptrs_ptr: *const *const u8) -> isize {
-    sys_call(SYS_CALL_EXEC, [path as usize, arg_ptrs_ptr as usize, 0])
+pub fn sys_exec(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> isize {
+    sys_call(SYS_CALL_EXEC, [path as usize, argv as usize, envp as usize])
+}
+
+pub fn sys_brk(addr: usize) -> isize {
+    sys_call(SYS_CALL_BRK, [addr, 0, 0])
+}
+
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    sys_call(SYS_CALL_MMAP, [start, len, prot])
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    sys_call(SYS_CALL_MUNMAP, [start, len, 0])
+}
+
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_call(SYS_CALL_MPROTECT, [start, len, prot])
 }
 
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut u8) -> isize {
-    sys_call(SYS_CALL_WAITPID, [pid as usize, exit_code_ptr as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut u8, options: usize) -> isize {
+    sys_call(
+        SYS_CALL_WAITPID,
+        [pid as usize, exit_code_ptr as usize, options],
+    )
 }
 
 pub fn sys_getpid() -> isize {
     sys_call(SYS_CALL_GETPID, [0, 0, 0])
 }
 
-pub fn sys_kill(pid: usize) -> isize {
-    sys_call(SYS_CALL_KILL, [pid, 0, 0])
+pub fn sys_gettid() -> isize {
+    sys_call(SYS_CALL_GETTID, [0, 0, 0])
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    sys_call(SYS_CALL_THREAD_CREATE, [entry, arg, 0])
+}
+
+pub fn sys_waittid(tid: usize, exit_code: *mut u8) -> isize {
+    sys_call(SYS_CALL_WAITTID, [tid, exit_code as usize, 0])
+}
+
+pub fn sys_mutex_create() -> isize {
+    sys_call(SYS_CALL_MUTEX_CREATE, [0, 0, 0])
+}
+
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    sys_call(SYS_CALL_MUTEX_LOCK, [mutex_id, 0, 0])
+}
+
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    sys_call(SYS_CALL_MUTEX_UNLOCK, [mutex_id, 0, 0])
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    sys_call(SYS_CALL_SEMAPHORE_CREATE, [count, 0, 0])
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    sys_call(SYS_CALL_SEMAPHORE_UP, [sem_id, 0, 0])
+}
+
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    sys_call(SYS_CALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
+}
+
+pub fn sys_condvar_create() -> isize {
+    sys_call(SYS_CALL_CONDVAR_CREATE, [0, 0, 0])
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    sys_call(SYS_CALL_CONDVAR_SIGNAL, [condvar_id, 0, 0])
+}
+
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    sys_call(SYS_CALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0])
+}
+
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    sys_call(SYS_CALL_KILL, [pid, signum, 0])
+}
+
+pub fn sys_sigaction(signum: usize, action: *const u8, old_action: *mut u8) -> isize {
+    sys_call(
+        SYS_CALL_SIGACTION,
+        [signum, action as usize, old_action as usize],
+    )
+}
+
+pub fn sys_sigprocmask(how: usize, set: u32) -> isize {
+    sys_call(SYS_CALL_SIGPROCMASK, [how, set as usize, 0])
+}
+
+pub fn sys_sigreturn() -> isize {
+    sys_call(SYS_CALL_SIGRETURN, [0, 0, 0])
 }
 
 pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
     sys_call(SYS_CALL_DUP2, [old_fd, new_fd, 0])
 }
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: *mut u8) -> isize {
+    sys_call(SYS_CALL_IOCTL, [fd, cmd, arg as usize])
+}
+
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    sys_call(SYS_CALL_SETPGID, [pid, pgid, 0])
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    sys_call(SYS_CALL_GETPGID, [pid, 0, 0])
+}
+
+pub fn sys_setsid() -> isize {
+    sys_call(SYS_CALL_SETSID, [0, 0, 0])
+}
+
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    sys_call(SYS_CALL_SETPRIORITY, [which, who, nice as usize])
+}
+
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    sys_call(SYS_CALL_GETPRIORITY, [which, who, 0])
+}
+
+pub fn sys_times(buf: *mut u8) -> isize {
+    sys_call(SYS_CALL_TIMES, [buf as usize, 0, 0])
+}
+
+pub fn sys_getrusage(who: isize, usage: *mut u8) -> isize {
+    sys_call(SYS_CALL_GETRUSAGE, [who as usize, usage as usize, 0])
+}